@@ -1,28 +1,10 @@
 use fast_paillier::utils;
 use rug::Integer;
 
-/// Safe 1536 bit prime number in hex encoding
-const P: &str = "e84f454a8dd9e923fc85be8ca09278e28c5a3d9419cf118ef56912910f364c5\
-                 29d999dba2837e55d413827ccf97a4b6c49addd56f079032164d487fbd22d5e\
-                 a9ff0c8fdc6bce1b878a7109f33061874f310ae35ac75db3ac3fd5f49d8b85b\
-                 8823f05fc288602abf6a4ef641a3766a44d7ecbceebe3bf144a582639b55658\
-                 e93cc57445715ce83c0e7088ec701ded2bcbd2e91a68cb26b1aaddadf99aeef\
-                 927fb82459a3805c232e36162cbea024a2fe7485b96eeb278d45016c622261b\
-                 3d3aa3";
-/// Safe 1536 bit prime number in hex encoding
-const Q: &str = "9461f6a273f4bdf08ce0b1071253e0688d622d6b714b407200fa709d964034c\
-                 1b84b97057a8dd48904a99e83f1cb4c94d6927ac6424b8028eefe6503336e03\
-                 1ff0d7379932b1f6fa457d8a1e4d9436c42df8ba86ad54cc83a708cd6385d4d\
-                 5cbf0c62f9f692f04e500726d5d41224e2ec88d48bd3d04c004c9a8e6ce23ee\
-                 fb54995d7b4473c021f8a72c06fe3ce6488e6b1b8ad51b635a853121f4285c0\
-                 c364aab061aea672cb6dd86cee08b63a5b3f1fc78f1712e1a333b2552471e5a\
-                 d8403f";
-
 fn encryption(c: &mut criterion::Criterion) {
     let mut rng = rand_dev::DevRng::new();
 
-    let p = Integer::from_str_radix(P, 16).unwrap();
-    let q = Integer::from_str_radix(Q, 16).unwrap();
+    let (p, q) = fast_paillier::test_vectors::safe_primes_1536();
 
     let dk: fast_paillier::DecryptionKey =
         fast_paillier::DecryptionKey::from_primes(p.clone(), q.clone()).unwrap();
@@ -31,11 +13,7 @@ fn encryption(c: &mut criterion::Criterion) {
     let mut group = c.benchmark_group("Encrypt");
 
     let mut generate_inputs = || {
-        let x = ek
-            .n()
-            .clone()
-            .random_below(&mut fast_paillier::utils::external_rand(&mut rng))
-            - ek.half_n();
+        let x = fast_paillier::utils::random_in_signed_group(&mut rng, ek);
         let nonce = fast_paillier::utils::sample_in_mult_group(&mut rng, ek.n());
         (x, nonce)
     };
@@ -47,6 +25,13 @@ fn encryption(c: &mut criterion::Criterion) {
             criterion::BatchSize::SmallInput,
         )
     });
+    group.bench_function("Regular Encrypt without validation", |b| {
+        b.iter_batched(
+            &mut generate_inputs,
+            |(x, nonce)| ek.encrypt_with_unchecked(&x, &nonce).unwrap(),
+            criterion::BatchSize::SmallInput,
+        )
+    });
     group.bench_function("Encrypt with known factorization", |b| {
         b.iter_batched(
             &mut generate_inputs,
@@ -55,6 +40,14 @@ fn encryption(c: &mut criterion::Criterion) {
         )
     });
 
+    group.bench_function("Encrypt with known factorization without validation", |b| {
+        b.iter_batched(
+            &mut generate_inputs,
+            |(x, nonce)| dk.encrypt_with_unchecked(&x, &nonce).unwrap(),
+            criterion::BatchSize::SmallInput,
+        )
+    });
+
     let p = convert_integer_to_unknown_order(&p);
     let q = convert_integer_to_unknown_order(&q);
     let dk = libpaillier::DecryptionKey::with_primes_unchecked(&p, &q).unwrap();
@@ -80,8 +73,7 @@ fn encryption(c: &mut criterion::Criterion) {
 fn decryption(c: &mut criterion::Criterion) {
     let mut rng = rand_dev::DevRng::new();
 
-    let p = Integer::from_str_radix(P, 16).unwrap();
-    let q = Integer::from_str_radix(Q, 16).unwrap();
+    let (p, q) = fast_paillier::test_vectors::safe_primes_1536();
 
     let dk = fast_paillier::DecryptionKey::from_primes(p.clone(), q.clone()).unwrap();
     let ek = dk.encryption_key();
@@ -119,8 +111,7 @@ fn decryption(c: &mut criterion::Criterion) {
 fn omul(c: &mut criterion::Criterion) {
     let mut rng = rand_dev::DevRng::new();
 
-    let p = Integer::from_str_radix(P, 16).unwrap();
-    let q = Integer::from_str_radix(Q, 16).unwrap();
+    let (p, q) = fast_paillier::test_vectors::safe_primes_1536();
 
     let dk = fast_paillier::DecryptionKey::from_primes(p.clone(), q.clone()).unwrap();
     let ek = dk.encryption_key();
@@ -130,7 +121,7 @@ fn omul(c: &mut criterion::Criterion) {
     let mut generate_inputs = || {
         let scalar = ek
             .nn()
-            .random_below_ref(&mut utils::external_rand(&mut rng))
+            .random_below_ref(&mut utils::ExternalRand64::new(&mut rng).rand_state())
             .into();
         let enc_x = utils::sample_in_mult_group(&mut rng, ek.nn());
         (scalar, enc_x)
@@ -152,7 +143,49 @@ fn omul(c: &mut criterion::Criterion) {
     });
 }
 
+fn odot(c: &mut criterion::Criterion) {
+    let mut rng = rand_dev::DevRng::new();
+
+    let (p, q) = fast_paillier::test_vectors::safe_primes_1536();
+
+    let dk = fast_paillier::DecryptionKey::from_primes(p.clone(), q.clone()).unwrap();
+    let ek = dk.encryption_key();
+
+    let mut group = c.benchmark_group("ODot");
+
+    let ciphertexts: Vec<_> = (0..16)
+        .map(|_| utils::sample_in_mult_group(&mut rng, ek.nn()))
+        .collect();
+    let prepared = ek.prepare_odot(&ciphertexts).unwrap();
+
+    let mut generate_scalars = || -> Vec<Integer> {
+        (0..16)
+            .map(|_| {
+                ek.n()
+                    .random_below_ref(&mut utils::ExternalRand64::new(&mut rng).rand_state())
+                    .into()
+            })
+            .collect()
+    };
+
+    group.bench_function("16 ciphertexts", |b| {
+        b.iter_batched(
+            &mut generate_scalars,
+            |scalars| ek.odot(&scalars, &ciphertexts).unwrap(),
+            criterion::BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("16 prepared ciphertexts", |b| {
+        b.iter_batched(
+            &mut generate_scalars,
+            |scalars| prepared.odot(&scalars).unwrap(),
+            criterion::BatchSize::SmallInput,
+        )
+    });
+}
+
 /// Old implementation of safe primes
+#[allow(deprecated)]
 pub fn naive_safe_prime(rng: &mut impl rand_core::RngCore, bits: u32) -> Integer {
     use rug::{integer::IsPrime, Assign};
     let mut rng = utils::external_rand(rng);
@@ -184,6 +217,25 @@ fn safe_primes(c: &mut criterion::Criterion) {
         group.bench_function(id("Current"), |b| {
             b.iter(|| utils::generate_safe_prime(&mut rng.clone(), bits))
         });
+        group.bench_function(id("Incremental search"), |b| {
+            b.iter(|| {
+                utils::sieve_generate_safe_primes_incremental(
+                    &mut rng.clone(),
+                    bits,
+                    135,
+                    utils::SAFE_PRIME_SEARCH_WINDOW,
+                )
+            })
+        });
+        group.bench_function(id("Trial with auto-selected sieve"), |b| {
+            b.iter(|| {
+                utils::sieve_generate_safe_primes(
+                    &mut rng.clone(),
+                    bits,
+                    utils::optimal_sieve_amount(bits),
+                )
+            })
+        });
         group.bench_function(id("Trial with sieve of 120 primes"), |b| {
             b.iter(|| utils::sieve_generate_safe_primes(&mut rng.clone(), bits, 120))
         });
@@ -196,6 +248,45 @@ fn safe_primes(c: &mut criterion::Criterion) {
     }
 }
 
+fn large_safe_primes(c: &mut criterion::Criterion) {
+    let rng = rand_dev::DevRng::new();
+
+    let mut group = c.benchmark_group("Large safe primes");
+    group.sample_size(10);
+    for bits in [1536, 2048] {
+        let id = |s| format!("{}/{}", bits, s);
+
+        group.bench_function(id("Trial with auto-selected sieve"), |b| {
+            b.iter(|| {
+                utils::sieve_generate_safe_primes(
+                    &mut rng.clone(),
+                    bits,
+                    utils::optimal_sieve_amount(bits),
+                )
+            })
+        });
+        group.bench_function(id("Windowed sieve"), |b| {
+            b.iter(|| utils::sieve_generate_safe_primes_windowed(&mut rng.clone(), bits, 1 << 16))
+        });
+    }
+}
+
+fn nonce_sampling(c: &mut criterion::Criterion) {
+    let mut rng = rand_dev::DevRng::new();
+
+    let (p, q) = fast_paillier::test_vectors::safe_primes_1536();
+    let n = p * q;
+
+    let mut group = c.benchmark_group("Nonce sampling");
+
+    group.bench_function("with GCD", |b| {
+        b.iter(|| utils::sample_in_mult_group(&mut rng, &n))
+    });
+    group.bench_function("without GCD", |b| {
+        b.iter(|| utils::sample_nonce_pq(&mut rng, &n))
+    });
+}
+
 fn rng_covertion(c: &mut criterion::Criterion) {
     let mut rng = rand_dev::DevRng::new();
 
@@ -203,11 +294,28 @@ fn rng_covertion(c: &mut criterion::Criterion) {
 
     group.bench_function("into GMP", |b| {
         b.iter(|| {
-            let mut gmp_rng = fast_paillier::utils::external_rand(std::hint::black_box(&mut rng));
+            let mut gmp_rng =
+                fast_paillier::utils::ExternalRand64::new(std::hint::black_box(&mut rng));
+            let mut gmp_rng = gmp_rng.rand_state();
             let dyn_rng: &mut dyn rug::rand::MutRandState = &mut gmp_rng;
             let _ = std::hint::black_box(dyn_rng);
         })
     });
+
+    group.bench_function("1536 random bits via 32-bit adapter", |b| {
+        b.iter(|| {
+            #[allow(deprecated)]
+            let mut gmp_rng = fast_paillier::utils::external_rand(&mut rng);
+            Integer::from(Integer::random_bits(1536, &mut gmp_rng))
+        })
+    });
+    group.bench_function("1536 random bits via 64-bit adapter", |b| {
+        b.iter(|| {
+            let mut gmp_rng = fast_paillier::utils::ExternalRand64::new(&mut rng);
+            let mut gmp_rng = gmp_rng.rand_state();
+            Integer::from(Integer::random_bits(1536, &mut gmp_rng))
+        })
+    });
 }
 
 criterion::criterion_group!(
@@ -215,7 +323,10 @@ criterion::criterion_group!(
     encryption,
     decryption,
     omul,
+    odot,
     safe_primes,
+    large_safe_primes,
+    nonce_sampling,
     rng_covertion
 );
 criterion::criterion_main!(benches);
@@ -0,0 +1,111 @@
+//! Measures effect of `pool` feature on encryption and decryption
+//!
+//! Prints amount of GMP allocations per operation, and measures time taken by the operation.
+//! Run the benchmark with and without the feature to compare:
+//!
+//! ```bash
+//! cargo bench --bench pool
+//! cargo bench --bench pool --features pool
+//! ```
+
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+use gmp_mpfr_sys::gmp;
+use rug::Integer;
+
+/// Amount of allocations and reallocations done by GMP
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+/// Memory functions that were used by GMP before we replaced them
+static ORIGINAL: OnceLock<(
+    gmp::allocate_function,
+    gmp::reallocate_function,
+    gmp::free_function,
+)> = OnceLock::new();
+
+extern "C" fn counting_alloc(size: usize) -> *mut c_void {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    let alloc = ORIGINAL.get().and_then(|f| f.0).unwrap();
+    alloc(size)
+}
+
+unsafe extern "C" fn counting_realloc(ptr: *mut c_void, old: usize, new: usize) -> *mut c_void {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    let realloc = ORIGINAL.get().and_then(|f| f.1).unwrap();
+    realloc(ptr, old, new)
+}
+
+unsafe extern "C" fn forwarding_free(ptr: *mut c_void, size: usize) {
+    let free = ORIGINAL.get().and_then(|f| f.2).unwrap();
+    free(ptr, size)
+}
+
+fn install_counting_allocator() {
+    ORIGINAL.get_or_init(|| {
+        let (mut alloc, mut realloc, mut free) = (None, None, None);
+        // Safety: replacement functions delegate to the original ones, so memory allocated
+        // before the replacement can still be reallocated and freed
+        unsafe {
+            gmp::get_memory_functions(&mut alloc, &mut realloc, &mut free);
+            gmp::set_memory_functions(
+                Some(counting_alloc),
+                Some(counting_realloc),
+                Some(forwarding_free),
+            );
+        }
+        (alloc, realloc, free)
+    });
+}
+
+/// Returns average amount of GMP allocations per call of `f`
+fn allocations_per_call(mut f: impl FnMut()) -> f64 {
+    const CALLS: u64 = 100;
+    // warm up, so the pool (if enabled) is populated
+    f();
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..CALLS {
+        f();
+    }
+    (ALLOCATIONS.load(Ordering::Relaxed) - before) as f64 / CALLS as f64
+}
+
+fn pool(c: &mut criterion::Criterion) {
+    install_counting_allocator();
+    let mut rng = rand_dev::DevRng::new();
+
+    let p = fast_paillier::utils::generate_safe_prime(&mut rng, 1536);
+    let q = fast_paillier::utils::generate_safe_prime(&mut rng, 1536);
+    let dk = fast_paillier::DecryptionKey::from_primes(p, q).unwrap();
+    let ek = dk.encryption_key();
+
+    let x = fast_paillier::utils::random_in_signed_group(&mut rng, ek);
+    let nonce = fast_paillier::utils::sample_in_mult_group(&mut rng, ek.n());
+    let ciphertext: Integer = ek.encrypt_with(&x, &nonce).unwrap();
+
+    let pool = if cfg!(feature = "pool") {
+        "with pool"
+    } else {
+        "without pool"
+    };
+    println!(
+        "GMP allocations per call ({pool}): encrypt = {}, encrypt with known factorization = {}, \
+         decrypt = {}",
+        allocations_per_call(|| drop(ek.encrypt_with(&x, &nonce).unwrap())),
+        allocations_per_call(|| drop(dk.encrypt_with(&x, &nonce).unwrap())),
+        allocations_per_call(|| drop(dk.decrypt(&ciphertext).unwrap())),
+    );
+
+    let mut group = c.benchmark_group(format!("Allocations ({pool})"));
+    group.bench_function("Encrypt", |b| {
+        b.iter(|| ek.encrypt_with(&x, &nonce).unwrap())
+    });
+    group.bench_function("Encrypt with known factorization", |b| {
+        b.iter(|| dk.encrypt_with(&x, &nonce).unwrap())
+    });
+    group.bench_function("Decrypt", |b| b.iter(|| dk.decrypt(&ciphertext).unwrap()));
+}
+
+criterion::criterion_group!(benches, pool);
+criterion::criterion_main!(benches);
@@ -0,0 +1,122 @@
+//! Finds optimal sieve parameter of safe prime generation for different bit sizes
+//!
+//! Timing the whole generation is too noisy to tell apart sieve parameters of close value: amount
+//! of trials needed to find a safe prime varies from run to run as much as the average itself.
+//! Instead, the benchmark measures the two costs the choice depends on:
+//!
+//! * `sieve`: time to reduce a candidate modulo one small prime
+//! * `test`: time of a primality test of a candidate that survived the sieve
+//!
+//! A candidate is rejected by small prime `s > 2` with probability `1/s`, so expected time
+//! spent on one candidate with sieve of `amount` primes can be computed exactly. Amount of
+//! candidates needed to find a safe prime doesn't depend on the sieve, so the amount minimizing
+//! time per candidate is the optimal one.
+//!
+//! The library sieves by at most 200 small primes. For every bit size, the benchmark prints the
+//! optimal amount within this limit, the time per candidate it gives relative to sieve of 135
+//! primes, and the optimal amount without the limit (searched up to 2000 primes) along with
+//! the time per candidate it would give relative to the limited optimum.
+//!
+//! ```bash
+//! cargo bench --bench sieve_amount
+//! ```
+
+use std::time::{Duration, Instant};
+
+use fast_paillier::utils::{GmpPrimalityTest, PrimalityTest};
+use rug::Integer;
+
+/// Same amount of Miller-Rabin rounds as used by the library
+const PRIMALITY_REPS: u32 = 25;
+/// Amount of small primes available for sieving in the library
+const MAX_AMOUNT: usize = 200;
+/// Amount of small primes considered when searching for optimum without the limit
+const EXTENDED_AMOUNT: usize = 2000;
+
+fn small_primes() -> Vec<u32> {
+    let mut primes = Vec::with_capacity(EXTENDED_AMOUNT);
+    let mut p = Integer::from(1);
+    while primes.len() < EXTENDED_AMOUNT {
+        p.next_prime_mut();
+        primes.push(p.to_u32().unwrap());
+    }
+    primes
+}
+
+/// Returns a random odd candidate of given bit length, same as the ones generated by the library
+fn candidate(rng: &mut rug::rand::RandState, bits: u32) -> Integer {
+    let mut x = Integer::from(Integer::random_bits(bits - 1, rng));
+    x.set_bit(bits - 2, true);
+    x |= 1u32;
+    x
+}
+
+fn survives_sieve(x: &Integer, primes: &[u32]) -> bool {
+    primes.iter().all(|&s| x.mod_u(s) != (s - 1) / 2)
+}
+
+/// Average time per call of `f` over calls taking at least `budget` in total
+fn time_per_call(budget: Duration, mut f: impl FnMut(u64)) -> Duration {
+    let start = Instant::now();
+    let mut calls = 0;
+    while start.elapsed() < budget {
+        f(calls);
+        calls += 1;
+    }
+    start.elapsed() / calls as u32
+}
+
+/// Expected time spent on a candidate when sieving with first `amount` primes
+fn expected_time(primes: &[u32], amount: usize, sieve: f64, test: f64) -> f64 {
+    let mut survival = 1.;
+    let mut time = 0.;
+    for &s in &primes[..amount] {
+        time += survival * sieve;
+        if s != 2 {
+            survival *= 1. - 1. / f64::from(s);
+        }
+    }
+    time + survival * test
+}
+
+fn main() {
+    let primes = small_primes();
+    let mut rng = rug::rand::RandState::new();
+    let budget = Duration::from_secs(5);
+
+    println!("bits  sieve, ns  test, us  optimum  vs 135  unlimited optimum  vs optimum");
+    for bits in [256, 512, 1024, 1536, 2048, 3072] {
+        let xs: Vec<_> = (0..100).map(|_| candidate(&mut rng, bits)).collect();
+        let sieve = time_per_call(budget, |i| {
+            let x = &xs[i as usize % xs.len()];
+            for &s in &primes[..MAX_AMOUNT] {
+                std::hint::black_box(x.mod_u(s));
+            }
+        }) / MAX_AMOUNT as u32;
+
+        let survivors: Vec<_> = std::iter::repeat_with(|| candidate(&mut rng, bits))
+            .filter(|x| survives_sieve(x, &primes[..MAX_AMOUNT]))
+            .take(1000)
+            .collect();
+        let test = time_per_call(budget, |i| {
+            let x = &survivors[i as usize % survivors.len()];
+            std::hint::black_box(GmpPrimalityTest.is_prime(x, PRIMALITY_REPS));
+        });
+
+        let (sieve, test) = (sieve.as_secs_f64(), test.as_secs_f64());
+        let time = |amount| expected_time(&primes, amount, sieve, test);
+        let optimum = |max| {
+            (1..=max)
+                .min_by(|&a, &b| time(a).total_cmp(&time(b)))
+                .unwrap()
+        };
+        let (optimal, unlimited) = (optimum(MAX_AMOUNT), optimum(EXTENDED_AMOUNT));
+        println!(
+            "{bits:>4}  {:>9.1}  {:>8.1}  {optimal:>7}  {:>6.3}  {unlimited:>17}  {:>10.3}",
+            sieve * 1e9,
+            test * 1e6,
+            time(optimal) / time(135),
+            time(unlimited) / time(optimal),
+        );
+    }
+}
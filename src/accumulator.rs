@@ -0,0 +1,236 @@
+use rand_core::{CryptoRng, RngCore};
+use rug::{Complete, Integer};
+
+use crate::{utils, Ciphertext, EncryptionKey};
+use crate::{Error, Reason};
+
+/// Size of running product (relative to size of `N^2`) after which it gets reduced modulo `N^2`
+const REDUCE_THRESHOLD: u32 = 4;
+
+/// Homomorphic accumulator of streamed ciphertexts
+///
+/// Computes `Enc(a1 + a2 + ... + ak)` given ciphertexts `Enc(a1), Enc(a2), ..., Enc(ak)`
+/// received one by one. Running product is reduced modulo `N^2` only once it becomes
+/// sufficiently large, which makes accumulation cheaper than calling
+/// [`oadd`](EncryptionKey::oadd) on each ciphertext.
+///
+/// ## Example
+/// ```rust
+/// # fn main() -> Result<(), fast_paillier::Error> {
+/// # let mut rng = rand_dev::DevRng::new();
+/// # let p = fast_paillier::utils::generate_safe_prime(&mut rng, 256);
+/// # let q = fast_paillier::utils::generate_safe_prime(&mut rng, 256);
+/// # let dk = fast_paillier::DecryptionKey::from_primes(p, q)?;
+/// use fast_paillier::SumAccumulator;
+/// use rug::Integer;
+///
+/// let ek = dk.encryption_key();
+/// let mut acc = SumAccumulator::new(ek);
+/// for x in 1..=10 {
+///     let c = ek.encrypt_with_random(&mut rng, &Integer::from(x))?.ciphertext;
+///     acc.push(&c)?;
+/// }
+/// let sum = acc.finish();
+/// assert_eq!(dk.decrypt(&sum)?, 55);
+/// # Ok(()) }
+/// ```
+///
+/// ## Re-randomization
+/// Randomness of the running total is a product of nonces of all pushed ciphertexts. In a
+/// long-lived aggregator, an observer who sees intermediate totals may link them to each other
+/// and to the inputs. Accumulator can be configured to multiply the total by a fresh `r^N` every
+/// `n` additions via [`rerandomize_every`](Self::rerandomize_every), which bounds the window
+/// in which the states are linkable.
+#[derive(Clone, Debug)]
+pub struct SumAccumulator<'k, R = NoRng> {
+    ek: &'k EncryptionKey,
+    acc: Integer,
+    rng: Option<R>,
+    /// Re-randomize every `rerandomize_every` pushes, `0` means never
+    rerandomize_every: u64,
+    /// Pushes since last re-randomization
+    pushed: u64,
+    rerandomizations: u64,
+}
+
+impl<'k> SumAccumulator<'k> {
+    /// Constructs an empty accumulator
+    pub fn new(ek: &'k EncryptionKey) -> Self {
+        Self {
+            ek,
+            acc: Integer::from(1),
+            rng: None,
+            rerandomize_every: 0,
+            pushed: 0,
+            rerandomizations: 0,
+        }
+    }
+
+    /// Makes accumulator re-randomize the running total every `n` additions
+    ///
+    /// Fresh nonces are sampled using `rng`. `n = 0` disables re-randomization.
+    pub fn rerandomize_every<R: RngCore + CryptoRng>(
+        self,
+        n: u64,
+        rng: R,
+    ) -> SumAccumulator<'k, R> {
+        SumAccumulator {
+            ek: self.ek,
+            acc: self.acc,
+            rng: Some(rng),
+            rerandomize_every: n,
+            pushed: self.pushed,
+            rerandomizations: self.rerandomizations,
+        }
+    }
+}
+
+impl<'k, R: RngCore + CryptoRng> SumAccumulator<'k, R> {
+    /// Adds ciphertext to the sum
+    ///
+    /// Returns error if ciphertext is not in `Z*_{N^2}`. In this case, accumulator remains unchanged.
+    pub fn push(&mut self, c: &Ciphertext) -> Result<(), Error> {
+        if !utils::in_mult_group(c, self.ek.nn()) {
+            return Err(Reason::Ops.into());
+        }
+        self.acc *= c;
+        self.pushed += 1;
+        if self.rerandomize_every != 0 && self.pushed >= self.rerandomize_every {
+            if let Some(rng) = &mut self.rng {
+                let nonce = utils::sample_in_mult_group(rng, self.ek.n());
+                self.acc *= self.ek.nonce_power(&nonce)?;
+                self.acc %= self.ek.nn();
+                self.pushed = 0;
+                self.rerandomizations += 1;
+            }
+        }
+        if self.acc.significant_bits() > REDUCE_THRESHOLD * self.ek.nn().significant_bits() {
+            self.acc %= self.ek.nn();
+        }
+        Ok(())
+    }
+
+    /// Amount of times the running total was re-randomized
+    pub fn rerandomizations(&self) -> u64 {
+        self.rerandomizations
+    }
+
+    /// Returns encryption of the sum of all pushed ciphertexts
+    ///
+    /// If no ciphertexts were pushed, returns `1` which is a (non-randomized) encryption of zero.
+    pub fn finish(self) -> Ciphertext {
+        self.acc % self.ek.nn()
+    }
+}
+
+/// Placeholder randomness source of [`SumAccumulator`] which doesn't re-randomize the total
+///
+/// It can not be constructed.
+#[derive(Clone, Debug)]
+pub enum NoRng {}
+
+impl RngCore for NoRng {
+    fn next_u32(&mut self) -> u32 {
+        match *self {}
+    }
+    fn next_u64(&mut self) -> u64 {
+        match *self {}
+    }
+    fn fill_bytes(&mut self, _dest: &mut [u8]) {
+        match *self {}
+    }
+    fn try_fill_bytes(&mut self, _dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        match *self {}
+    }
+}
+
+impl CryptoRng for NoRng {}
+
+/// Un-reduced product of ciphertexts
+///
+/// Multiplies ciphertexts without reducing the product modulo `N^2`. Reduction happens only once,
+/// in [`finalize`](Self::finalize), which yields `Enc(a1 + ... + ak)` given ciphertexts `Enc(a1)`,
+/// ..., `Enc(ak)`. Unlike [`SumAccumulator`], the product is never reduced implicitly, so it's up
+/// to the caller to keep its size in check (e.g. via [`bits`](Self::bits)). Ciphertexts are not
+/// validated until the product is finalized.
+///
+/// ## Example
+/// ```rust
+/// # fn main() -> Result<(), fast_paillier::Error> {
+/// # let mut rng = rand_dev::DevRng::new();
+/// # let p = fast_paillier::utils::generate_safe_prime(&mut rng, 256);
+/// # let q = fast_paillier::utils::generate_safe_prime(&mut rng, 256);
+/// # let dk = fast_paillier::DecryptionKey::from_primes(p, q)?;
+/// use fast_paillier::RawProduct;
+/// use rug::Integer;
+///
+/// let ek = dk.encryption_key();
+/// let mut product = RawProduct::new();
+/// for x in 1..=10 {
+///     let c = ek.encrypt_with_random(&mut rng, &Integer::from(x))?.ciphertext;
+///     product.mul(&c);
+/// }
+/// let sum = product.finalize(ek)?;
+/// assert_eq!(dk.decrypt(&sum)?, 55);
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug)]
+pub struct RawProduct {
+    product: Integer,
+    len: usize,
+}
+
+impl RawProduct {
+    /// Constructs an empty product
+    pub fn new() -> Self {
+        Self {
+            product: Integer::from(1),
+            len: 0,
+        }
+    }
+
+    /// Multiplies the product by a ciphertext, without reducing it
+    pub fn mul(&mut self, c: &Ciphertext) {
+        self.product *= c;
+        self.len += 1;
+    }
+
+    /// Returns un-reduced product
+    pub fn product(&self) -> &Integer {
+        &self.product
+    }
+
+    /// Returns size of un-reduced product in bits
+    pub fn bits(&self) -> u32 {
+        self.product.significant_bits()
+    }
+
+    /// Returns amount of multiplied ciphertexts
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks whether no ciphertexts were multiplied
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Reduces the product modulo `N^2`, returns encryption of the sum of all multiplied ciphertexts
+    ///
+    /// If no ciphertexts were multiplied, returns `1` which is a (non-randomized) encryption of
+    /// zero. Returns error if any of multiplied ciphertexts wasn't in `Z*_{N^2}`, i.e. if
+    /// the reduced product is not in `Z*_{N^2}`.
+    pub fn finalize(&self, ek: &EncryptionKey) -> Result<Ciphertext, Error> {
+        let c = self.product.modulo_ref(ek.nn()).complete();
+        if !utils::in_mult_group(&c, ek.nn()) {
+            return Err(Reason::Ops.into());
+        }
+        Ok(c)
+    }
+}
+
+impl Default for RawProduct {
+    fn default() -> Self {
+        Self::new()
+    }
+}
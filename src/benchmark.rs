@@ -0,0 +1,85 @@
+//! Runtime measurement of paillier throughput on the current machine
+
+use std::time::{Duration, Instant};
+
+use rand_core::{CryptoRng, RngCore};
+
+use crate::{utils, DecryptionKey, Error};
+
+/// Throughput of paillier operations measured by [`DecryptionKey::benchmark`]
+///
+/// Throughput is measured in operations per second on the machine and the thread that ran the
+/// benchmark.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct BenchmarkReport {
+    /// Amount of operations of each kind carried out
+    pub iterations: usize,
+    /// Encryptions per second, using known factorization of `N`
+    pub encrypt_per_sec: f64,
+    /// Decryptions per second
+    pub decrypt_per_sec: f64,
+    /// Homomorphic multiplications by scalar per second, using known factorization of `N`
+    pub omul_per_sec: f64,
+}
+
+impl DecryptionKey {
+    /// Measures throughput of encryption, decryption, and `omul` with this key
+    ///
+    /// Carries out `iterations` operations of each kind on random inputs sampled using `rng`,
+    /// and reports how many operations per second were done. It's meant for capacity planning at
+    /// runtime (e.g. to pick a batch size), as opposed to `criterion` benchmarks of the crate.
+    ///
+    /// Measurement is done in the calling thread and takes roughly as long as `iterations`
+    /// encryptions, decryptions and `omul`s. Small amount of iterations gives a noisy estimate.
+    /// If `iterations` is zero, one iteration is done.
+    pub fn benchmark(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        iterations: usize,
+    ) -> Result<BenchmarkReport, Error> {
+        let iterations = iterations.max(1);
+        let plaintexts: Vec<_> = (0..iterations)
+            .map(|_| utils::random_in_signed_group(rng, self.encryption_key()))
+            .collect();
+        let nonces: Vec<_> = (0..iterations)
+            .map(|_| utils::sample_in_mult_group(rng, self.n()))
+            .collect();
+        let scalars: Vec<_> = (0..iterations)
+            .map(|_| utils::sample_in_mult_group(rng, self.n()))
+            .collect();
+
+        let start = Instant::now();
+        let ciphertexts = plaintexts
+            .iter()
+            .zip(&nonces)
+            .map(|(x, nonce)| self.encrypt_with(x, nonce))
+            .collect::<Result<Vec<_>, _>>()?;
+        let encrypt = start.elapsed();
+
+        let start = Instant::now();
+        for c in &ciphertexts {
+            std::hint::black_box(self.decrypt(c)?);
+        }
+        let decrypt = start.elapsed();
+
+        let start = Instant::now();
+        for (scalar, c) in scalars.iter().zip(&ciphertexts) {
+            std::hint::black_box(self.omul(scalar, c)?);
+        }
+        let omul = start.elapsed();
+
+        Ok(BenchmarkReport {
+            iterations,
+            encrypt_per_sec: per_sec(iterations, encrypt),
+            decrypt_per_sec: per_sec(iterations, decrypt),
+            omul_per_sec: per_sec(iterations, omul),
+        })
+    }
+}
+
+fn per_sec(iterations: usize, elapsed: Duration) -> f64 {
+    // Clock resolution may be too coarse to measure very fast operations
+    let elapsed = elapsed.max(Duration::from_nanos(1));
+    iterations as f64 / elapsed.as_secs_f64()
+}
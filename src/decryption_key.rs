@@ -1,24 +1,50 @@
+use std::sync::OnceLock;
+
 use rand_core::{CryptoRng, RngCore};
 use rug::{Complete, Integer};
 
-use crate::{utils, Ciphertext, EncryptionKey, Nonce, Plaintext};
+use crate::{utils, Ciphertext, Encryption, EncryptionKey, Nonce, Plaintext};
 use crate::{Error, Reason};
 
+/// Tag that prefixes output of [`DecryptionKey::public_serialize`]
+pub(crate) const REDACTED_KEY_TAG: &[u8] = b"paillier-dk-redacted:";
+
 /// Paillier decryption key
+///
+/// Key is `Send + Sync`, so it can be shared across threads.
 #[derive(Clone)]
 pub struct DecryptionKey {
     ek: EncryptionKey,
-    /// `lcm(p-1, q-1)`
+    /// `lcm(p-1, q-1)` (or `lcm(p_1-1, ..., p_k-1)` for multi-prime key)
     lambda: Integer,
-    /// `lambda^-1 mod N`
-    mu: Integer,
+    /// `phi(N) = (p-1)(q-1)` (or `(p_1-1)...(p_k-1)` for multi-prime key)
+    phi: Integer,
 
-    p: Integer,
-    q: Integer,
+    /// Prime factors of `N`. Normally, it's `[p, q]`, but there might be more than two
+    /// factors in multi-prime setting.
+    primes: Vec<Integer>,
 
+    /// CRT for exponentiation modulo `N^2`, computed either at construction or on first use
+    crt: OnceLock<CrtData>,
+    /// CRT for exponentiation modulo `N`, built on first use by [`pow_mod_n`](Self::pow_mod_n)
+    crt_mod_n: OnceLock<utils::CrtExp>,
+    /// Values needed only for decryption, computed either at construction or on first use
+    decryption: OnceLock<DecryptionData>,
+}
+
+/// CRT state used by all operations that benefit from known factorization
+#[derive(Clone)]
+struct CrtData {
     crt_mod_nn: utils::CrtExp,
     /// Calculates `x ^ N mod N^2`. It's used for faster encryption
     exp_n: utils::Exponent,
+}
+
+/// Precomputations needed for decryption, but not for encryption
+#[derive(Clone)]
+struct DecryptionData {
+    /// `lambda^-1 mod N`
+    mu: Integer,
     /// Calculates `x ^ lambda mod N^2`. It's used for faster decryption
     exp_lambda: utils::Exponent,
 }
@@ -33,67 +59,459 @@ impl DecryptionKey {
         Self::from_primes(p, q)
     }
 
+    /// Generates a paillier key that satisfies additional security properties
+    ///
+    /// Samples two safe primes of `bits` bits each (so `N` is `2 * bits` bits long), and
+    /// regenerates them until all of the following properties hold:
+    ///
+    /// * `p` and `q` are safe primes, i.e. `(p-1)/2` and `(q-1)/2` are primes as well. It's
+    ///   guaranteed by construction.
+    /// * `gcd(N, phi(N)) = 1`. It's required by some protocols (e.g. proofs of correctness of
+    ///   paillier modulus), and is stricter than invertibility of `lambda` modulo `N` which is
+    ///   checked by [`from_primes`](Self::from_primes).
+    /// * `|p - q| > 2^(bits - 100)`. Primes that are too close to each other make `N` vulnerable
+    ///   to Fermat's factorization. The bound is the same as in FIPS 186-4 for RSA primes.
+    ///
+    /// For random safe primes of cryptographic size, these properties hold with overwhelming
+    /// probability, so regeneration is practically never needed.
+    pub fn generate_strong(rng: &mut (impl RngCore + CryptoRng), bits: u32) -> Result<Self, Error> {
+        let min_distance = Integer::from(1) << bits.saturating_sub(100);
+        loop {
+            let p = utils::generate_safe_prime(rng, bits);
+            let q = utils::generate_safe_prime(rng, bits);
+
+            if (&p - &q).complete().abs() <= min_distance {
+                continue;
+            }
+            let n = (&p * &q).complete();
+            let phi = (&p - 1u8).complete() * (&q - 1u8).complete();
+            if n.gcd(&phi) != 1 {
+                continue;
+            }
+
+            return Self::from_primes(p, q);
+        }
+    }
+
+    /// Generates a paillier key using randomness provided by operating system
+    ///
+    /// Same as [`DecryptionKey::generate`], but sources randomness via `getrandom`. Use
+    /// [`DecryptionKey::generate`] if you need control over randomness source, e.g. for
+    /// deterministic tests.
+    #[cfg(feature = "getrandom")]
+    pub fn generate_default() -> Result<Self, Error> {
+        Self::generate(&mut rand_core::OsRng)
+    }
+
+    /// Generates a paillier key, searching for primes incrementally
+    ///
+    /// Same as [`DecryptionKey::generate`], but primes are generated via
+    /// [`utils::sieve_generate_safe_primes_incremental`] which derives all candidates
+    /// from a single random draw. Refer to its documentation for latency characteristics.
+    pub fn generate_incremental(rng: &mut (impl RngCore + CryptoRng)) -> Result<Self, Error> {
+        let p = utils::sieve_generate_safe_primes_incremental(
+            rng,
+            1536,
+            utils::optimal_sieve_amount(1536),
+            utils::SAFE_PRIME_SEARCH_WINDOW,
+        );
+        let q = utils::sieve_generate_safe_primes_incremental(
+            rng,
+            1536,
+            utils::optimal_sieve_amount(1536),
+            utils::SAFE_PRIME_SEARCH_WINDOW,
+        );
+        Self::from_primes(p, q)
+    }
+
     /// Constructs a paillier key from primes `p`, `q`
     ///
     /// `p` and `q` need to be safe primes sufficiently large to meet security level requirements.
+    /// They can be given in any order: the key always stores the smaller prime as `p`, so
+    /// `from_primes(p, q)` and `from_primes(q, p)` result in identical keys.
     ///
     /// Returns error if `p` and `q` do not correspond to a valid paillier key.
-    #[allow(clippy::many_single_char_names)]
+    #[must_use = "constructed key is not used"]
     pub fn from_primes(p: Integer, q: Integer) -> Result<Self, Error> {
-        // Paillier doesn't work if p == q
-        if p == q {
-            return Err(Reason::InvalidPQ.into());
+        Self::from_multiprime(vec![p, q])
+    }
+
+    /// Constructs a paillier key from primes `p`, `q`, deferring decryption-specific precomputations
+    ///
+    /// Same as [`from_primes`](Self::from_primes), but `mu` and the exponent used for decryption
+    /// are computed on first use rather than at construction. It makes construction faster for
+    /// keys that are only used for fast encryption, and are never used for decryption.
+    #[must_use = "constructed key is not used"]
+    pub fn from_primes_lazy(p: Integer, q: Integer) -> Result<Self, Error> {
+        let primes = vec![p, q];
+        let n = primes.iter().product::<Integer>();
+        Self::from_multiprime_and_n_lazy(primes, n)
+    }
+
+    /// Constructs a paillier key from primes `p`, `q` and already known `N = p * q`
+    ///
+    /// Same as [`from_primes`](Self::from_primes), but uses the supplied `n`, which is handy when
+    /// `N` is stored alongside the primes. `n` is still checked to be equal to `p * q`.
+    ///
+    /// Returns error if `n != p * q`, or if `p` and `q` do not correspond to a valid paillier key.
+    #[must_use = "constructed key is not used"]
+    pub fn from_primes_and_n(p: Integer, q: Integer, n: Integer) -> Result<Self, Error> {
+        if (&p * &q).complete() != n {
+            return Err(Reason::InvalidN.into());
         }
-        let pm1 = Integer::from(&p - 1);
-        let qm1 = Integer::from(&q - 1);
-        let ek = EncryptionKey::from_n((&p * &q).complete());
-        let lambda = pm1.clone().lcm(&qm1);
-        if lambda.cmp0().is_eq() {
+        Self::from_multiprime_and_n(vec![p, q], n)
+    }
+
+    /// Constructs a multi-prime paillier key from primes `p_1, ..., p_k`
+    ///
+    /// Multi-prime modulus `N = p_1 * ... * p_k` makes decryption and other operations that benefit
+    /// from known factorization faster, as exponentiations are carried out modulo smaller factors.
+    ///
+    /// Primes need to be distinct safe primes of roughly equal size. At least two primes need to
+    /// be provided.
+    /// Primes can be given in any order, the key stores them in ascending order.
+    ///
+    /// ## Security
+    /// For a fixed size of `N`, each additional prime makes the factors smaller, and factoring
+    /// algorithms whose running time depends on the size of the smallest factor (such as ECM)
+    /// become more efficient. Each prime needs to be large enough to resist such attacks: as a rule
+    /// of thumb, don't use more than 3 primes for 3072-bits `N`.
+    ///
+    /// Returns error if primes do not correspond to a valid paillier key.
+    #[must_use = "constructed key is not used"]
+    pub fn from_multiprime(primes: Vec<Integer>) -> Result<Self, Error> {
+        let n = primes.iter().product::<Integer>();
+        Self::from_multiprime_and_n(primes, n)
+    }
+
+    /// Constructs a multi-prime key, `n` must be a product of `primes`
+    fn from_multiprime_and_n(primes: Vec<Integer>, n: Integer) -> Result<Self, Error> {
+        let key = Self::from_multiprime_and_n_lazy(primes, n)?;
+        key.decryption_data();
+        Ok(key)
+    }
+
+    /// Same as [`from_multiprime_and_n`](Self::from_multiprime_and_n), but doesn't compute
+    /// decryption-specific values
+    fn from_multiprime_and_n_lazy(primes: Vec<Integer>, n: Integer) -> Result<Self, Error> {
+        let key = Self::from_multiprime_and_n_deferred(primes, n)?;
+        let crt_mod_nn =
+            utils::CrtExp::try_build_nn_from_primes(&key.primes).map_err(Reason::BuildFastExp)?;
+        let exp_n = crt_mod_nn.prepare_exponent(key.n());
+        let _ = key.crt.set(CrtData { crt_mod_nn, exp_n });
+        Ok(key)
+    }
+
+    /// Constructs a multi-prime key deferring all precomputations until first use
+    ///
+    /// Used when deserializing keys: a service may deserialize many keys while using only a
+    /// few of them, so keys that are never used don't pay for building CRT. Only cheap checks
+    /// are done here, so that building CRT on first use cannot fail.
+    #[cfg(feature = "serde")]
+    pub(crate) fn from_multiprime_deferred(primes: Vec<Integer>) -> Result<Self, Error> {
+        let n = primes.iter().product::<Integer>();
+        Self::from_multiprime_and_n_deferred(primes, n)
+    }
+
+    /// Same as [`from_multiprime_deferred`](Self::from_multiprime_deferred), `n` must be a
+    /// product of `primes`
+    fn from_multiprime_and_n_deferred(mut primes: Vec<Integer>, n: Integer) -> Result<Self, Error> {
+        if primes.len() < 2 {
             return Err(Reason::InvalidPQ.into());
         }
+        // Paillier doesn't work if any of primes are equal
+        for (i, p) in primes.iter().enumerate() {
+            if primes[i + 1..].contains(p) {
+                return Err(Reason::InvalidPQ.into());
+            }
+        }
+
+        // Primes are stored in ascending order, so that keys constructed from the same primes
+        // given in different order are serialized identically
+        primes.sort();
 
-        // u = lambda^-1 mod N
-        let u = lambda.invert_ref(ek.n()).ok_or(Reason::InvalidPQ)?.into();
+        let ek = EncryptionKey::from_n(n);
+        let phi = primes
+            .iter()
+            .map(|p| (p - 1u8).complete())
+            .product::<Integer>();
+        let lambda = primes.iter().fold(Integer::from(1), |lambda, p| {
+            lambda.lcm(&(p - 1u8).complete())
+        });
+        // `mu = lambda^-1 mod N` is computed lazily, but we need to make sure that it exists
+        if lambda.cmp0().is_eq() || !utils::in_mult_group_abs(&lambda, ek.n()) {
+            return Err(Reason::InvalidPQ.into());
+        }
 
-        let crt_mod_nn = utils::CrtExp::build_nn(&p, &q).ok_or(Reason::BuildFastExp)?;
-        let exp_n = crt_mod_nn.prepare_exponent(ek.n());
-        let exp_lambda = crt_mod_nn.prepare_exponent(&lambda);
+        // Same conditions as checked when building `CrtExp`, so building it lazily cannot fail
+        for (i, p) in primes.iter().enumerate() {
+            if *p <= 1 {
+                return Err(Reason::BuildFastExp(utils::CrtBuildError::InvalidFactor).into());
+            }
+            if primes[i + 1..].iter().any(|q| p.gcd_ref(q).complete() != 1) {
+                return Err(Reason::BuildFastExp(utils::CrtBuildError::NotCoprime).into());
+            }
+        }
 
         Ok(Self {
             ek,
             lambda,
-            mu: u,
-            p,
-            q,
-            crt_mod_nn,
-            exp_n,
-            exp_lambda,
+            phi,
+            primes,
+            crt: OnceLock::new(),
+            crt_mod_n: OnceLock::new(),
+            decryption: OnceLock::new(),
+        })
+    }
+
+    fn crt(&self) -> &CrtData {
+        self.crt.get_or_init(|| {
+            // Primes are checked to be greater than 1 and pairwise coprime at construction
+            #[allow(clippy::expect_used)]
+            let crt_mod_nn = utils::CrtExp::try_build_nn_from_primes(&self.primes)
+                .expect("primes are checked at construction");
+            let exp_n = crt_mod_nn.prepare_exponent(self.n());
+            CrtData { crt_mod_nn, exp_n }
+        })
+    }
+
+    fn decryption_data(&self) -> &DecryptionData {
+        self.decryption.get_or_init(|| {
+            // `lambda` is checked to be invertible modulo `N` at construction
+            #[allow(clippy::expect_used)]
+            let mu = self
+                .lambda
+                .invert_ref(self.ek.n())
+                .expect("lambda is invertible modulo N")
+                .into();
+            DecryptionData {
+                mu,
+                exp_lambda: self.crt().crt_mod_nn.prepare_exponent(&self.lambda),
+            }
         })
     }
 
-    /// Decrypts the ciphertext, returns plaintext in `{-N/2, .., N_2}`
+    /// Parses a decryption key from bytes
+    ///
+    /// Bytes are expected to be in format produced by [`DecryptionKey::to_bytes`]:
+    /// `len(p) || p || len(q) || q` where `p` and `q` are encoded in big-endian and
+    /// their lengths are encoded as big-endian `u32`. Multi-prime keys are encoded
+    /// the same way, with each prime being length-prefixed.
+    ///
+    /// Returns error if bytes are malformed, or if primes do not correspond to a
+    /// valid paillier key. Those cases can be distinguished via [`Error::kind`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Self::from_multiprime(Self::parse_primes(bytes)?)
+    }
+
+    /// Parses primes from bytes in format produced by [`DecryptionKey::to_bytes`]
+    pub(crate) fn parse_primes(mut bytes: &[u8]) -> Result<Vec<Integer>, Error> {
+        fn read_integer(bytes: &[u8]) -> Option<(Integer, &[u8])> {
+            let (len, bytes) = bytes.split_first_chunk::<4>()?;
+            let len = usize::try_from(u32::from_be_bytes(*len)).ok()?;
+            if len == 0 || bytes.len() < len {
+                return None;
+            }
+            let (x, bytes) = bytes.split_at(len);
+            Some((Integer::from_digits(x, rug::integer::Order::Msf), bytes))
+        }
+
+        let mut primes = vec![];
+        while !bytes.is_empty() {
+            let (p, rest) = read_integer(bytes).ok_or(Reason::InvalidEncoding)?;
+            primes.push(p);
+            bytes = rest;
+        }
+        if primes.len() < 2 {
+            return Err(Reason::InvalidEncoding.into());
+        }
+        Ok(primes)
+    }
+
+    /// Serializes primes `p` and `q` into bytes
+    ///
+    /// Output can be parsed back via [`DecryptionKey::from_bytes`]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        for x in &self.primes {
+            let x = x.to_digits::<u8>(rug::integer::Order::Msf);
+            #[allow(clippy::expect_used)]
+            let len = u32::try_from(x.len()).expect("prime is too large");
+            bytes.extend_from_slice(&len.to_be_bytes());
+            bytes.extend_from_slice(&x);
+        }
+        bytes
+    }
+
+    /// Serializes the key into a recovery bundle protected by a checksum
+    ///
+    /// Bundle is [`to_bytes`](Self::to_bytes) output followed by its CRC-32 checksum encoded as
+    /// big-endian `u32`. It's meant for persisting the key, so that corruption of stored data is
+    /// detected by [`from_recovery_bundle`](Self::from_recovery_bundle). Checksum doesn't provide
+    /// any protection against intentional tampering.
+    pub fn to_recovery_bundle(&self) -> Vec<u8> {
+        let mut bytes = self.to_bytes();
+        let checksum = utils::crc32(&bytes);
+        bytes.extend_from_slice(&checksum.to_be_bytes());
+        bytes
+    }
+
+    /// Parses a key from a recovery bundle produced by [`to_recovery_bundle`](Self::to_recovery_bundle)
+    ///
+    /// Checksum is verified before the key is reconstructed. Returns error of
+    /// [`ErrorKind::Corrupted`](crate::ErrorKind::Corrupted) kind if checksum doesn't match.
+    pub fn from_recovery_bundle(bytes: &[u8]) -> Result<Self, Error> {
+        let (bytes, checksum) = bytes
+            .split_last_chunk::<4>()
+            .ok_or(Reason::CorruptedBundle)?;
+        if utils::crc32(bytes) != u32::from_be_bytes(*checksum) {
+            return Err(Reason::CorruptedBundle.into());
+        }
+        Self::from_bytes(bytes)
+    }
+
+    /// Serializes only the public part of the key
+    ///
+    /// Output contains `N` in big-endian bytes, prefixed by a tag marking that it's a redacted
+    /// decryption key. Primes are not included, so the output can be safely written, for instance,
+    /// into audit logs to record which key was used. It can be parsed back via
+    /// [`EncryptionKey::from_public_serialized`] which, naturally, yields an encryption key.
+    pub fn public_serialize(&self) -> Vec<u8> {
+        [REDACTED_KEY_TAG, &self.ek.to_bytes()].concat()
+    }
+
+    /// Decrypts the ciphertext, returns plaintext in `{-N/2, .., N/2}`
     pub fn decrypt(&self, c: &Ciphertext) -> Result<Plaintext, Error> {
+        let plaintext = self.decrypt_unsigned_raw(c)?;
+        Ok(utils::unsigned_to_signed(plaintext, self.n()))
+    }
+
+    /// Decrypts a batch of ciphertexts, aborts on the first invalid ciphertext
+    ///
+    /// Returns all plaintexts in the same order as ciphertexts. If any ciphertext is invalid,
+    /// returns error without decrypting the rest of the batch. Position of invalid ciphertext
+    /// can be obtained via [`Error::index`].
+    pub fn decrypt_all(&self, cs: &[Ciphertext]) -> Result<Vec<Plaintext>, Error> {
+        cs.iter()
+            .enumerate()
+            .map(|(i, c)| {
+                let plaintext = self
+                    .decrypt_unsigned_raw_inner(c)
+                    .ok_or(Reason::DecryptAt(i))?;
+                Ok(utils::unsigned_to_signed(plaintext, self.n()))
+            })
+            .collect()
+    }
+
+    /// Decrypts the ciphertext, returns plaintext as unsigned residue in `{0, .., N-1}`
+    ///
+    /// Unlike [`decrypt`](Self::decrypt), the plaintext is not mapped to `{-N/2, .., N/2}`.
+    /// [`utils::unsigned_to_signed`] can be used to obtain the same output as `decrypt`.
+    pub fn decrypt_unsigned_raw(&self, c: &Ciphertext) -> Result<Integer, Error> {
+        self.decrypt_unsigned_raw_inner(c)
+            .ok_or_else(|| Reason::Decrypt.into())
+    }
+
+    /// Decrypts the ciphertext, returns `None` if ciphertext is invalid
+    ///
+    /// Same as [`decrypt`](Self::decrypt), but doesn't construct an [`Error`] on failure. Can be
+    /// used in hot paths where failures are expected and their reason is not needed.
+    pub fn try_decrypt(&self, c: &Ciphertext) -> Option<Plaintext> {
+        let plaintext = self.decrypt_unsigned_raw_inner(c)?;
+        Some(utils::unsigned_to_signed(plaintext, self.n()))
+    }
+
+    fn decrypt_unsigned_raw_inner(&self, c: &Ciphertext) -> Option<Integer> {
         if !utils::in_mult_group(c, self.ek.nn()) {
-            return Err(Reason::Decrypt.into());
+            return None;
         }
 
+        let data = self.decryption_data();
+
         // a = c^\lambda mod n^2
-        let a = self
-            .crt_mod_nn
-            .exp(c, &self.exp_lambda)
-            .ok_or(Reason::Decrypt)?;
+        let mut a = self.crt().crt_mod_nn.exp(c, &data.exp_lambda)?;
 
-        // ell = L(a, N)
-        let l = self.ek.l(&a).ok_or(Reason::Decrypt)?;
+        // ell = L(a, N) = (a - 1) / N, computed in place
+        //
+        // `c` is in Z*_{N^2}, so `a` is in Z*_{N^2} as well. `c mod N` is in Z*_N, and `lambda`
+        // is the Carmichael exponent of Z*_N, i.e. `y^lambda = 1 mod N` for every `y` in Z*_N,
+        // so `a = 1 mod N`. No need to validate `a` once again.
+        a -= 1u8;
+        a /= self.ek.n();
 
         // m = lu = L(a)*u = L(c^\lamba*)u mod n
-        let plaintext = (l * &self.mu) % self.ek.n();
+        a *= &data.mu;
+        a %= self.ek.n();
+        Some(a)
+    }
 
-        if Integer::from(&plaintext << 1) >= *self.n() {
-            Ok(plaintext - self.n())
-        } else {
+    /// Decrypts the ciphertext, performing the same computation for valid and invalid ciphertexts
+    ///
+    /// Output is the same as [`decrypt`](Self::decrypt) produces. However, `decrypt` returns an error
+    /// as soon as it finds out that the ciphertext is invalid, so it takes much less time to process
+    /// an invalid ciphertext. When decryption is exposed as an oracle to an adversary (which is
+    /// typical for chosen-ciphertext attack scenarios), the difference in timing tells whether
+    /// the submitted ciphertext passed validation. `decrypt_uniform` carries out the whole
+    /// decryption regardless, and checks the validity only at the very end.
+    ///
+    /// Note that underlying big integer arithmetic is not constant-time, so this function only
+    /// removes the timing difference caused by early exit; timing may still depend on the values
+    /// being processed.
+    pub fn decrypt_uniform(&self, c: &Ciphertext) -> Result<Plaintext, Error> {
+        let is_valid = utils::in_mult_group(c, self.ek.nn());
+        let data = self.decryption_data();
+
+        // a = c^\lambda mod n^2
+        let a = self.crt().crt_mod_nn.exp(c, &data.exp_lambda);
+        let is_valid = is_valid & a.is_some();
+        let a = a.unwrap_or_default();
+
+        // ell = L(a, N), we don't validate `a` as it'd be the case in `decrypt`
+        let l = self.ek.l_unchecked(&a);
+
+        // m = lu = L(a)*u = L(c^\lamba*)u mod n
+        let plaintext = (l * &data.mu).modulo(self.ek.n());
+        let plaintext = utils::unsigned_to_signed(plaintext, self.n());
+
+        if is_valid {
             Ok(plaintext)
+        } else {
+            Err(Reason::Decrypt.into())
+        }
+    }
+
+    /// Decrypts the ciphertext produced by [`EncryptionKey::encrypt_bytes`]
+    ///
+    /// Returns exactly the bytes that were encrypted, including leading zeroes. Returns error if
+    /// ciphertext is invalid, or if plaintext doesn't start with `0x01` prefix added by
+    /// `encrypt_bytes`.
+    pub fn decrypt_bytes(&self, c: &Ciphertext) -> Result<Vec<u8>, Error> {
+        let x = self.decrypt_unsigned_raw(c)?;
+        let mut bytes = x.to_digits::<u8>(rug::integer::Order::Msf);
+        if bytes.first() != Some(&1) {
+            return Err(Reason::InvalidEncoding.into());
+        }
+        bytes.remove(0);
+        Ok(bytes)
+    }
+
+    /// Decrypts the ciphertext produced by
+    /// [`EncryptionKey::encrypt_with_context`](crate::EncryptionKey::encrypt_with_context)
+    ///
+    /// Returns error if ciphertext is invalid, or if it was encrypted with context different from
+    /// `expected_context`.
+    pub fn decrypt_with_context(
+        &self,
+        c: &Ciphertext,
+        expected_context: &Integer,
+    ) -> Result<Plaintext, Error> {
+        let packed = self.decrypt(c)?;
+        let context = packed.keep_bits_ref(EncryptionKey::CONTEXT_BITS).complete();
+        if context != *expected_context {
+            return Err(Reason::ContextMismatch.into());
         }
+        // `packed - context` is divisible by `2^CONTEXT_BITS`, so the shift is exact
+        Ok((packed - context) >> EncryptionKey::CONTEXT_BITS)
     }
 
     /// Encrypts a plaintext `x` in `{-N/2, .., N/2}` with `nonce` from `Z*_n`
@@ -101,43 +519,277 @@ impl DecryptionKey {
     /// It uses the fact that factorization of `N` is known to speed up encryption.
     ///
     /// Returns error if inputs are not in specified range
+    #[must_use = "encryption returns a new ciphertext"]
     pub fn encrypt_with(&self, x: &Plaintext, nonce: &Nonce) -> Result<Ciphertext, Error> {
         if !self.ek.in_signed_group(x) || !utils::in_mult_group(nonce, self.n()) {
             return Err(Reason::Encrypt.into());
         }
+        self.encrypt_with_unchecked(x, nonce)
+    }
 
-        let x = if x.cmp0().is_ge() {
-            x.clone()
-        } else {
-            (x + self.n()).complete()
-        };
-
-        // a = (1 + N)^x mod N^2 = (1 + xN) mod N^2
-        let a = (Integer::ONE + x * self.ek.n()) % self.ek.nn();
+    /// Encrypts the plaintext `x` with `nonce` without validating the inputs
+    ///
+    /// Same as [`encrypt_with`](Self::encrypt_with), but skips the range checks which saves
+    /// a GCD computation. Caller must ensure that `x` is in `{-N/2, .., N/2}` and `nonce` is in
+    /// `Z*_n` (e.g. it was sampled via [`utils::sample_in_mult_group`]), otherwise resulting
+    /// ciphertext is not a valid encryption of `x`.
+    #[must_use = "encryption returns a new ciphertext"]
+    pub fn encrypt_with_unchecked(
+        &self,
+        x: &Plaintext,
+        nonce: &Nonce,
+    ) -> Result<Ciphertext, Error> {
+        // a = (1 + N)^x mod N^2
+        let a = self.ek.encode_message_unchecked(x);
         // b = nonce^N mod N^2
         let b = self
+            .crt()
             .crt_mod_nn
-            .exp(nonce, &self.exp_n)
+            .exp(nonce, &self.crt().exp_n)
             .ok_or(Reason::Encrypt)?;
 
-        Ok((a * b) % self.ek.nn())
+        let mut c = a;
+        c *= &b;
+        crate::pool::give(b);
+        c %= self.ek.nn();
+        Ok(c)
     }
 
-    /// Encrypts the plaintext `x` in `{-N/2, .., N_2}`
+    /// Encrypts the plaintext `x` in `{-N/2, .., N/2}`
     ///
     /// It's uses the fact that factorization of `N` is known to speed up encryption.
     ///
-    /// Nonce is sampled randomly using `rng`.
+    /// Nonce is sampled randomly using `rng` via [`utils::sample_nonce_pq`]: as `N` is a product of
+    /// known large primes, nonce is in `Z*_N` except with negligible probability, so sampling
+    /// doesn't need to compute GCD.
     ///
     /// Returns error if plaintext is not in specified range
+    #[must_use = "encryption returns a new ciphertext"]
     pub fn encrypt_with_random(
         &self,
         rng: &mut (impl RngCore + CryptoRng),
         x: &Plaintext,
-    ) -> Result<(Ciphertext, Nonce), Error> {
+    ) -> Result<Encryption, Error> {
+        if !self.ek.in_signed_group(x) {
+            return Err(Reason::Encrypt.into());
+        }
+        let nonce = utils::sample_nonce_pq(rng, self.ek.n());
+        // nonce is in `Z*_n` except with negligible probability, no need to validate it
+        let ciphertext = self.encrypt_with_unchecked(x, &nonce)?;
+        Ok(Encryption { ciphertext, nonce })
+    }
+
+    /// Decrypts `c` and encrypts resulting plaintext with a fresh nonce
+    ///
+    /// Refreshed ciphertext is unlinkable to `c` and carries no history of homomorphic operations
+    /// that produced `c`. Returns refreshed ciphertext, the plaintext, and the nonce used to
+    /// encrypt it.
+    ///
+    /// Returns error if `c` is not a valid ciphertext
+    #[must_use = "refresh returns a new ciphertext"]
+    pub fn refresh(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        c: &Ciphertext,
+    ) -> Result<(Ciphertext, Plaintext, Nonce), Error> {
+        let x = self.decrypt(c)?;
+        let Encryption { ciphertext, nonce } = self.encrypt_with_random(rng, &x)?;
+        Ok((ciphertext, x, nonce))
+    }
+
+    /// Blinds a ciphertext preserving the sign of its plaintext
+    ///
+    /// Takes `c = Enc(m)` such that `|m| <= bound`, and returns `Enc(r * m + s)` where `r` is a
+    /// random positive scalar and `s` is a random shift in `{0, .., r - 1}`, re-randomized with a
+    /// fresh nonce. Decryption of the output is positive if `m > 0`, negative if `m < 0`, and
+    /// non-negative (but not necessarily zero) if `m = 0`. It's a building block for comparison:
+    /// blinding `osub(Enc(a), Enc(b))` and having the key holder decrypt the result tells whether
+    /// `a >= b` without revealing `a - b`.
+    ///
+    /// `r` is sampled from `{1, .., R}` where `R = floor((N/2) / (bound + 1))` is the largest value
+    /// for which the output doesn't wrap around `N`. Note that `bound` can not be checked
+    /// without decrypting, so it's up to the caller to make sure the bound is correct, otherwise
+    /// the sign is not preserved.
+    ///
+    /// ## Leakage
+    /// The decryptor learns the sign of `m` exactly. The magnitude of `m` is only hidden up to
+    /// a factor of `R`: decrypted `v` implies that `|m|` is roughly between `|v| / R` and `|v|`.
+    /// Smaller `bound` makes `R` larger and hides the magnitude better; with `bound` close to
+    /// `N/2`, `R = 1` and the plaintext is not blinded at all. The output distribution is not
+    /// uniform, so `blind_compare` must not be used where the magnitude needs to be hidden
+    /// statistically.
+    ///
+    /// Returns error if `bound` is negative or is too large (`R < 1`), or if ciphertext is invalid.
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
+    pub fn blind_compare(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        c: &Ciphertext,
+        bound: &Integer,
+    ) -> Result<Ciphertext, Error> {
+        if bound.is_negative() {
+            return Err(Reason::Ops.into());
+        }
+        let max_r = self.half_n() / (bound + 1u8).complete();
+        if max_r < 1 {
+            return Err(Reason::Ops.into());
+        }
+
+        // `r` must be invertible modulo `N` to be used in `omul`, which holds with overwhelming
+        // probability
+        let mut gmp_rng = utils::ExternalRand64::new(&mut *rng);
+        let mut gmp_rng = gmp_rng.rand_state();
+        let r = loop {
+            let r = Integer::from(max_r.random_below_ref(&mut gmp_rng)) + 1u8;
+            if utils::in_mult_group_abs(&r, self.n()) {
+                break r;
+            }
+        };
+        let s = Integer::from(r.random_below_ref(&mut gmp_rng));
+        drop(gmp_rng);
+
+        let scaled = self.omul(&r, c)?;
+        let shift = self.encrypt_with_random(rng, &s)?.ciphertext;
+        self.ek.oadd(&scaled, &shift)
+    }
+
+    /// Checks that the key works end-to-end
+    ///
+    /// Encrypts a random plaintext with the encryption key, checks that it decrypts back to the
+    /// same plaintext, and that encryption which uses the factorization of `N` gives the same
+    /// ciphertext. Valid key always passes the test, so it's meant as a health check after
+    /// importing key material.
+    ///
+    /// Returns error if any of the checks fail.
+    pub fn self_test(&self, rng: &mut (impl RngCore + CryptoRng)) -> Result<(), Error> {
+        let x = utils::random_in_signed_group(rng, &self.ek);
+        let Encryption { ciphertext, nonce } = self
+            .ek
+            .encrypt_with_random(rng, &x)
+            .map_err(|_| Reason::SelfTestFailed)?;
+        let decrypted = self.try_decrypt(&ciphertext);
+        let reencrypted = self.encrypt_with(&x, &nonce).ok();
+        if decrypted.as_ref() != Some(&x) || reencrypted.as_ref() != Some(&ciphertext) {
+            return Err(Reason::SelfTestFailed.into());
+        }
+        Ok(())
+    }
+
+    /// Checks that `c` is an encryption of `x` with `nonce`
+    ///
+    /// Re-encrypts `x` with `nonce` (using the fact that factorization of `N` is known to speed
+    /// up encryption) and compares the result with `c`. Since encryption output is canonical,
+    /// `c` must be in `{0, .., N^2 - 1}` to match.
+    ///
+    /// Returns error if `x` or `nonce` are not in the range accepted by
+    /// [`encrypt_with`](Self::encrypt_with).
+    pub fn verify_encryption(
+        &self,
+        c: &Ciphertext,
+        x: &Plaintext,
+        nonce: &Nonce,
+    ) -> Result<bool, Error> {
+        Ok(self.encrypt_with(x, nonce)? == *c)
+    }
+
+    /// Checks whether two ciphertexts encrypt the same plaintext
+    ///
+    /// Computes `osub(c1, c2)` and checks that it decrypts to zero, which is cheaper than
+    /// decrypting both ciphertexts and comparing the plaintexts.
+    ///
+    /// Returns error if any of ciphertexts is invalid
+    pub fn ciphertexts_encrypt_equal(
+        &self,
+        c1: &Ciphertext,
+        c2: &Ciphertext,
+    ) -> Result<bool, Error> {
+        let diff = self.ek.osub(c1, c2)?;
+        Ok(self.decrypt_unsigned_raw(&diff)?.is_zero())
+    }
+
+    /// Computes `nonce^N mod N^2`
+    ///
+    /// Same as [`EncryptionKey::nonce_power`], but uses the fact that factorization of `N` is
+    /// known to speed up the exponentiation.
+    ///
+    /// Returns error if `nonce` is not in `Z*_n`
+    pub fn nonce_power(&self, nonce: &Nonce) -> Result<Integer, Error> {
+        if !utils::in_mult_group(nonce, self.n()) {
+            return Err(Reason::Encrypt.into());
+        }
+        Ok(self
+            .crt()
+            .crt_mod_nn
+            .exp(nonce, &self.crt().exp_n)
+            .ok_or(Reason::Encrypt)?)
+    }
+
+    /// Computes `x^e mod N`
+    ///
+    /// Uses the fact that factorization of `N` is known to speed up the exponentiation. Data
+    /// needed for that is computed on the first call and cached in the key. Exponent `e` can be
+    /// negative, in which case the output is `(x^-1)^|e| mod N`.
+    ///
+    /// Returns error if `x` is not in `Z*_N`
+    pub fn pow_mod_n(&self, x: &Integer, e: &Integer) -> Result<Integer, Error> {
+        if !utils::in_mult_group_abs(x, self.n()) {
+            return Err(Reason::Ops.into());
+        }
+        let crt = self.crt_mod_n();
+        Ok(crt.exp(x, &crt.prepare_exponent(e)).ok_or(Reason::Ops)?)
+    }
+
+    /// Computes `x^e mod N^2`
+    ///
+    /// Uses the fact that factorization of `N` is known to speed up the exponentiation. Exponent
+    /// `e` can be negative, in which case the output is `(x^-1)^|e| mod N^2`.
+    ///
+    /// Returns error if `x` is not in `Z*_N`
+    pub fn pow_mod_nn(&self, x: &Integer, e: &Integer) -> Result<Integer, Error> {
+        if !utils::in_mult_group_abs(x, self.n()) {
+            return Err(Reason::Ops.into());
+        }
+        let e = self.crt().crt_mod_nn.prepare_exponent(e);
+        Ok(self.crt().crt_mod_nn.exp(x, &e).ok_or(Reason::Ops)?)
+    }
+
+    /// Computes `x^e mod N^2`
+    ///
+    /// Same as [`pow_mod_nn`](Self::pow_mod_nn)
+    ///
+    /// Returns error if `x` is not in `Z*_N`
+    pub fn exp_mod_nn(&self, x: &Integer, e: &Integer) -> Result<Integer, Error> {
+        self.pow_mod_nn(x, e)
+    }
+
+    pub(crate) fn crt_mod_n(&self) -> &utils::CrtExp {
+        self.crt_mod_n.get_or_init(|| {
+            let factors = self
+                .primes
+                .iter()
+                .map(|p| (p.clone(), (p - 1u8).complete()))
+                .collect();
+            // primes are distinct and greater than 1, which is checked at construction
+            #[allow(clippy::expect_used)]
+            utils::CrtExp::build_from_factors(factors).expect("primes are distinct")
+        })
+    }
+
+    /// Samples a nonce in `Z*_n` and computes `nonce^N mod N^2`
+    ///
+    /// It uses the fact that factorization of `N` is known to speed up computing the power.
+    /// Output can be used with [`EncryptionKey::encrypt_with_precomputed`].
+    pub fn sample_nonce_and_power(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+    ) -> Result<(Nonce, Integer), Error> {
         let nonce = utils::sample_in_mult_group(rng, self.ek.n());
-        let ciphertext = self.encrypt_with(x, &nonce)?;
-        Ok((ciphertext, nonce))
+        let nonce_pow_n = self
+            .crt()
+            .crt_mod_nn
+            .exp(&nonce, &self.crt().exp_n)
+            .ok_or(Reason::Encrypt)?;
+        Ok((nonce, nonce_pow_n))
     }
 
     /// Homomorphic multiplication of scalar at ciphertext
@@ -147,6 +799,7 @@ impl DecryptionKey {
     /// ```text
     /// omul(a, Enc(c)) = Enc(a * c)
     /// ```
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
     pub fn omul(&self, scalar: &Integer, ciphertext: &Ciphertext) -> Result<Ciphertext, Error> {
         if !utils::in_mult_group_abs(scalar, self.n())
             || !utils::in_mult_group(ciphertext, self.ek.nn())
@@ -154,8 +807,90 @@ impl DecryptionKey {
             return Err(Reason::Ops.into());
         }
 
-        let e = self.crt_mod_nn.prepare_exponent(scalar);
-        Ok(self.crt_mod_nn.exp(ciphertext, &e).ok_or(Reason::Ops)?)
+        let e = self.crt().crt_mod_nn.prepare_exponent(scalar);
+        Ok(self
+            .crt()
+            .crt_mod_nn
+            .exp(ciphertext, &e)
+            .ok_or(Reason::Ops)?)
+    }
+
+    /// Homomorphic multiplication of scalar at ciphertext followed by re-randomization
+    ///
+    /// Same as [`EncryptionKey::omul_rerand`], but uses the fact that factorization of `N` is
+    /// known to speed up both multiplication and computing `r^N` for the fresh nonce `r`.
+    /// Returns resulting ciphertext and `r`.
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
+    pub fn omul_rerand(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        scalar: &Integer,
+        ciphertext: &Ciphertext,
+        nonce: Option<&mut Nonce>,
+    ) -> Result<Encryption, Error> {
+        if let Some(nonce) = &nonce {
+            if !utils::in_mult_group(nonce, self.n()) {
+                return Err(Reason::Ops.into());
+            }
+        }
+        let product = self.omul(scalar, ciphertext)?;
+        let (fresh_nonce, fresh_nonce_pow_n) = self.sample_nonce_and_power(rng)?;
+        if let Some(nonce) = nonce {
+            let nonce_pow_k: Integer = nonce
+                .pow_mod_ref(scalar, self.n())
+                .ok_or(Reason::Ops)?
+                .into();
+            *nonce = (nonce_pow_k * &fresh_nonce) % self.n();
+        }
+        Ok(Encryption {
+            ciphertext: (product * fresh_nonce_pow_n) % self.ek.nn(),
+            nonce: fresh_nonce,
+        })
+    }
+
+    /// Homomorphic product of a cleartext matrix and a vector of ciphertexts
+    ///
+    /// Same as [`EncryptionKey::omatvec`], but uses the fact that factorization of `N` is known
+    /// to speed up exponentiations and re-randomization.
+    #[must_use = "homomorphic operations return new ciphertexts and don't modify the inputs"]
+    pub fn omatvec(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        matrix: &[Vec<Integer>],
+        ciphertexts: &[Ciphertext],
+    ) -> Result<Vec<Encryption>, Error> {
+        for row in matrix {
+            crate::ensure_same_len(ciphertexts.len(), row.len())?;
+        }
+        if let Some(i) = ciphertexts
+            .iter()
+            .position(|c| !utils::in_mult_group(c, self.ek.nn()))
+        {
+            return Err(Reason::OpsAt(i).into());
+        }
+        matrix
+            .iter()
+            .map(|row| {
+                let mut product = self.ek.oadd_identity();
+                for (scalar, ciphertext) in row.iter().zip(ciphertexts) {
+                    let e = self.crt().crt_mod_nn.prepare_exponent(scalar);
+                    let c = self
+                        .crt()
+                        .crt_mod_nn
+                        .exp(ciphertext, &e)
+                        .ok_or(Reason::Ops)?;
+                    product = (product * c) % self.ek.nn();
+                }
+                let Encryption {
+                    ciphertext: enc_zero,
+                    nonce,
+                } = self.encrypt_with_random(rng, &Integer::ZERO)?;
+                Ok(Encryption {
+                    ciphertext: (product * enc_zero) % self.ek.nn(),
+                    nonce,
+                })
+            })
+            .collect()
     }
 
     /// Returns a (public) encryption key corresponding to the (secret) decryption key
@@ -163,11 +898,38 @@ impl DecryptionKey {
         &self.ek
     }
 
+    /// Boxes the key as [`AnyEncryptionKey`](crate::AnyEncryptionKey) trait object
+    ///
+    /// Handy for storing encryption and decryption keys in the same collection
+    pub fn into_any(self) -> Box<dyn crate::AnyEncryptionKey> {
+        Box::new(self)
+    }
+
     /// The Paillier modulus
     pub fn n(&self) -> &Integer {
         self.ek.n()
     }
 
+    /// Returns `N^2`
+    pub fn nn(&self) -> &Integer {
+        self.ek.nn()
+    }
+
+    /// Returns `N/2`
+    pub fn half_n(&self) -> &Integer {
+        self.ek.half_n()
+    }
+
+    /// Returns `-N/2`
+    pub fn neg_half_n(&self) -> &Integer {
+        self.ek.neg_half_n()
+    }
+
+    /// Euler's totient `phi(N)`
+    pub fn phi(&self) -> &Integer {
+        &self.phi
+    }
+
     /// The Paillier `lambda`
     pub fn lambda(&self) -> &Integer {
         &self.lambda
@@ -175,20 +937,129 @@ impl DecryptionKey {
 
     /// The Paillier `mu`
     pub fn mu(&self) -> &Integer {
-        &self.mu
+        &self.decryption_data().mu
     }
 
     /// Prime `p`
+    ///
+    /// It's the smallest prime factor of `N`
     pub fn p(&self) -> &Integer {
-        &self.p
+        &self.primes[0]
     }
     /// Prime `q`
+    ///
+    /// It's the larger prime factor of `N`. For multi-prime keys, it's the second smallest prime
+    /// factor of `N`
     pub fn q(&self) -> &Integer {
-        &self.q
+        &self.primes[1]
+    }
+
+    /// All prime factors of `N` in ascending order
+    ///
+    /// It's `[p, q]` unless the key is [multi-prime](Self::from_multiprime)
+    pub fn primes(&self) -> &[Integer] {
+        &self.primes
     }
 
-    /// Bits length of smaller prime (`p` or `q`)
+    /// Bits length of smallest prime factor of `N`
     pub fn bits_length(&self) -> u32 {
-        self.p.significant_bits().min(self.q.significant_bits())
+        self.primes
+            .iter()
+            .map(|p| p.significant_bits())
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+/// Keys are equal if they have the same prime factors
+///
+/// Primes are stored in ascending order, so it doesn't matter in which order they were given at
+/// construction. Note that comparison is not constant-time.
+impl PartialEq for DecryptionKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.primes == other.primes
+    }
+}
+
+impl Eq for DecryptionKey {}
+
+/// Hashes canonical big-endian bytes of prime factors in ascending order, so the hash doesn't
+/// depend on how `rug` represents integers
+impl std::hash::Hash for DecryptionKey {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.primes.len().hash(state);
+        for p in &self.primes {
+            p.to_digits::<u8>(rug::integer::Order::Msf).hash(state)
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for DecryptionKey {
+    type Error = Error;
+
+    /// Parses a decryption key, see [`DecryptionKey::from_bytes`]
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::from_bytes(bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn self_test_detects_corrupted_mu() {
+        let mut rng = rand_dev::DevRng::new();
+        let p = crate::utils::generate_safe_prime(&mut rng, 256);
+        let q = crate::utils::generate_safe_prime(&mut rng, 256);
+        let mut dk = super::DecryptionKey::from_primes(p, q).unwrap();
+        dk.self_test(&mut rng).unwrap();
+
+        let mut data = dk.decryption_data().clone();
+        data.mu += 1;
+        dk.decryption = std::sync::OnceLock::from(data);
+        let err = dk.self_test(&mut rng).unwrap_err();
+        assert_eq!(err.kind(), crate::ErrorKind::InvalidKey);
+    }
+    #[test]
+    fn lazy_key_skips_decryption_data() {
+        let mut rng = rand_dev::DevRng::new();
+        let p = crate::utils::generate_safe_prime(&mut rng, 256);
+        let q = crate::utils::generate_safe_prime(&mut rng, 256);
+
+        let eager = super::DecryptionKey::from_primes(p.clone(), q.clone()).unwrap();
+        assert!(eager.decryption.get().is_some());
+
+        let lazy = super::DecryptionKey::from_primes_lazy(p, q).unwrap();
+        assert!(lazy.decryption.get().is_none());
+
+        // Encryption doesn't need decryption data
+        let x = rug::Integer::from(123);
+        let enc = lazy.encrypt_with_random(&mut rng, &x).unwrap();
+        let _ = lazy.nonce_power(&enc.nonce).unwrap();
+        assert!(lazy.decryption.get().is_none());
+
+        assert_eq!(lazy.decrypt(&enc.ciphertext).unwrap(), x);
+        assert!(lazy.decryption.get().is_some());
+        assert_eq!(lazy.mu(), eager.mu());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn deserialized_key_defers_precomputations() {
+        let mut rng = rand_dev::DevRng::new();
+        let p = crate::utils::generate_safe_prime(&mut rng, 256);
+        let q = crate::utils::generate_safe_prime(&mut rng, 256);
+        let dk = super::DecryptionKey::from_primes(p, q).unwrap();
+        let x = rug::Integer::from(123);
+        let c = dk.encrypt_with_random(&mut rng, &x).unwrap().ciphertext;
+
+        let serialized = serde_json::to_string(&dk).unwrap();
+        let deserialized: super::DecryptionKey = serde_json::from_str(&serialized).unwrap();
+        assert!(deserialized.crt.get().is_none());
+        assert!(deserialized.decryption.get().is_none());
+
+        assert_eq!(deserialized.decrypt(&c).unwrap(), x);
+        assert!(deserialized.crt.get().is_some());
+        assert!(deserialized.decryption.get().is_some());
+        assert_eq!(deserialized.mu(), dk.mu());
     }
 }
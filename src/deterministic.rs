@@ -0,0 +1,135 @@
+//! Deterministic encryption for known-answer tests and reproducible transcripts
+//!
+//! **Not secure for real use**: Paillier encryption is semantically secure only when the nonce is
+//! random and secret. Deterministic nonce makes ciphertexts of the same plaintext equal, and
+//! anyone who guesses the plaintext can check the guess by re-encrypting it.
+
+use std::collections::{HashMap, VecDeque};
+
+use rug::Integer;
+use sha2::{Digest, Sha256};
+
+use crate::utils::{self, len_prefix};
+use crate::{Ciphertext, EncryptionKey, Error, Nonce, Plaintext, Reason};
+
+/// Domain separation tag for nonce derivation
+const NONCE_TAG: &[u8] = b"fast-paillier/deterministic-nonce/v1";
+
+impl EncryptionKey {
+    /// Encrypts the plaintext `x` in `{-N/2, .., N/2}` with nonce derived from `x`, `N`, and `domain`
+    ///
+    /// Same inputs always produce the same ciphertext, which is handy for known-answer tests and
+    /// reproducible protocol transcripts. Nonce is obtained by hashing the inputs with SHA-256
+    /// and mapping the hash into `Z*_n`.
+    ///
+    /// ## Security
+    /// Deterministic encryption is **insecure**: it leaks whether two ciphertexts encrypt the
+    /// same plaintext, and allows anyone to check a guess of the plaintext. Never use it outside
+    /// of tests.
+    ///
+    /// Returns error if plaintext is not in specified range
+    #[must_use = "encryption returns a new ciphertext"]
+    pub fn encrypt_deterministic(&self, x: &Plaintext, domain: &[u8]) -> Result<Ciphertext, Error> {
+        if !self.in_signed_group(x) {
+            return Err(Reason::Encrypt.into());
+        }
+        let nonce = self.derive_nonce(x, domain);
+        self.encrypt_with_unchecked(x, &nonce)
+    }
+
+    /// Creates a cache for [deterministic encryption](Self::encrypt_deterministic) holding up to
+    /// `capacity` ciphertexts
+    pub fn deterministic_cache(&self, capacity: usize) -> DeterministicCache<'_> {
+        DeterministicCache {
+            ek: self,
+            capacity,
+            entries: HashMap::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+            computed: 0,
+        }
+    }
+
+    /// Hashes `x`, `N`, and `domain` into `Z*_n` via [`utils::hash_to_mult_group`]
+    fn derive_nonce(&self, x: &Plaintext, domain: &[u8]) -> Nonce {
+        let x_bytes = x.to_digits::<u8>(rug::integer::Order::Msf);
+        let n_bytes = self.n().to_digits::<u8>(rug::integer::Order::Msf);
+        let seed = Sha256::new()
+            .chain_update(NONCE_TAG)
+            .chain_update(len_prefix(domain))
+            .chain_update(domain)
+            .chain_update(len_prefix(&n_bytes))
+            .chain_update(&n_bytes)
+            .chain_update([u8::from(x.is_negative())])
+            .chain_update(len_prefix(&x_bytes))
+            .chain_update(&x_bytes);
+        utils::hash_to_mult_group(&seed, self.n())
+    }
+}
+
+/// Bounded cache of [deterministic encryptions](EncryptionKey::encrypt_deterministic)
+///
+/// Memoizes ciphertexts keyed by `(x, domain)`, which helps protocols that repeatedly encrypt
+/// the same public constants. When the cache is full, the oldest entry is evicted. Cache is
+/// bound to the key it was created with.
+///
+/// Can be obtained via [`EncryptionKey::deterministic_cache`].
+///
+/// ## Security
+/// Same considerations apply as for [`EncryptionKey::encrypt_deterministic`]: never use it
+/// outside of tests.
+pub struct DeterministicCache<'k> {
+    ek: &'k EncryptionKey,
+    capacity: usize,
+    entries: HashMap<(Integer, Vec<u8>), Ciphertext>,
+    /// Keys in insertion order, used for eviction
+    order: VecDeque<(Integer, Vec<u8>)>,
+    /// Amount of ciphertexts that were actually computed, i.e. amount of cache misses
+    computed: u64,
+}
+
+impl DeterministicCache<'_> {
+    /// Encrypts the plaintext deterministically, returns cached ciphertext if there's one
+    ///
+    /// Output is the same as [`EncryptionKey::encrypt_deterministic`] produces
+    #[must_use = "encryption returns a new ciphertext"]
+    pub fn encrypt(&mut self, x: &Plaintext, domain: &[u8]) -> Result<Ciphertext, Error> {
+        let key = (x.clone(), domain.to_vec());
+        if let Some(ciphertext) = self.entries.get(&key) {
+            return Ok(ciphertext.clone());
+        }
+
+        let ciphertext = self.ek.encrypt_deterministic(x, domain)?;
+        self.computed += 1;
+        if self.capacity == 0 {
+            return Ok(ciphertext);
+        }
+        if self.entries.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(key.clone(), ciphertext.clone());
+        self.order.push_back(key);
+        Ok(ciphertext)
+    }
+
+    /// Amount of ciphertexts that were computed rather than taken from the cache
+    pub fn computed(&self) -> u64 {
+        self.computed
+    }
+
+    /// Amount of cached ciphertexts
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Maximum amount of cached ciphertexts
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
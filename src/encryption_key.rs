@@ -1,30 +1,140 @@
+use std::borrow::Borrow;
+use std::sync::OnceLock;
+
 use rand_core::{CryptoRng, RngCore};
-use rug::{Complete, Integer};
+use rug::{Assign, Complete, Integer};
 
-use crate::{utils, Ciphertext, Nonce, Plaintext};
+use crate::{utils, Ciphertext, Encryption, Nonce, Plaintext, PreparedCiphertexts};
 use crate::{Bug, Error, Reason};
 
 /// Paillier encryption key
+///
+/// Key is `Send + Sync`, so it can be shared across threads.
 #[derive(Clone, Debug)]
 pub struct EncryptionKey {
     n: Integer,
+    /// Values derived from `N`, computed either at construction or on first use
+    derived: OnceLock<Derived>,
+}
+
+#[derive(Clone, Debug)]
+struct Derived {
     nn: Integer,
     half_n: Integer,
     neg_half_n: Integer,
+    /// `Enc(1) = 1 + N`
+    enc_one: Ciphertext,
+    /// `Enc(-1) = 1 - N mod N^2`
+    enc_neg_one: Ciphertext,
 }
 
-impl EncryptionKey {
-    /// Constructs an encryption key from `N`
-    pub fn from_n(n: Integer) -> Self {
-        let nn = n.clone() * &n;
-        let half_n = n.clone() >> 1u32;
+impl Derived {
+    fn new(n: &Integer) -> Self {
+        let nn = n.square_ref().complete();
+        let half_n = (n >> 1u32).complete();
         let neg_half_n = -half_n.clone();
+        let enc_one = (n + 1u8).complete();
+        let enc_neg_one = (&nn - n).complete() + 1u8;
         Self {
-            n,
             nn,
             half_n,
             neg_half_n,
+            enc_one,
+            enc_neg_one,
+        }
+    }
+}
+
+impl EncryptionKey {
+    /// Constructs an encryption key from `N`
+    #[must_use = "constructed key is not used"]
+    pub fn from_n(n: Integer) -> Self {
+        let derived = OnceLock::from(Derived::new(&n));
+        Self { n, derived }
+    }
+
+    /// Constructs an encryption key from `N`, deferring precomputations until first use
+    ///
+    /// Unlike [`from_n`](Self::from_n), values derived from `N` (such as `N^2`) are not computed
+    /// until they're needed. It makes construction cheaper when the key is only used to read `N`,
+    /// e.g. to identify a key after deserialization. Otherwise, the key behaves exactly like the
+    /// one constructed via `from_n`.
+    #[must_use = "constructed key is not used"]
+    pub fn from_n_lazy(n: Integer) -> Self {
+        Self {
+            n,
+            derived: OnceLock::new(),
+        }
+    }
+
+    /// Constructs an encryption key from primes `p`, `q`
+    ///
+    /// Computes `N = p * q` and doesn't retain the primes, so it's cheaper than constructing
+    /// a [`DecryptionKey`](crate::DecryptionKey) and taking its [public part](crate::DecryptionKey::encryption_key). Primes
+    /// are not validated.
+    #[must_use = "constructed key is not used"]
+    pub fn from_primes(p: &Integer, q: &Integer) -> Self {
+        Self::from_n((p * q).complete())
+    }
+
+    /// Parses an encryption key from big-endian bytes representation of `N`
+    ///
+    /// Returns error if bytes are empty, or if `N` is not an odd number greater than 1.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.is_empty() {
+            return Err(Reason::InvalidEncoding.into());
         }
+        Self::from_n_checked(Integer::from_digits(bytes, rug::integer::Order::Msf))
+    }
+
+    /// Constructs an encryption key from `N`
+    ///
+    /// Returns error if `N` is not an odd number greater than 1
+    pub(crate) fn from_n_checked(n: Integer) -> Result<Self, Error> {
+        if n <= 1 || n.is_even() {
+            return Err(Reason::InvalidN.into());
+        }
+        Ok(Self::from_n(n))
+    }
+
+    /// Extracts an encryption key from decryption key serialized via serde into
+    /// [`postcard`](::postcard)
+    ///
+    /// Takes bytes produced by `postcard::to_allocvec(&decryption_key)`, i.e. the binary serde
+    /// form of [`DecryptionKey`](crate::DecryptionKey) (tuple `(p, q)`, or a tagged list of primes for multi-prime keys),
+    /// and returns the corresponding encryption key. It's cheaper than deserializing the whole
+    /// decryption key and then taking its [encryption key](crate::DecryptionKey::encryption_key), as no
+    /// precomputations for the secret key are done.
+    ///
+    /// Note that the primes are not validated, so it's not guaranteed that bytes correspond to a
+    /// valid decryption key. Returns error if bytes are malformed, or if `N` is not an odd number
+    /// greater than 1.
+    #[cfg(feature = "postcard")]
+    pub fn from_decryption_key_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let (crate::serde::DecryptionKeyPrimes(primes), rest) =
+            ::postcard::take_from_bytes(bytes).map_err(|_| Reason::InvalidEncoding)?;
+        if !rest.is_empty() || primes.len() < 2 {
+            return Err(Reason::InvalidEncoding.into());
+        }
+        Self::from_n_checked(primes.iter().product())
+    }
+
+    /// Parses an encryption key from output of [`DecryptionKey::public_serialize`](crate::DecryptionKey::public_serialize)
+    ///
+    /// Returns error if bytes are not tagged as a redacted decryption key, or if `N` is invalid
+    /// (same as [`EncryptionKey::from_bytes`]).
+    pub fn from_public_serialized(bytes: &[u8]) -> Result<Self, Error> {
+        let n = bytes
+            .strip_prefix(crate::decryption_key::REDACTED_KEY_TAG)
+            .ok_or(Reason::InvalidEncoding)?;
+        Self::from_bytes(n)
+    }
+
+    /// Serializes `N` in big-endian bytes
+    ///
+    /// Output can be parsed back via [`EncryptionKey::from_bytes`]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.n.to_digits(rug::integer::Order::Msf)
     }
 
     /// Returns `N`
@@ -34,15 +144,49 @@ impl EncryptionKey {
 
     /// Returns `N^2`
     pub fn nn(&self) -> &Integer {
-        &self.nn
+        &self.derived().nn
     }
 
     /// Returns `N/2`
+    ///
+    /// Precisely, returns `floor(N/2)`, which equals to `(N-1)/2` for odd `N`. It's the upper
+    /// bound of [signed group](Self::in_signed_group) when `N` is odd.
     pub fn half_n(&self) -> &Integer {
-        &self.half_n
+        &self.derived().half_n
+    }
+
+    /// Returns `-N/2`
+    ///
+    /// Precisely, returns `-floor(N/2)`, which is the lower bound of
+    /// [signed group](Self::in_signed_group).
+    pub fn neg_half_n(&self) -> &Integer {
+        &self.derived().neg_half_n
+    }
+
+    /// Returns fingerprint of the key
+    ///
+    /// Fingerprint is the 64 least significant bits of `N`. It's used to identify the key, e.g.
+    /// by [`TaggedCiphertext`](crate::TaggedCiphertext), and is not meant to provide any security
+    /// guarantees.
+    pub fn fingerprint(&self) -> u64 {
+        self.n.to_u64_wrapping()
+    }
+
+    /// Boxes the key as [`AnyEncryptionKey`](crate::AnyEncryptionKey) trait object
+    ///
+    /// Handy for storing encryption and decryption keys in the same collection
+    pub fn into_any(self) -> Box<dyn crate::AnyEncryptionKey> {
+        Box::new(self)
+    }
+
+    fn derived(&self) -> &Derived {
+        self.derived.get_or_init(|| Derived::new(&self.n))
     }
 
     /// `l(x) = (x-1)/n`
+    ///
+    /// Returns `None` if `x` is not in `Z*_{N^2}` or if `x != 1 mod N`
+    #[cfg_attr(not(test), allow(dead_code))]
     pub(crate) fn l(&self, x: &Integer) -> Option<Integer> {
         if (x % self.n()).complete() != *Integer::ONE {
             return None;
@@ -51,49 +195,279 @@ impl EncryptionKey {
             return None;
         }
 
+        Some(self.l_unchecked(x))
+    }
+
+    /// `l(x) = (x-1)/n`
+    ///
+    /// Unlike [`l`](Self::l), doesn't check that `x` is in `Z*_{N^2}` and that `x = 1 mod N`.
+    /// Caller must ensure that these conditions hold, otherwise output is meaningless.
+    pub(crate) fn l_unchecked(&self, x: &Integer) -> Integer {
         // (x - 1) / N
-        Some((x - Integer::ONE).complete() / self.n())
+        (x - Integer::ONE).complete() / self.n()
     }
 
-    /// Encrypts the plaintext `x` in `{-N/2, .., N_2}` with `nonce` in `Z*_n`
+    /// Encrypts the plaintext `x` in `{-N/2, .., N/2}` with `nonce` in `Z*_n`
     ///
     /// Returns error if inputs are not in specified range
+    #[must_use = "encryption returns a new ciphertext"]
     pub fn encrypt_with(&self, x: &Plaintext, nonce: &Nonce) -> Result<Ciphertext, Error> {
         if !self.in_signed_group(x) || !utils::in_mult_group(nonce, self.n()) {
             return Err(Reason::Encrypt.into());
         }
+        self.encrypt_with_unchecked(x, nonce)
+    }
 
-        let x = if x.cmp0().is_ge() {
-            x.clone()
-        } else {
-            (x + self.n()).complete()
-        };
+    /// Encrypts the plaintext `x` modulo `N` with `nonce` in `Z*_n`
+    ///
+    /// Unlike [`encrypt_with`](Self::encrypt_with), `x` can be any integer: it's reduced into
+    /// `{-N/2, .., N/2}` via [`utils::signed_modulo`] before encryption. Reduction is intentionally
+    /// lossy, i.e. the ciphertext decrypts to `x mod N` rather than `x` when `x` is out of range.
+    ///
+    /// Returns error if nonce is not in `Z*_n`
+    #[must_use = "encryption returns a new ciphertext"]
+    pub fn encrypt_with_wrap(&self, x: &Integer, nonce: &Nonce) -> Result<Ciphertext, Error> {
+        let x = utils::signed_modulo(x, self.n());
+        self.encrypt_with(&x, nonce)
+    }
 
-        // a = (1 + N)^x mod N^2 = (1 + xN) mod N^2
-        let a = (Integer::ONE + (&x * self.n()).complete()) % self.nn();
+    /// Encrypts the plaintext `x` with `nonce` without validating the inputs
+    ///
+    /// Same as [`encrypt_with`](Self::encrypt_with), but skips the range checks which saves
+    /// a GCD computation. Caller must ensure that `x` is in `{-N/2, .., N/2}` and `nonce` is in
+    /// `Z*_n` (e.g. it was sampled via [`utils::sample_in_mult_group`]), otherwise resulting
+    /// ciphertext is not a valid encryption of `x`.
+    #[must_use = "encryption returns a new ciphertext"]
+    pub fn encrypt_with_unchecked(
+        &self,
+        x: &Plaintext,
+        nonce: &Nonce,
+    ) -> Result<Ciphertext, Error> {
+        let a = self.encode_message_unchecked(x);
         // b = nonce^N mod N^2
-        let b = nonce
-            .clone()
-            .pow_mod(self.n(), self.nn())
-            .map_err(|_| Bug::PowModUndef)?;
+        let mut b = crate::pool::take();
+        b.assign(
+            nonce
+                .pow_mod_ref(self.n(), self.nn())
+                .ok_or(Bug::PowModUndef)?,
+        );
 
-        let c = (a * b).modulo(self.nn());
+        let mut c = a;
+        c *= &b;
+        crate::pool::give(b);
+        c.modulo_mut(self.nn());
         Ok(c)
     }
 
-    /// Encrypts the plaintext `x` in `{-N/2, .., N_2}`
+    /// Encrypts the plaintext `x` in `{-N/2, .., N/2}` given precomputed `nonce^N mod N^2`
+    ///
+    /// Skips the most expensive part of encryption, which is computing `nonce^N mod N^2`. Pair of
+    /// nonce and its power can be obtained, for instance, via
+    /// [`DecryptionKey::sample_nonce_and_power`](crate::DecryptionKey::sample_nonce_and_power).
+    ///
+    /// Returns error if `x` is not in specified range, or if `nonce_pow_n` is not in `Z*_{N^2}`
+    #[must_use = "encryption returns a new ciphertext"]
+    pub fn encrypt_with_precomputed(
+        &self,
+        x: &Plaintext,
+        nonce_pow_n: &Integer,
+    ) -> Result<Ciphertext, Error> {
+        if !self.in_signed_group(x) || !utils::in_mult_group(nonce_pow_n, self.nn()) {
+            return Err(Reason::Encrypt.into());
+        }
+
+        let a = self.encode_message_unchecked(x);
+        Ok((a * nonce_pow_n) % self.nn())
+    }
+
+    /// Encodes the plaintext `x` in `{-N/2, .., N/2}` as `(1 + N)^x mod N^2`
+    ///
+    /// It's the message-dependent part of encryption: `Enc(x; r) = encode_message(x) * r^N mod N^2`.
+    /// Computed as `(1 + xN) mod N^2`, which is cheap. Can be used to build ciphertexts from
+    /// externally computed nonce powers.
+    ///
+    /// Returns error if `x` is not in specified range
+    pub fn encode_message(&self, x: &Plaintext) -> Result<Integer, Error> {
+        if !self.in_signed_group(x) {
+            return Err(Reason::Encrypt.into());
+        }
+        Ok(self.encode_message_unchecked(x))
+    }
+
+    /// Computes `nonce^N mod N^2`
+    ///
+    /// It's the nonce-dependent part of encryption:
+    /// `Enc(x; nonce) = encode_message(x) * nonce_power(nonce) mod N^2` (see
+    /// [`encode_message`](Self::encode_message)). Can be used, for instance, by verifiers in ZK
+    /// protocols that receive a nonce and need to check a ciphertext.
+    ///
+    /// Returns error if `nonce` is not in `Z*_n`
+    pub fn nonce_power(&self, nonce: &Nonce) -> Result<Integer, Error> {
+        if !utils::in_mult_group(nonce, self.n()) {
+            return Err(Reason::Encrypt.into());
+        }
+        Ok(nonce
+            .pow_mod_ref(self.n(), self.nn())
+            .ok_or(Bug::PowModUndef)?
+            .into())
+    }
+
+    /// Computes `(1 + N)^x mod N^2`, `x` needs to be in `{-N/2, .., N/2}`
+    pub(crate) fn encode_message_unchecked(&self, x: &Plaintext) -> Integer {
+        let x = utils::signed_to_unsigned(x, self.n());
+        // (1 + N)^x mod N^2 = (1 + xN) mod N^2
+        (Integer::ONE + x * self.n()) % self.nn()
+    }
+
+    /// Encrypts the plaintext `x` in `{-N/2, .., N/2}`
     ///
     /// Nonce is sampled randomly using `rng`.
     ///
     /// Returns error if plaintext is not in specified range
+    #[must_use = "encryption returns a new ciphertext"]
     pub fn encrypt_with_random(
         &self,
         rng: &mut (impl RngCore + CryptoRng),
         x: &Plaintext,
-    ) -> Result<(Ciphertext, Nonce), Error> {
+    ) -> Result<Encryption, Error> {
+        if !self.in_signed_group(x) {
+            return Err(Reason::Encrypt.into());
+        }
         let nonce = utils::sample_in_mult_group(rng, self.n());
-        let ciphertext = self.encrypt_with(x, &nonce)?;
-        Ok((ciphertext, nonce))
+        // nonce is guaranteed to be in `Z*_n`, no need to validate it once again
+        let ciphertext = self.encrypt_with_unchecked(x, &nonce)?;
+        Ok(Encryption { ciphertext, nonce })
+    }
+
+    /// Encrypts each plaintext in `{-N/2, .., N/2}` with a fresh random nonce
+    ///
+    /// Returns encryptions in the same order as plaintexts. Empty list of plaintexts gives an
+    /// empty list of encryptions.
+    ///
+    /// Returns error if any plaintext is not in specified range, the error carries its
+    /// [index](Error::index).
+    #[must_use = "encryption returns new ciphertexts"]
+    pub fn encrypt_many(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        plaintexts: &[Plaintext],
+    ) -> Result<Vec<Encryption>, Error> {
+        plaintexts
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                self.encrypt_with_random(rng, x)
+                    .map_err(|_| Reason::EncryptAt(i).into())
+            })
+            .collect()
+    }
+
+    /// Encrypts the plaintext `x` in `{-N/2, .., N/2}` given as any type convertible into [`Integer`]
+    ///
+    /// Same as [`encrypt_with_random`](Self::encrypt_with_random), but saves converting primitive
+    /// integers into [`Integer`]:
+    ///
+    /// ```rust
+    /// # fn main() -> Result<(), fast_paillier::Error> {
+    /// # let mut rng = rand_dev::DevRng::new();
+    /// # let p = fast_paillier::utils::generate_safe_prime(&mut rng, 256);
+    /// # let q = fast_paillier::utils::generate_safe_prime(&mut rng, 256);
+    /// # let dk = fast_paillier::DecryptionKey::from_primes(p, q)?;
+    /// let ek = dk.encryption_key();
+    ///
+    /// let c1 = ek.encrypt(&mut rng, 42_u64)?.ciphertext;
+    /// let c2 = ek.encrypt(&mut rng, -42_i64)?.ciphertext;
+    /// assert_eq!(dk.decrypt(&c1)?, 42);
+    /// assert_eq!(dk.decrypt(&c2)?, -42);
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// Nonce is sampled randomly using `rng`.
+    ///
+    /// Returns error if plaintext is not in specified range
+    #[must_use = "encryption returns a new ciphertext"]
+    pub fn encrypt(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        x: impl Into<Integer>,
+    ) -> Result<Encryption, Error> {
+        self.encrypt_with_random(rng, &x.into())
+    }
+
+    /// Encrypts the plaintext `x` in `{-N/2, .., N/2}` using randomness provided by operating system
+    ///
+    /// Same as [`EncryptionKey::encrypt_with_random`], but nonce is sampled using randomness
+    /// sourced via `getrandom`. Use [`EncryptionKey::encrypt_with_random`] if you need control
+    /// over randomness source, e.g. for deterministic tests.
+    ///
+    /// Returns error if plaintext is not in specified range
+    #[cfg(feature = "getrandom")]
+    #[must_use = "encryption returns a new ciphertext"]
+    pub fn encrypt_default(&self, x: &Plaintext) -> Result<Encryption, Error> {
+        self.encrypt_with_random(&mut rand_core::OsRng, x)
+    }
+
+    /// Encrypts the plaintext given as bytes
+    ///
+    /// Bytes are prefixed with a `0x01` byte, so that leading zero bytes are preserved, and
+    /// `0x01 || bytes` is interpreted as big-endian unsigned integer `x` which needs to be in
+    /// `{0, .., N-1}`. Messages up to `len(N) - 2` bytes always fit, where `len(N)` is the byte
+    /// length of `N`. `x` is mapped to `{-N/2, .., N/2}` and encrypted. Use
+    /// [`DecryptionKey::decrypt_bytes`](crate::DecryptionKey::decrypt_bytes) to get the exact
+    /// bytes back.
+    ///
+    /// Nonce is sampled randomly using `rng`.
+    ///
+    /// Returns error if plaintext is not in specified range
+    #[must_use = "encryption returns a new ciphertext"]
+    pub fn encrypt_bytes(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        bytes: &[u8],
+    ) -> Result<Encryption, Error> {
+        let prefix_bit = u32::try_from(bytes.len())
+            .ok()
+            .and_then(|len| len.checked_mul(8))
+            .ok_or(Reason::Encrypt)?;
+        let mut x = Integer::from_digits(bytes, rug::integer::Order::Msf);
+        x.set_bit(prefix_bit, true);
+        if x >= *self.n() {
+            return Err(Reason::Encrypt.into());
+        }
+        let x = utils::unsigned_to_signed(x, self.n());
+        self.encrypt_with_random(rng, &x)
+    }
+
+    /// Size of the context tag in bits, see [`encrypt_with_context`](Self::encrypt_with_context)
+    pub const CONTEXT_BITS: u32 = 128;
+
+    /// Encrypts the plaintext `x` tagged with `context`
+    ///
+    /// `context` must be in `{0, .., 2^CONTEXT_BITS - 1}`, it's packed together with `x` into a
+    /// single plaintext `x * 2^CONTEXT_BITS + context`. Use
+    /// [`DecryptionKey::decrypt_with_context`](crate::DecryptionKey::decrypt_with_context) to
+    /// decrypt the ciphertext and verify that it was produced for the expected context, which
+    /// prevents a ciphertext from being reused in a different context.
+    ///
+    /// Packing reduces the usable plaintext space: `x` must be in
+    /// `{-N/2^(CONTEXT_BITS+1), .., N/2^(CONTEXT_BITS+1)}` (roughly). Note that homomorphic
+    /// operations over such ciphertexts also alter the context tag.
+    ///
+    /// Nonce is sampled randomly using `rng`.
+    ///
+    /// Returns error if `x` or `context` is not in specified range
+    #[must_use = "encryption returns a new ciphertext"]
+    pub fn encrypt_with_context(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        x: &Plaintext,
+        context: &Integer,
+    ) -> Result<Encryption, Error> {
+        if context.is_negative() || context.significant_bits() > Self::CONTEXT_BITS {
+            return Err(Reason::Encrypt.into());
+        }
+        let packed = (x << Self::CONTEXT_BITS).complete() + context;
+        self.encrypt_with_random(rng, &packed)
     }
 
     /// Homomorphic addition of two ciphertexts
@@ -101,6 +475,16 @@ impl EncryptionKey {
     /// ```text
     /// oadd(Enc(a1), Enc(a2)) = Enc(a1 + a2)
     /// ```
+    ///
+    /// Inputs are not modified, so ignoring the output is a compile-time warning:
+    /// ```rust,compile_fail
+    /// #![deny(unused_must_use)]
+    /// # use fast_paillier::{Ciphertext, EncryptionKey};
+    /// # fn f(ek: &EncryptionKey, c1: &Ciphertext, c2: &Ciphertext) {
+    /// ek.oadd(c1, c2);
+    /// # }
+    /// ```
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
     pub fn oadd(&self, c1: &Ciphertext, c2: &Ciphertext) -> Result<Ciphertext, Error> {
         if !utils::in_mult_group(c1, self.nn()) || !utils::in_mult_group(c2, self.nn()) {
             return Err(Reason::Ops.into());
@@ -108,16 +492,108 @@ impl EncryptionKey {
         Ok((c1 * c2).complete() % self.nn())
     }
 
+    /// Homomorphic doubling of a ciphertext
+    ///
+    /// ```text
+    /// odouble(Enc(a)) = Enc(2a)
+    /// ```
+    ///
+    /// Same as `oadd(c, c)`, but validates the ciphertext once and computes a square instead of
+    /// a general multiplication.
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
+    pub fn odouble(&self, c: &Ciphertext) -> Result<Ciphertext, Error> {
+        if !utils::in_mult_group(c, self.nn()) {
+            return Err(Reason::Ops.into());
+        }
+        Ok(c.square_ref().complete() % self.nn())
+    }
+
+    /// Identity element of [homomorphic addition](Self::oadd)
+    ///
+    /// Returns `1` which is a (non-randomized) encryption of zero, i.e. `oadd(c, 1) = c` for
+    /// any ciphertext `c`. It can be used as initial value when folding ciphertexts:
+    ///
+    /// ```rust
+    /// # fn main() -> Result<(), fast_paillier::Error> {
+    /// # let mut rng = rand_dev::DevRng::new();
+    /// # let p = fast_paillier::utils::generate_safe_prime(&mut rng, 256);
+    /// # let q = fast_paillier::utils::generate_safe_prime(&mut rng, 256);
+    /// # let dk = fast_paillier::DecryptionKey::from_primes(p, q)?;
+    /// # let ek = dk.encryption_key();
+    /// # let ciphertexts = [1, 2, 3].map(|x| ek.encrypt_with_random(&mut rng, &x.into()).unwrap().ciphertext);
+    /// let sum = ciphertexts
+    ///     .iter()
+    ///     .try_fold(ek.oadd_identity(), |acc, c| ek.oadd(&acc, c))?;
+    /// # assert_eq!(dk.decrypt(&sum)?, 6);
+    /// # Ok(()) }
+    /// ```
+    #[must_use]
+    pub fn oadd_identity(&self) -> Ciphertext {
+        Integer::from(1)
+    }
+
+    /// Deterministic encryption of `1`
+    ///
+    /// Returns `Enc(1) = (1 + N) mod N^2`, i.e. encryption of `1` with nonce `1`. The ciphertext is
+    /// fixed per key and cached, so it's cheap to use as a building block, e.g. to increment an
+    /// encrypted value via [`oadd`](Self::oadd).
+    ///
+    /// The ciphertext is **not** randomized: anyone who knows the key can tell that it encrypts
+    /// `1`. The result of `oadd` with another ciphertext is as random as that ciphertext.
+    /// Use [`encrypt_with_random`](Self::encrypt_with_random) if the ciphertext needs to hide the
+    /// plaintext.
+    pub fn enc_one(&self) -> &Ciphertext {
+        &self.derived().enc_one
+    }
+
+    /// Deterministic encryption of `-1`
+    ///
+    /// Returns `Enc(-1) = (1 - N) mod N^2 = 1 + (N - 1) * N`, i.e. encryption of `-1` with nonce
+    /// `1`. Same as [`enc_one`](Self::enc_one), it's cached and is **not** randomized. Handy for
+    /// decrementing an encrypted value via [`oadd`](Self::oadd).
+    pub fn enc_neg_one(&self) -> &Ciphertext {
+        &self.derived().enc_neg_one
+    }
+
+    /// Homomorphic sum of ciphertexts
+    ///
+    /// ```text
+    /// osum([Enc(a1), ..., Enc(ak)]) = Enc(a1 + ... + ak)
+    /// ```
+    ///
+    /// Ciphertexts can be given as any collection or iterator of items that
+    /// [borrow](Borrow) a ciphertext, e.g. `&[Ciphertext]`, `Vec<&Ciphertext>`, or
+    /// an iterator of user-defined wrapper types, so they don't need to be collected into a
+    /// `Vec<Ciphertext>` first.
+    ///
+    /// Sum of empty list is [`oadd_identity`](Self::oadd_identity). Returns error if any of
+    /// ciphertexts is invalid, the error carries its [index](Error::index).
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
+    pub fn osum<C: Borrow<Ciphertext>>(
+        &self,
+        ciphertexts: impl IntoIterator<Item = C>,
+    ) -> Result<Ciphertext, Error> {
+        ciphertexts
+            .into_iter()
+            .enumerate()
+            .try_fold(self.oadd_identity(), |acc, (i, c)| {
+                self.oadd(&acc, c.borrow())
+                    .map_err(|_| Reason::OpsAt(i).into())
+            })
+    }
+
     /// Homomorphic subtraction of two ciphertexts
     ///
     /// ```text
     /// osub(Enc(a1), Enc(a2)) = Enc(a1 - a2)
     /// ```
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
     pub fn osub(&self, c1: &Ciphertext, c2: &Ciphertext) -> Result<Ciphertext, Error> {
         if !utils::in_mult_group(c1, self.nn()) {
             return Err(Reason::Ops.into());
         }
         let c2 = self.oneg(c2)?;
+        // Both `c1` and `c2` are in `{0, .., N^2 - 1}`, so is the output
         Ok((c1 * c2) % self.nn())
     }
 
@@ -126,6 +602,7 @@ impl EncryptionKey {
     /// ```text
     /// omul(a, Enc(c)) = Enc(a * c)
     /// ```
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
     pub fn omul(&self, scalar: &Integer, ciphertext: &Ciphertext) -> Result<Ciphertext, Error> {
         if !utils::in_mult_group_abs(scalar, self.n())
             || !utils::in_mult_group(ciphertext, self.nn())
@@ -133,23 +610,399 @@ impl EncryptionKey {
             return Err(Reason::Ops.into());
         }
 
+        // `pow_mod` output is always in `{0, .., N^2 - 1}`
+        Ok(ciphertext
+            .pow_mod_ref(scalar, self.nn())
+            .ok_or(Reason::Ops)?
+            .into())
+    }
+
+    /// Homomorphic multiplication of scalar at ciphertext with known plaintext bound
+    ///
+    /// Same as [`omul`](Self::omul), but takes `plaintext_bound` such that `|c| <= plaintext_bound`,
+    /// and returns error if `|scalar| * plaintext_bound > N/2`. That guarantees that the output
+    /// plaintext `scalar * c` doesn't wrap around `N`, which is assumed by some protocols (e.g. ZK
+    /// range proofs).
+    ///
+    /// Note that the plaintext bound can not be checked without knowing the secret key, so it's up
+    /// to the caller to make sure the bound is correct.
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
+    pub fn omul_bounded(
+        &self,
+        scalar: &Integer,
+        ciphertext: &Ciphertext,
+        plaintext_bound: &Integer,
+    ) -> Result<Ciphertext, Error> {
+        if plaintext_bound.is_negative()
+            || (scalar * plaintext_bound).complete().abs() > *self.half_n()
+        {
+            return Err(Reason::Ops.into());
+        }
+        self.omul(scalar, ciphertext)
+    }
+
+    /// Homomorphic multiplication of a signed scalar at ciphertext
+    ///
+    /// ```text
+    /// omul_signed(a, Enc(c)) = Enc(a * c mod N)
+    /// ```
+    ///
+    /// Scalar is treated the same way as a plaintext in [`encrypt_with`](Self::encrypt_with): it
+    /// must be in `{-N/2, .., N/2}`, and negative scalars are allowed. Resulting ciphertext
+    /// decrypts to `a * c` reduced into `{-N/2, .., N/2}` (see [`utils::signed_modulo`]), so the
+    /// sign of the output is preserved as long as `|a * c| <= N/2`. Unlike [`omul`](Self::omul),
+    /// scalar is not required to be coprime with `N`, so `a = 0` is allowed.
+    ///
+    /// Returns error if scalar is out of range, or if ciphertext is invalid.
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
+    pub fn omul_signed(
+        &self,
+        scalar: &Plaintext,
+        ciphertext: &Ciphertext,
+    ) -> Result<Ciphertext, Error> {
+        if !self.in_signed_group(scalar) || !utils::in_mult_group(ciphertext, self.nn()) {
+            return Err(Reason::Ops.into());
+        }
+        // `pow_mod` output is always in `{0, .., N^2 - 1}`
         Ok(ciphertext
             .pow_mod_ref(scalar, self.nn())
             .ok_or(Reason::Ops)?
             .into())
     }
 
+    /// Homomorphic inner product of scalars and ciphertexts
+    ///
+    /// ```text
+    /// odot([a1, ..., ak], [Enc(c1), ..., Enc(ck)]) = Enc(a1 * c1 + ... + ak * ck)
+    /// ```
+    ///
+    /// Unlike [`omul`](Self::omul), scalars are not required to be coprime with `N`. Returns error
+    /// if amounts of scalars and ciphertexts don't match, or if any ciphertext is invalid (the
+    /// error carries its [index](Error::index)). Inner product of empty lists is
+    /// [`oadd_identity`](Self::oadd_identity).
+    ///
+    /// Same as for [`osum`](Self::osum), ciphertexts can be given as any collection or iterator of
+    /// items that [borrow](Borrow) a ciphertext, as long as its length is known.
+    ///
+    /// If the same ciphertexts are used with many different scalars, consider
+    /// [preparing](Self::prepare_odot) them.
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
+    pub fn odot<C: Borrow<Ciphertext>>(
+        &self,
+        scalars: &[Integer],
+        ciphertexts: impl IntoIterator<Item = C, IntoIter: ExactSizeIterator>,
+    ) -> Result<Ciphertext, Error> {
+        let ciphertexts = ciphertexts.into_iter();
+        crate::ensure_same_len(ciphertexts.len(), scalars.len())?;
+        let mut result = self.oadd_identity();
+        for (i, (scalar, ciphertext)) in scalars.iter().zip(ciphertexts).enumerate() {
+            let ciphertext = ciphertext.borrow();
+            if !utils::in_mult_group(ciphertext, self.nn()) {
+                return Err(Reason::OpsAt(i).into());
+            }
+            let c = ciphertext
+                .pow_mod_ref(scalar, self.nn())
+                .ok_or(Reason::Ops)?;
+            result = (result * Integer::from(c)) % self.nn();
+        }
+        Ok(result)
+    }
+
+    /// Homomorphic product of a cleartext matrix and a vector of ciphertexts
+    ///
+    /// ```text
+    /// omatvec(M, [Enc(v1), ..., Enc(vk)]) = [Enc(M[1] · v), ..., Enc(M[m] · v)]
+    /// ```
+    ///
+    /// Each output ciphertext is an [`odot`](Self::odot) of the corresponding matrix row and
+    /// `ciphertexts`, re-randomized with a fresh nonce sampled using `rng`. Returns resulting
+    /// ciphertexts along with the nonces used for re-randomization.
+    ///
+    /// Returns error if any matrix row length doesn't match the amount of ciphertexts, or if any
+    /// ciphertext is invalid.
+    #[must_use = "homomorphic operations return new ciphertexts and don't modify the inputs"]
+    pub fn omatvec(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        matrix: &[Vec<Integer>],
+        ciphertexts: &[Ciphertext],
+    ) -> Result<Vec<Encryption>, Error> {
+        for row in matrix {
+            crate::ensure_same_len(ciphertexts.len(), row.len())?;
+        }
+        matrix
+            .iter()
+            .map(|row| {
+                let product = self.odot(row, ciphertexts)?;
+                self.rerandomize(rng, &product)
+            })
+            .collect()
+    }
+
+    /// Prepares ciphertexts for fast [homomorphic inner product](PreparedCiphertexts::odot)
+    ///
+    /// Precomputation makes sense when the same ciphertexts are multiplied by many different scalar
+    /// vectors. Returns error if any ciphertext is invalid.
+    pub fn prepare_odot(
+        &self,
+        ciphertexts: &[Ciphertext],
+    ) -> Result<PreparedCiphertexts<'_>, Error> {
+        PreparedCiphertexts::new(self, ciphertexts)
+    }
+
+    /// Homomorphic multiplication of a small unsigned scalar at ciphertext
+    ///
+    /// ```text
+    /// omul_u64(a, Enc(c)) = Enc(a * c)
+    /// ```
+    ///
+    /// Faster alternative to [`omul`](Self::omul) for small scalars. Unlike `omul`, the scalar
+    /// doesn't need to be checked for being coprime with `N`, and `scalar = 0` is allowed.
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
+    pub fn omul_u64(&self, scalar: u64, ciphertext: &Ciphertext) -> Result<Ciphertext, Error> {
+        if !utils::in_mult_group(ciphertext, self.nn()) {
+            return Err(Reason::Ops.into());
+        }
+
+        // Exponent is non-negative, so the power is always defined
+        let scalar = Integer::from(scalar);
+        #[allow(clippy::expect_used)]
+        let result = ciphertext
+            .pow_mod_ref(&scalar, self.nn())
+            .expect("exponent is non-negative");
+        Ok(result.into())
+    }
+
+    /// Homomorphic multiplication of a small signed scalar at ciphertext
+    ///
+    /// ```text
+    /// omul_i64(a, Enc(c)) = Enc(a * c)
+    /// ```
+    ///
+    /// Faster alternative to [`omul`](Self::omul) for small scalars, see [`omul_u64`](Self::omul_u64)
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
+    pub fn omul_i64(&self, scalar: i64, ciphertext: &Ciphertext) -> Result<Ciphertext, Error> {
+        let result = self.omul_u64(scalar.unsigned_abs(), ciphertext)?;
+        if scalar < 0 {
+            self.oneg(&result)
+        } else {
+            Ok(result)
+        }
+    }
+
     /// Homomorphic negation of a ciphertext
     ///
     /// ```text
     /// oneg(Enc(a)) = Enc(-a)
     /// ```
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
     pub fn oneg(&self, ciphertext: &Ciphertext) -> Result<Ciphertext, Error> {
+        // `invert` output is always in `{0, .., N^2 - 1}`
         Ok(ciphertext.invert_ref(self.nn()).ok_or(Reason::Ops)?.into())
     }
 
+    /// Homomorphic subtraction of a ciphertext from a plaintext
+    ///
+    /// ```text
+    /// rsub_plaintext(k, Enc(m)) = Enc(k - m)
+    /// ```
+    ///
+    /// Computed as `(1 + kN) * c^-1 mod N^2` in one go, which is cheaper than encrypting `k` and
+    /// calling [`osub`](Self::osub). Output is not re-randomized: it uses the inverse of the nonce
+    /// of `c`.
+    ///
+    /// Returns error if `k` is not in `{-N/2, .., N/2}` or if ciphertext is invalid.
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
+    pub fn rsub_plaintext(&self, k: &Plaintext, c: &Ciphertext) -> Result<Ciphertext, Error> {
+        if !self.in_signed_group(k) || !utils::in_mult_group(c, self.nn()) {
+            return Err(Reason::Ops.into());
+        }
+        let enc_k = self.encode_message_unchecked(k);
+        let neg_c = self.oneg(c)?;
+        Ok((enc_k * neg_c) % self.nn())
+    }
+
+    /// Homomorphic selection of one of two ciphertexts
+    ///
+    /// ```text
+    /// oselect(cond, Enc(a), Enc(b)) = Enc(cond ? a : b)
+    /// ```
+    ///
+    /// Selected ciphertext is re-randomized with a fresh nonce sampled using `rng`, so output
+    /// can't be linked to either of inputs. Returns resulting ciphertext and the nonce used for
+    /// re-randomization.
+    ///
+    /// ## Leakage model
+    /// Only the selection step is branch-free: both ciphertexts are written into fixed-width
+    /// buffers of `N^2` size and combined limb by limb with a mask derived from `cond`, so neither
+    /// control flow nor memory access pattern of this step depends on `cond`. Validation of the
+    /// inputs and re-randomization of the selected ciphertext use GMP arithmetic, which is not
+    /// constant-time: their timing depends on the values of `a`, `b` and the selected ciphertext.
+    /// If an observer can tell apart these values by timing, they can learn `cond`.
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
+    pub fn oselect(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        cond: bool,
+        a: &Ciphertext,
+        b: &Ciphertext,
+    ) -> Result<Encryption, Error> {
+        if !utils::in_mult_group(a, self.nn()) || !utils::in_mult_group(b, self.nn()) {
+            return Err(Reason::Ops.into());
+        }
+
+        let selected = utils::select_masked(cond, a, b, self.nn());
+
+        self.rerandomize(rng, &selected)
+    }
+
+    /// Homomorphic addition of two ciphertexts followed by re-randomization
+    ///
+    /// ```text
+    /// oadd_rerand(Enc(a1; r1), Enc(a2; r2)) = Enc(a1 + a2; r1 * r2 * r)
+    /// ```
+    ///
+    /// where `r` is a fresh nonce sampled using `rng`. Returns resulting ciphertext and `r`.
+    ///
+    /// ## Nonce accounting
+    /// If `nonce` is provided, it's updated as `nonce = nonce * r mod N`. When it holds the product
+    /// of nonces of `c1` and `c2` (i.e. `r1 * r2 mod N`), after the operation it holds the nonce of
+    /// the output ciphertext. This way, a chain of re-randomizing operations can be tracked with a
+    /// single nonce, see also [`omul_rerand`](Self::omul_rerand).
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
+    pub fn oadd_rerand(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        c1: &Ciphertext,
+        c2: &Ciphertext,
+        nonce: Option<&mut Nonce>,
+    ) -> Result<Encryption, Error> {
+        if let Some(nonce) = &nonce {
+            if !utils::in_mult_group(nonce, self.n()) {
+                return Err(Reason::Ops.into());
+            }
+        }
+        let sum = self.oadd(c1, c2)?;
+        let output = self.rerandomize(rng, &sum)?;
+        if let Some(nonce) = nonce {
+            *nonce *= &output.nonce;
+            *nonce %= self.n();
+        }
+        Ok(output)
+    }
+
+    /// Homomorphic multiplication of scalar at ciphertext followed by re-randomization
+    ///
+    /// ```text
+    /// omul_rerand(k, Enc(a; r1)) = Enc(k * a; r1^k * r)
+    /// ```
+    ///
+    /// where `r` is a fresh nonce sampled using `rng`. Returns resulting ciphertext and `r`.
+    ///
+    /// ## Nonce accounting
+    /// If `nonce` is provided, it's updated as `nonce = nonce^k * r mod N`. When it holds the nonce
+    /// of `ciphertext`, after the operation it holds the nonce of the output ciphertext. See also
+    /// [`oadd_rerand`](Self::oadd_rerand).
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
+    pub fn omul_rerand(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        scalar: &Integer,
+        ciphertext: &Ciphertext,
+        nonce: Option<&mut Nonce>,
+    ) -> Result<Encryption, Error> {
+        if let Some(nonce) = &nonce {
+            if !utils::in_mult_group(nonce, self.n()) {
+                return Err(Reason::Ops.into());
+            }
+        }
+        let product = self.omul(scalar, ciphertext)?;
+        let output = self.rerandomize(rng, &product)?;
+        if let Some(nonce) = nonce {
+            let nonce_pow_k: Integer = nonce
+                .pow_mod_ref(scalar, self.n())
+                .ok_or(Reason::Ops)?
+                .into();
+            *nonce = (nonce_pow_k * &output.nonce) % self.n();
+        }
+        Ok(output)
+    }
+
+    /// Re-randomizes the ciphertext with a fresh nonce, returns resulting ciphertext and the nonce
+    ///
+    /// Ciphertext needs to be valid
+    fn rerandomize(
+        &self,
+        rng: &mut (impl RngCore + CryptoRng),
+        ciphertext: &Ciphertext,
+    ) -> Result<Encryption, Error> {
+        let nonce = utils::sample_in_mult_group(rng, self.n());
+        let enc_zero = self.encrypt_with_unchecked(&Integer::ZERO, &nonce)?;
+        Ok(Encryption {
+            ciphertext: self.oadd(ciphertext, &enc_zero)?,
+            nonce,
+        })
+    }
+
     /// Checks whether `x` is `{-N/2, .., N/2}`
+    ///
+    /// Precisely, checks that `-floor(N/2) <= x <= floor((N-1)/2)`. For odd `N` (which is always
+    /// the case for valid paillier key), it's a closed interval `[-floor(N/2), floor(N/2)]`. For
+    /// even `N`, the upper bound is excluded, so that `N/2` and `-N/2` (which are congruent modulo
+    /// `N`) don't both belong to the group. In both cases, the group contains exactly `N`
+    /// elements, each corresponding to a distinct residue modulo `N`.
     pub fn in_signed_group(&self, x: &Integer) -> bool {
-        self.neg_half_n <= *x && *x <= self.half_n
+        match x.cmp_abs(self.half_n()) {
+            std::cmp::Ordering::Less => true,
+            // `|x| = floor(N/2)`: both bounds are included for odd `N`, only the lower one for even
+            std::cmp::Ordering::Equal => self.n.is_odd() || x.is_negative(),
+            std::cmp::Ordering::Greater => false,
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for EncryptionKey {
+    type Error = Error;
+
+    /// Parses an encryption key, see [`EncryptionKey::from_bytes`]
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Self::from_bytes(bytes)
+    }
+}
+
+/// Keys are equal if they have the same `N`
+impl PartialEq for EncryptionKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.n == other.n
+    }
+}
+
+impl Eq for EncryptionKey {}
+
+/// Hashes canonical big-endian bytes of `N` (same as [`to_bytes`](EncryptionKey::to_bytes)
+/// outputs), so the hash doesn't depend on how `rug` represents integers
+impl std::hash::Hash for EncryptionKey {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.to_bytes().hash(state)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rug::{Complete, Integer};
+
+    #[test]
+    fn l_unchecked_matches_l() {
+        let mut rng = rand_dev::DevRng::new();
+        let p = crate::utils::generate_safe_prime(&mut rng, 256);
+        let q = crate::utils::generate_safe_prime(&mut rng, 256);
+        let ek = super::EncryptionKey::from_n((&p * &q).complete());
+
+        for _ in 0..50 {
+            // x = 1 + kN, where k in Z*_N, satisfies both conditions checked by `l`
+            let k = crate::utils::sample_in_mult_group(&mut rng, ek.n());
+            let x = (Integer::ONE + k * ek.n()) % ek.nn();
+            assert_eq!(ek.l(&x), Some(ek.l_unchecked(&x)));
+        }
     }
 }
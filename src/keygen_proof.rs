@@ -0,0 +1,190 @@
+//! Non-interactive proof that paillier modulus is generated correctly
+//!
+//! Proof shows that `N` is a Paillier-Blum modulus, i.e. `N = pq` where `p`, `q` are primes
+//! congruent to 3 modulo 4, and `gcd(N, phi(N)) = 1`, without revealing `p` and `q`. It follows
+//! the modulus proof `Π^mod` from [CGGMP21] (Figure 16), made non-interactive via Fiat-Shamir
+//! transform with SHA-256.
+//!
+//! [CGGMP21]: https://eprint.iacr.org/2021/060
+
+use rand_core::{CryptoRng, RngCore};
+use rug::{Complete, Integer};
+use sha2::{Digest, Sha256};
+
+use crate::utils::{self, len_prefix};
+use crate::{DecryptionKey, EncryptionKey, Error, Reason};
+
+/// Domain separation tag for challenge derivation
+const CHALLENGE_TAG: &[u8] = b"fast-paillier/keygen-proof/challenge/v1";
+
+/// Amount of rounds, soundness error is `2^-ROUNDS`
+const ROUNDS: usize = 80;
+
+/// Proof that paillier key was generated correctly
+///
+/// Can be obtained via [`DecryptionKey::generate_with_correctness_proof`] or
+/// [`KeyGenProof::prove`], and checked by anyone who knows the encryption key via
+/// [`KeyGenProof::verify`]. Proof shows that `N` is a Paillier-Blum modulus, i.e. `N = pq` where
+/// `p`, `q` are primes congruent to 3 modulo 4, and `gcd(N, phi(N)) = 1`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeyGenProof {
+    /// `w` in `Z_N` with Jacobi symbol `(w | N) = -1`
+    w: Integer,
+    rounds: Vec<Round>,
+}
+
+/// Response to a single challenge `y`
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Round {
+    /// `x^4 = (-1)^a * w^b * y mod N`
+    x: Integer,
+    a: bool,
+    b: bool,
+    /// `z^N = y mod N`
+    z: Integer,
+}
+
+impl DecryptionKey {
+    /// Generates a paillier key along with a proof of its correct generation
+    ///
+    /// Key is generated via [`generate_strong`](Self::generate_strong), so `p` and `q` are safe
+    /// primes of `bits` bits each. Safe primes (except 5) are congruent to 3 modulo 4, so
+    /// the key is a Paillier-Blum modulus, which is shown by the proof.
+    pub fn generate_with_correctness_proof(
+        rng: &mut (impl RngCore + CryptoRng),
+        bits: u32,
+    ) -> Result<(Self, KeyGenProof), Error> {
+        let dk = Self::generate_strong(rng, bits)?;
+        let proof = KeyGenProof::prove(rng, &dk)?;
+        Ok((dk, proof))
+    }
+}
+
+impl KeyGenProof {
+    /// Proves that `N` of the decryption key is a Paillier-Blum modulus
+    ///
+    /// Returns error if the key is not a Paillier-Blum modulus: it has more than two primes,
+    /// any of them is not congruent to 3 modulo 4, or `gcd(N, phi(N)) != 1`.
+    pub fn prove(rng: &mut (impl RngCore + CryptoRng), dk: &DecryptionKey) -> Result<Self, Error> {
+        let n = dk.n();
+        let [p, q] = dk.primes() else {
+            return Err(Reason::NotBlumModulus.into());
+        };
+        if p.mod_u(4) != 3 || q.mod_u(4) != 3 {
+            return Err(Reason::NotBlumModulus.into());
+        }
+        let n_inv = n
+            .invert_ref(dk.phi())
+            .map(Integer::from)
+            .ok_or(Reason::NotBlumModulus)?;
+
+        let w = loop {
+            let w = utils::sample_in_mult_group(rng, n);
+            if w.jacobi(n) == -1 {
+                break w;
+            }
+        };
+
+        // `x^((p+1)/4)` is a square root of a quadratic residue `x` modulo `p`, which is itself
+        // a quadratic residue, so applying it twice gives a fourth root
+        let fourth_root_exp = |p: &Integer| -> Integer {
+            let e = (p + 1u8).complete() >> 2u32;
+            e.square().modulo(&(p - 1u8).complete())
+        };
+        let (e_p, e_q) = (fourth_root_exp(p), fourth_root_exp(q));
+
+        let rounds = challenges(n, &w)
+            .map(|y| {
+                let z = dk.pow_mod_n(&y, &n_inv)?;
+
+                // Exactly one of `±y`, `±wy` is a quadratic residue modulo both `p` and `q`, as
+                // `-1` is a non-residue modulo both primes, and `w` is a non-residue modulo
+                // exactly one of them
+                let (a, b, y) = [(false, false), (true, false), (false, true), (true, true)]
+                    .into_iter()
+                    .map(|(a, b)| (a, b, adjust(n, &w, &y, a, b)))
+                    .find(|(_, _, y)| y.legendre(p) == 1 && y.legendre(q) == 1)
+                    .ok_or(Reason::NotBlumModulus)?;
+
+                #[allow(clippy::expect_used)]
+                let x_p = (&y % p)
+                    .complete()
+                    .pow_mod(&e_p, p)
+                    .expect("exponent is non-negative");
+                #[allow(clippy::expect_used)]
+                let x_q = (&y % q)
+                    .complete()
+                    .pow_mod(&e_q, q)
+                    .expect("exponent is non-negative");
+                let x = dk
+                    .crt_mod_n()
+                    .recombine(&[x_p, x_q])
+                    .ok_or(Reason::NotBlumModulus)?;
+
+                Ok(Round { x, a, b, z })
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Self { w, rounds })
+    }
+
+    /// Verifies the proof against the encryption key
+    ///
+    /// Returns `true` if the proof shows that `N` is a Paillier-Blum modulus
+    pub fn verify(&self, ek: &EncryptionKey) -> bool {
+        let n = ek.n();
+        if n.is_even() || *n <= 1 || utils::is_probably_prime(n, utils::PRIMALITY_REPS) {
+            return false;
+        }
+        if self.rounds.len() != ROUNDS
+            || self.w.is_negative()
+            || self.w >= *n
+            || self.w.jacobi(n) != -1
+        {
+            return false;
+        }
+
+        challenges(n, &self.w).zip(&self.rounds).all(|(y, round)| {
+            let in_range = |v: &Integer| !v.is_negative() && v < n;
+            if !in_range(&round.x) || !in_range(&round.z) {
+                return false;
+            }
+            let z_n = round.z.pow_mod_ref(n, n).map(Integer::from);
+            let x_4 = round.x.pow_mod_ref(&Integer::from(4), n).map(Integer::from);
+            z_n.as_ref() == Some(&y)
+                && x_4.as_ref() == Some(&adjust(n, &self.w, &y, round.a, round.b))
+        })
+    }
+}
+
+/// Computes `(-1)^a * w^b * y mod N`
+fn adjust(n: &Integer, w: &Integer, y: &Integer, a: bool, b: bool) -> Integer {
+    let mut y = y.clone();
+    if b {
+        y = (y * w) % n;
+    }
+    if a {
+        y = n - y;
+    }
+    y
+}
+
+/// Derives [`ROUNDS`] challenges in `Z*_N` from `N` and `w`
+///
+/// Each challenge is obtained via [`utils::hash_to_mult_group`] with the round index appended to
+/// the hashed inputs.
+fn challenges<'a>(n: &'a Integer, w: &Integer) -> impl Iterator<Item = Integer> + 'a {
+    let n_bytes = n.to_digits::<u8>(rug::integer::Order::Msf);
+    let w_bytes = w.to_digits::<u8>(rug::integer::Order::Msf);
+    let seed = Sha256::new()
+        .chain_update(CHALLENGE_TAG)
+        .chain_update(len_prefix(&n_bytes))
+        .chain_update(&n_bytes)
+        .chain_update(len_prefix(&w_bytes))
+        .chain_update(&w_bytes);
+
+    (0..ROUNDS as u64)
+        .map(move |i| utils::hash_to_mult_group(&seed.clone().chain_update(i.to_be_bytes()), n))
+}
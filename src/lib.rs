@@ -1,48 +1,245 @@
 #![doc = include_str!("../README.md")]
 #![forbid(missing_docs)]
 
+mod accumulator;
+mod benchmark;
 mod decryption_key;
+#[cfg(feature = "deterministic-encryption")]
+mod deterministic;
 mod encryption_key;
+#[cfg(feature = "keygen-proof")]
+mod keygen_proof;
+mod pool;
+#[cfg(feature = "postcard")]
+mod postcard;
+pub mod prelude;
+mod prepared;
 pub mod utils;
 
 #[cfg(feature = "serde")]
 mod serde;
+mod tagged;
+#[cfg(feature = "test-vectors")]
+pub mod test_vectors;
+mod tracker;
 
 use std::fmt;
 
 use rand_core::{CryptoRng, RngCore};
 use rug::Integer;
 
+/// Re-export of [`rug`] crate which this library is built on
+///
+/// Use it to be sure that you use exactly the same version of `rug` as the library does,
+/// without having to add `rug` to your dependencies.
+pub use rug;
+
+/// Big integer type used in the public API of the library
+///
+/// It's an alias to [`rug::Integer`]. [`Ciphertext`], [`Plaintext`], and [`Nonce`] are all
+/// represented as `BigInt`.
+///
+/// ## Example
+/// ```rust
+/// use fast_paillier::{BigInt, DecryptionKey};
+///
+/// # fn main() -> Result<(), fast_paillier::Error> {
+/// # let mut rng = rand_dev::DevRng::new();
+/// # let p = fast_paillier::utils::generate_safe_prime(&mut rng, 256);
+/// # let q = fast_paillier::utils::generate_safe_prime(&mut rng, 256);
+/// let dk = DecryptionKey::from_primes(p, q)?;
+/// let x = BigInt::from(42);
+/// let ciphertext = dk.encryption_key().encrypt_with_random(&mut rng, &x)?.ciphertext;
+/// assert_eq!(dk.decrypt(&ciphertext)?, x);
+/// # Ok(()) }
+/// ```
+pub type BigInt = Integer;
+
 /// Paillier ciphertext
+///
+/// All ciphertexts output by the library (by encryption or homomorphic operations) are in
+/// canonical form, i.e. they're in `{0, .., N^2 - 1}`, so two ciphertexts can be compared
+/// directly.
 pub type Ciphertext = Integer;
 /// Paillier plaintext
 pub type Plaintext = Integer;
 /// Paillier nonce
 pub type Nonce = Integer;
 
-pub use self::{decryption_key::DecryptionKey, encryption_key::EncryptionKey};
+/// Ciphertext along with the nonce it was encrypted with
+///
+/// Returned by functions that sample the nonce, such as
+/// [`encrypt_with_random`](EncryptionKey::encrypt_with_random). Ciphertext and nonce are both
+/// represented as [`BigInt`], so named fields prevent mixing them up. Can be converted into
+/// a `(ciphertext, nonce)` tuple.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Encryption {
+    /// Ciphertext
+    pub ciphertext: Ciphertext,
+    /// Nonce used to produce the ciphertext
+    pub nonce: Nonce,
+}
+
+impl From<Encryption> for (Ciphertext, Nonce) {
+    fn from(encryption: Encryption) -> Self {
+        (encryption.ciphertext, encryption.nonce)
+    }
+}
+
+#[cfg(feature = "deterministic-encryption")]
+pub use self::deterministic::DeterministicCache;
+#[cfg(feature = "keygen-proof")]
+pub use self::keygen_proof::KeyGenProof;
+pub use self::{
+    accumulator::{NoRng, RawProduct, SumAccumulator},
+    benchmark::BenchmarkReport,
+    decryption_key::DecryptionKey,
+    encryption_key::EncryptionKey,
+    prepared::PreparedCiphertexts,
+    tagged::TaggedCiphertext,
+    tracker::PlaintextTracker,
+};
+
+/// Checks that encryption and decryption keys form a pair, i.e. they have the same `N`
+///
+/// Useful when keys are received separately, to guard against mixing up key material from
+/// different generations. See also [`verify_key_pair_roundtrip`] which checks that keys are
+/// functionally paired.
+pub fn verify_key_pair(ek: &EncryptionKey, dk: &DecryptionKey) -> bool {
+    ek.n() == dk.n()
+}
+
+/// Checks that encryption and decryption keys form a pair by encryption round trip
+///
+/// Encrypts a random plaintext with `ek` and checks that `dk` decrypts it correctly. Returns
+/// `false` if keys are not paired, or if any step of the round trip fails.
+pub fn verify_key_pair_roundtrip(
+    rng: &mut (impl RngCore + CryptoRng),
+    ek: &EncryptionKey,
+    dk: &DecryptionKey,
+) -> bool {
+    let x = utils::random_in_signed_group(rng, ek);
+    ek.encrypt_with_random(rng, &x)
+        .and_then(|encryption| dk.decrypt(&encryption.ciphertext))
+        .is_ok_and(|decrypted| decrypted == x)
+}
 
 /// Error type used in the library
 #[derive(Debug, thiserror::Error)]
 #[error(transparent)]
 pub struct Error(#[from] Reason);
 
+impl Error {
+    /// Returns the kind of the error
+    pub fn kind(&self) -> ErrorKind {
+        match &self.0 {
+            Reason::InvalidPQ | Reason::InvalidN | Reason::SelfTestFailed => ErrorKind::InvalidKey,
+            #[cfg(feature = "keygen-proof")]
+            Reason::NotBlumModulus => ErrorKind::InvalidKey,
+            Reason::InvalidEncoding => ErrorKind::InvalidEncoding,
+            Reason::CorruptedBundle => ErrorKind::Corrupted,
+            #[cfg(feature = "postcard")]
+            Reason::UnknownVersion(_) => ErrorKind::InvalidEncoding,
+            Reason::Encrypt | Reason::EncryptAt(_) => ErrorKind::Encrypt,
+            Reason::Decrypt | Reason::DecryptAt(_) | Reason::ContextMismatch => ErrorKind::Decrypt,
+            Reason::Ops
+            | Reason::OpsAt(_)
+            | Reason::LengthMismatch { .. }
+            | Reason::KeyMismatch => ErrorKind::Ops,
+            Reason::BuildFastExp(_) | Reason::Bug(_) => ErrorKind::Internal,
+        }
+    }
+
+    /// Returns position of the element that caused the error in a batch operation
+    ///
+    /// Returns `None` if error wasn't caused by a batch operation such as
+    /// [`DecryptionKey::decrypt_all`], [`EncryptionKey::encrypt_many`], or
+    /// [`EncryptionKey::osum`], or if it was caused by a batch as a whole (e.g. lengths of
+    /// inputs don't match)
+    pub fn index(&self) -> Option<usize> {
+        match &self.0 {
+            Reason::EncryptAt(i) | Reason::DecryptAt(i) | Reason::OpsAt(i) => Some(*i),
+            _ => None,
+        }
+    }
+}
+
+/// Kind of [`Error`]
+///
+/// With `serde` feature enabled, the kind can be serialized, e.g. to communicate the failure
+/// category to a remote party. [`Error`] itself is not serializable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Key is well-formed, but its value doesn't correspond to a valid paillier key
+    InvalidKey,
+    /// Key encoding is malformed (e.g. it has wrong length)
+    InvalidEncoding,
+    /// Stored data is corrupted (checksum mismatch)
+    Corrupted,
+    /// Encryption failed as inputs are not in valid range
+    Encrypt,
+    /// Decryption failed as ciphertext is not valid
+    Decrypt,
+    /// Homomorphic operation failed as inputs are not valid
+    Ops,
+    /// Internal error
+    Internal,
+}
+
 #[derive(Debug, thiserror::Error)]
 enum Reason {
     #[error("p,q are invalid")]
     InvalidPQ,
+    #[error("N is invalid")]
+    InvalidN,
+    #[error("key self-test failed")]
+    SelfTestFailed,
+    #[cfg(feature = "keygen-proof")]
+    #[error("N is not a Paillier-Blum modulus")]
+    NotBlumModulus,
+    #[error("invalid key encoding")]
+    InvalidEncoding,
+    #[error("recovery bundle is corrupted: checksum mismatch")]
+    CorruptedBundle,
+    #[cfg(feature = "postcard")]
+    #[error("unknown encoding version: {0}")]
+    UnknownVersion(u8),
     #[error("encryption error")]
     Encrypt,
+    #[error("encryption error: plaintext at position {0} is out of range")]
+    EncryptAt(usize),
     #[error("decryption error")]
     Decrypt,
+    #[error("decryption error: ciphertext at position {0} is invalid")]
+    DecryptAt(usize),
+    #[error("ciphertext was encrypted with different context")]
+    ContextMismatch,
     #[error("homomorphic operation failed: invalid inputs")]
     Ops,
+    #[error("homomorphic operation failed: ciphertext at position {0} is invalid")]
+    OpsAt(usize),
+    #[error("homomorphic operation failed: expected {expected} elements, got {actual}")]
+    LengthMismatch { expected: usize, actual: usize },
+    #[error("ciphertext is tagged by another key")]
+    KeyMismatch,
     #[error("could not precompute data for faster exponentiation")]
-    BuildFastExp,
+    BuildFastExp(#[source] utils::CrtBuildError),
     #[error("bug occurred")]
     Bug(#[source] Bug),
 }
 
+/// Validates lengths of batch operation inputs, returns error if they don't match
+///
+/// Empty batches are valid: it's up to the operation to define its output on empty input.
+fn ensure_same_len(expected: usize, actual: usize) -> Result<(), Error> {
+    if expected != actual {
+        return Err(Reason::LengthMismatch { expected, actual }.into());
+    }
+    Ok(())
+}
+
 #[derive(Debug, thiserror::Error)]
 enum Bug {
     #[error("pow mod undefined")]
@@ -83,6 +280,37 @@ mod sealed {
 ///     Ok(ciphertext)
 /// }
 /// ```
+///
+/// Batch operations are available through the trait object as well:
+///
+/// ```rust
+/// use fast_paillier::{AnyEncryptionKey, Ciphertext, Error};
+/// use rug::Integer;
+///
+/// fn weighted_sum(
+///     ek: &dyn AnyEncryptionKey,
+///     weights: &[Integer],
+///     ciphertexts: &[Ciphertext],
+/// ) -> Result<(Ciphertext, Ciphertext), Error> {
+///     Ok((ek.osum(ciphertexts)?, ek.odot(weights, ciphertexts)?))
+/// }
+///
+/// # fn main() -> Result<(), Error> {
+/// # let mut rng = rand_dev::DevRng::new();
+/// # let p = fast_paillier::utils::generate_safe_prime(&mut rng, 256);
+/// # let q = fast_paillier::utils::generate_safe_prime(&mut rng, 256);
+/// # let dk = fast_paillier::DecryptionKey::from_primes(p, q)?;
+/// let mut encrypt = |x: i32| dk.encryption_key().encrypt_with_random(&mut rng, &x.into());
+/// let ciphertexts = [encrypt(1)?.ciphertext, encrypt(2)?.ciphertext, encrypt(3)?.ciphertext];
+/// let weights = [4, 5, 6].map(Integer::from);
+///
+/// for ek in [&dk as &dyn AnyEncryptionKey, dk.encryption_key()] {
+///     let (sum, dot) = weighted_sum(ek, &weights, &ciphertexts)?;
+///     assert_eq!(dk.decrypt(&sum)?, 6);
+///     assert_eq!(dk.decrypt(&dot)?, 4 + 10 + 18);
+/// }
+/// # Ok(()) }
+/// ```
 pub trait AnyEncryptionKey: sealed::Sealed {
     /// Returns `N`
     fn n(&self) -> &Integer;
@@ -91,7 +319,7 @@ pub trait AnyEncryptionKey: sealed::Sealed {
     /// Returns `N/2`
     fn half_n(&self) -> &Integer;
 
-    /// Encrypts the plaintext `x` in `{-N/2, .., N_2}` with `nonce` in `Z*_n`
+    /// Encrypts the plaintext `x` in `{-N/2, .., N/2}` with `nonce` in `Z*_n`
     ///
     /// Returns error if inputs are not in specified range
     fn encrypt_with(&self, x: &Plaintext, nonce: &Nonce) -> Result<Ciphertext, Error>;
@@ -121,13 +349,33 @@ pub trait AnyEncryptionKey: sealed::Sealed {
     /// ```
     fn oneg(&self, ciphertext: &Ciphertext) -> Result<Ciphertext, Error>;
 
+    /// Homomorphic sum of ciphertexts
+    ///
+    /// ```text
+    /// osum([Enc(a1), ..., Enc(ak)]) = Enc(a1 + ... + ak)
+    /// ```
+    ///
+    /// Sum of empty list is `1`, which is a (non-randomized) encryption of zero. Returns error
+    /// if any of ciphertexts is invalid, the error carries its [index](Error::index).
+    fn osum(&self, ciphertexts: &[Ciphertext]) -> Result<Ciphertext, Error>;
+    /// Homomorphic inner product of scalars and ciphertexts
+    ///
+    /// ```text
+    /// odot([a1, ..., ak], [Enc(c1), ..., Enc(ck)]) = Enc(a1 * c1 + ... + ak * ck)
+    /// ```
+    ///
+    /// Returns error if amounts of scalars and ciphertexts don't match, or if any ciphertext is
+    /// invalid. Inner product of empty lists is `1`, which is a (non-randomized) encryption of
+    /// zero.
+    fn odot(&self, scalars: &[Integer], ciphertexts: &[Ciphertext]) -> Result<Ciphertext, Error>;
+
     /// Checks whether `x` is `{-N/2, .., N/2}`
     fn in_signed_group(&self, x: &Integer) -> bool;
 }
 
 /// Additional functionality implemented for [AnyEncryptionKey]
 pub trait AnyEncryptionKeyExt: AnyEncryptionKey {
-    /// Encrypts the plaintext `x` in `{-N/2, .., N_2}`
+    /// Encrypts the plaintext `x` in `{-N/2, .., N/2}`
     ///
     /// Nonce is sampled randomly using `rng`.
     ///
@@ -136,7 +384,7 @@ pub trait AnyEncryptionKeyExt: AnyEncryptionKey {
         &self,
         rng: &mut (impl RngCore + CryptoRng),
         x: &Plaintext,
-    ) -> Result<(Ciphertext, Nonce), Error>;
+    ) -> Result<Encryption, Error>;
 }
 
 impl<E: AnyEncryptionKey> AnyEncryptionKeyExt for E {
@@ -144,10 +392,10 @@ impl<E: AnyEncryptionKey> AnyEncryptionKeyExt for E {
         &self,
         rng: &mut (impl RngCore + CryptoRng),
         x: &Plaintext,
-    ) -> Result<(Ciphertext, Nonce), Error> {
+    ) -> Result<Encryption, Error> {
         let nonce = utils::sample_in_mult_group(rng, self.n());
         let ciphertext = self.encrypt_with(x, &nonce)?;
-        Ok((ciphertext, nonce))
+        Ok(Encryption { ciphertext, nonce })
     }
 }
 
@@ -184,6 +432,14 @@ impl AnyEncryptionKey for EncryptionKey {
         self.oneg(ciphertext)
     }
 
+    fn osum(&self, ciphertexts: &[Ciphertext]) -> Result<Ciphertext, Error> {
+        self.osum(ciphertexts)
+    }
+
+    fn odot(&self, scalars: &[Integer], ciphertexts: &[Ciphertext]) -> Result<Ciphertext, Error> {
+        self.odot(scalars, ciphertexts)
+    }
+
     fn in_signed_group(&self, x: &Integer) -> bool {
         self.in_signed_group(x)
     }
@@ -222,6 +478,14 @@ impl AnyEncryptionKey for DecryptionKey {
         self.encryption_key().oneg(ciphertext)
     }
 
+    fn osum(&self, ciphertexts: &[Ciphertext]) -> Result<Ciphertext, Error> {
+        self.encryption_key().osum(ciphertexts)
+    }
+
+    fn odot(&self, scalars: &[Integer], ciphertexts: &[Ciphertext]) -> Result<Ciphertext, Error> {
+        self.encryption_key().odot(scalars, ciphertexts)
+    }
+
     fn in_signed_group(&self, x: &Integer) -> bool {
         self.encryption_key().in_signed_group(x)
     }
@@ -234,3 +498,32 @@ impl<'a> fmt::Debug for dyn AnyEncryptionKey + 'a {
             .finish_non_exhaustive()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::error::Error as _;
+
+    // Keys and precomputed data are meant to be shared across threads. `rug::Integer` is
+    // `Send + Sync`, this makes sure it stays the case for types built on top of it.
+    const _: fn() = || {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<crate::EncryptionKey>();
+        assert_send_sync::<crate::DecryptionKey>();
+        assert_send_sync::<crate::utils::CrtExp>();
+        assert_send_sync::<crate::utils::Exponent>();
+        assert_send_sync::<crate::PreparedCiphertexts>();
+        assert_send_sync::<crate::Encryption>();
+        assert_send_sync::<crate::Error>();
+    };
+
+    #[test]
+    fn bug_is_error_source() {
+        let err = crate::Error::from(crate::Bug::PowModUndef);
+        assert_eq!(err.kind(), crate::ErrorKind::Internal);
+        assert_eq!(err.to_string(), "bug occurred");
+
+        let source = err.source().expect("bug must be a source of the error");
+        assert_eq!(source.to_string(), "pow mod undefined");
+        assert!(source.source().is_none());
+    }
+}
@@ -0,0 +1,111 @@
+//! Thread-local pool of reusable integers used as scratch space in hot paths
+//!
+//! When `pool` feature is enabled, integers given back via [`give`] are kept in a thread-local
+//! pool, and [`take`] reuses them instead of allocating. Pool is bounded both in the amount of
+//! integers and in their size, and integers are wiped before they're put into the pool as they
+//! may hold secret intermediate values (e.g. residues modulo secret primes). Pool is dropped when
+//! the thread exits.
+//!
+//! Without `pool` feature, [`take`] always allocates and [`give`] drops the integer, so hot paths
+//! can be written once regardless of the feature.
+
+use rug::{Assign, Integer};
+
+/// Max amount of integers kept in the pool of a single thread
+#[cfg_attr(not(feature = "pool"), allow(dead_code))]
+const MAX_POOLED: usize = 16;
+
+/// Integers with larger capacity (in bits) are not pooled
+///
+/// It's enough to hold `N^2` for 4096 bits `N`
+#[cfg_attr(not(feature = "pool"), allow(dead_code))]
+const MAX_POOLED_BITS: usize = 8192 + 2 * 64;
+
+#[cfg(feature = "pool")]
+thread_local! {
+    static POOL: std::cell::RefCell<Vec<Integer>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// Takes an integer from the pool, or allocates a new one if the pool is empty
+///
+/// Returned integer is zero
+pub(crate) fn take() -> Integer {
+    #[cfg(feature = "pool")]
+    if let Some(x) = POOL.with(|pool| pool.borrow_mut().pop()) {
+        return x;
+    }
+    Integer::new()
+}
+
+/// Gives the integer back to the pool
+///
+/// Integer is wiped before being put into the pool. It's dropped if the pool is full, or if
+/// the integer is too large.
+pub(crate) fn give(x: Integer) {
+    #[cfg(feature = "pool")]
+    {
+        if x.capacity() > MAX_POOLED_BITS {
+            return;
+        }
+        let mut x = x;
+        wipe(&mut x);
+        POOL.with(|pool| {
+            let mut pool = pool.borrow_mut();
+            if pool.len() < MAX_POOLED {
+                pool.push(x)
+            }
+        })
+    }
+    #[cfg(not(feature = "pool"))]
+    drop(x)
+}
+
+/// Overwrites all allocated limbs of `x` with zeroes and sets `x` to zero
+#[cfg_attr(not(feature = "pool"), allow(dead_code))]
+fn wipe(x: &mut Integer) {
+    let capacity = x.capacity();
+    if capacity == 0 {
+        return;
+    }
+    // Setting the highest bit of zero fills all the limbs below it with zeroes
+    x.assign(0);
+    x.set_bit(u32::try_from(capacity - 1).unwrap_or(u32::MAX), true);
+    x.assign(0);
+}
+
+#[cfg(all(test, feature = "pool"))]
+mod test {
+    use rug::Integer;
+
+    fn pool_len() -> usize {
+        super::POOL.with(|pool| pool.borrow().len())
+    }
+
+    #[test]
+    fn pooled_integers_are_reused_and_wiped() {
+        let x = Integer::from(Integer::u_pow_u(3, 1000));
+        let capacity = x.capacity();
+        super::give(x);
+        assert_eq!(pool_len(), 1);
+
+        let y = super::take();
+        assert_eq!(pool_len(), 0);
+        assert_eq!(y, 0);
+        assert_eq!(y.capacity(), capacity);
+    }
+
+    #[test]
+    fn pool_is_bounded() {
+        for _ in 0..2 * super::MAX_POOLED {
+            super::give(Integer::with_capacity(1024));
+        }
+        assert_eq!(pool_len(), super::MAX_POOLED);
+
+        // Too large integers are not pooled
+        while pool_len() > 0 {
+            super::take();
+        }
+        super::give(Integer::with_capacity(super::MAX_POOLED_BITS + 1024));
+        assert_eq!(pool_len(), 0);
+    }
+}
@@ -0,0 +1,80 @@
+//! Versioned binary encoding of the keys based on [`postcard`](::postcard)
+//!
+//! Unlike serde implementations, the format doesn't depend on how `rug` serializes integers.
+//! Encoding starts with a 1-byte version followed by the key itself. In version 1, the
+//! encryption key is encoded as length-prefixed big-endian `N`, and the decryption key is
+//! encoded as a length-prefixed sequence of length-prefixed big-endian primes.
+
+use rug::Integer;
+
+use crate::{DecryptionKey, EncryptionKey, Error, Reason};
+
+/// Current version of the encoding
+const VERSION: u8 = 1;
+
+impl EncryptionKey {
+    /// Encodes the key using versioned [`postcard`](::postcard) schema
+    ///
+    /// Output can be parsed back via [`EncryptionKey::from_postcard`]
+    pub fn to_postcard(&self) -> Vec<u8> {
+        let n = self.n().to_digits::<u8>(rug::integer::Order::Msf);
+        encode(&n)
+    }
+
+    /// Decodes the key encoded via [`EncryptionKey::to_postcard`]
+    ///
+    /// Returns error if encoding version is unknown, if bytes are malformed, or if `N` is not
+    /// an odd number greater than 1.
+    pub fn from_postcard(bytes: &[u8]) -> Result<Self, Error> {
+        let n: &[u8] = decode(bytes)?;
+        Self::from_bytes(n)
+    }
+}
+
+impl DecryptionKey {
+    /// Encodes the key using versioned [`postcard`](::postcard) schema
+    ///
+    /// Output can be parsed back via [`DecryptionKey::from_postcard`]
+    pub fn to_postcard(&self) -> Vec<u8> {
+        let primes = self
+            .primes()
+            .iter()
+            .map(|p| p.to_digits::<u8>(rug::integer::Order::Msf))
+            .collect::<Vec<_>>();
+        encode(&primes)
+    }
+
+    /// Decodes the key encoded via [`DecryptionKey::to_postcard`]
+    ///
+    /// Returns error if encoding version is unknown, if bytes are malformed, or if primes
+    /// don't form a valid key.
+    pub fn from_postcard(bytes: &[u8]) -> Result<Self, Error> {
+        let primes: Vec<&[u8]> = decode(bytes)?;
+        if primes.len() < 2 || primes.iter().any(|p| p.is_empty()) {
+            return Err(Reason::InvalidEncoding.into());
+        }
+        let primes = primes
+            .into_iter()
+            .map(|p| Integer::from_digits(p, rug::integer::Order::Msf))
+            .collect();
+        Self::from_multiprime(primes)
+    }
+}
+
+fn encode<T: serde::Serialize + ?Sized>(value: &T) -> Vec<u8> {
+    #[allow(clippy::expect_used)]
+    ::postcard::to_allocvec(&(VERSION, value)).expect("serialization into vec never fails")
+}
+
+fn decode<'de, T: serde::Deserialize<'de>>(bytes: &'de [u8]) -> Result<T, Error> {
+    let (version, bytes) =
+        ::postcard::take_from_bytes::<u8>(bytes).map_err(|_| Reason::InvalidEncoding)?;
+    if version != VERSION {
+        return Err(Reason::UnknownVersion(version).into());
+    }
+    let (value, rest) = ::postcard::take_from_bytes(bytes).map_err(|_| Reason::InvalidEncoding)?;
+    if !rest.is_empty() {
+        return Err(Reason::InvalidEncoding.into());
+    }
+    Ok(value)
+}
@@ -0,0 +1,34 @@
+//! Commonly used types, traits, and functions
+//!
+//! Brings into scope both key types, [`AnyEncryptionKey`] and [`AnyEncryptionKeyExt`] traits,
+//! error types, and the most commonly used [`utils`](crate::utils).
+//!
+//! ## Example
+//! ```rust
+//! use fast_paillier::prelude::*;
+//!
+//! // `encrypt_with_random` is provided by `AnyEncryptionKeyExt` which is in scope
+//! fn encrypt_42(
+//!     ek: &impl AnyEncryptionKey,
+//!     rng: &mut (impl rand_core::RngCore + rand_core::CryptoRng),
+//! ) -> Result<Ciphertext, Error> {
+//!     let ciphertext = ek.encrypt_with_random(rng, &BigInt::from(42))?.ciphertext;
+//!     Ok(ciphertext)
+//! }
+//!
+//! # fn main() -> Result<(), Error> {
+//! let mut rng = rand_dev::DevRng::new();
+//! let p = generate_safe_prime(&mut rng, 256);
+//! let q = generate_safe_prime(&mut rng, 256);
+//! let dk = DecryptionKey::from_primes(p, q)?;
+//!
+//! let ciphertext = encrypt_42(dk.encryption_key(), &mut rng)?;
+//! assert_eq!(dk.decrypt(&ciphertext)?, 42);
+//! # Ok(()) }
+//! ```
+
+pub use crate::utils::{generate_safe_prime, in_mult_group, sample_in_mult_group, ExternalRand64};
+pub use crate::{
+    AnyEncryptionKey, AnyEncryptionKeyExt, BigInt, Ciphertext, DecryptionKey, EncryptionKey, Error,
+    ErrorKind, Nonce, Plaintext,
+};
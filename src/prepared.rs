@@ -0,0 +1,127 @@
+use std::fmt;
+
+use rug::Integer;
+
+use crate::{utils, Ciphertext, EncryptionKey};
+use crate::{Error, Reason};
+
+/// Size of window (in bits) used for fixed-base exponentiation
+const WINDOW: u32 = 4;
+
+/// Ciphertexts prepared for fast [homomorphic inner product](PreparedCiphertexts::odot)
+///
+/// Holds precomputed powers `c_i^(2^(4j)) mod N^2` for each ciphertext `c_i`, which speeds up
+/// exponentiations when the same ciphertexts are multiplied by many different scalar vectors
+/// (e.g. when a fixed encrypted database is queried many times). Takes `bits(N) / 4` integers
+/// of size `N^2` per ciphertext.
+///
+/// Can be obtained via [`EncryptionKey::prepare_odot`].
+#[derive(Clone)]
+pub struct PreparedCiphertexts<'k> {
+    ek: &'k EncryptionKey,
+    /// `tables[i][j] = c_i^(2^(WINDOW * j)) mod N^2`
+    tables: Vec<Vec<Integer>>,
+}
+
+impl<'k> PreparedCiphertexts<'k> {
+    pub(crate) fn new(ek: &'k EncryptionKey, ciphertexts: &[Ciphertext]) -> Result<Self, Error> {
+        let windows = ek.n().significant_bits().div_ceil(WINDOW);
+        let tables = ciphertexts
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                if !utils::in_mult_group(c, ek.nn()) {
+                    return Err(Reason::OpsAt(i).into());
+                }
+                let mut table = Vec::with_capacity(windows as usize);
+                let mut power = c.clone();
+                for _ in 0..windows {
+                    let next = power
+                        .pow_mod_ref(&Integer::from(1u32 << WINDOW), ek.nn())
+                        .ok_or(Reason::Ops)?
+                        .into();
+                    table.push(power);
+                    power = next;
+                }
+                Ok(table)
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Self { ek, tables })
+    }
+
+    /// Amount of prepared ciphertexts
+    pub fn len(&self) -> usize {
+        self.tables.len()
+    }
+
+    /// Checks whether there are no prepared ciphertexts
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// Homomorphic inner product of prepared ciphertexts and scalars
+    ///
+    /// ```text
+    /// odot([a1, ..., ak], [Enc(c1), ..., Enc(ck)]) = Enc(a1 * c1 + ... + ak * ck)
+    /// ```
+    ///
+    /// Output is the same as [`EncryptionKey::odot`] produces. Returns error if amount of
+    /// scalars doesn't match amount of prepared ciphertexts, or if any scalar is not in
+    /// `{-N, .., N}`.
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
+    pub fn odot(&self, scalars: &[Integer]) -> Result<Ciphertext, Error> {
+        crate::ensure_same_len(self.tables.len(), scalars.len())?;
+        let nn = self.ek.nn();
+
+        // Buckets for positive and negative scalars: `buckets[d]` is a product of all
+        // precomputed powers which need to be raised to power `d`
+        let mut pos_buckets: [Integer; 1 << WINDOW] = Default::default();
+        let mut neg_buckets: [Integer; 1 << WINDOW] = Default::default();
+        pos_buckets.iter_mut().for_each(|b| *b = Integer::from(1));
+        neg_buckets.iter_mut().for_each(|b| *b = Integer::from(1));
+
+        for (scalar, table) in scalars.iter().zip(&self.tables) {
+            if scalar.cmp_abs(self.ek.n()).is_gt() {
+                return Err(Reason::Ops.into());
+            }
+            let buckets = if scalar.cmp0().is_lt() {
+                &mut neg_buckets
+            } else {
+                &mut pos_buckets
+            };
+            let digits = scalar.to_digits::<u8>(rug::integer::Order::Lsf);
+            let digits = digits.iter().flat_map(|byte| [byte & 0xf, byte >> 4]);
+            for (digit, power) in digits.zip(table) {
+                if digit != 0 {
+                    let bucket = &mut buckets[usize::from(digit)];
+                    *bucket *= power;
+                    *bucket %= nn;
+                }
+            }
+        }
+
+        let pos = Self::combine_buckets(pos_buckets, nn);
+        let neg = Self::combine_buckets(neg_buckets, nn);
+        let neg_inv = neg.invert(nn).map_err(|_| Reason::Ops)?;
+        Ok((pos * neg_inv) % nn)
+    }
+
+    /// Computes `prod_d buckets[d]^d mod nn`
+    fn combine_buckets(buckets: [Integer; 1 << WINDOW], nn: &Integer) -> Integer {
+        let mut acc = Integer::from(1);
+        let mut running = Integer::from(1);
+        for bucket in buckets.into_iter().skip(1).rev() {
+            running = (running * bucket) % nn;
+            acc = (acc * &running) % nn;
+        }
+        acc
+    }
+}
+
+impl fmt::Debug for PreparedCiphertexts<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PreparedCiphertexts")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
@@ -1,4 +1,31 @@
+//! Serde implementations of the keys
+//!
+//! Representation depends on whether serializer is
+//! [human-readable](serde::Serializer::is_human_readable):
+//!
+//! * Human-readable formats (e.g. JSON) use a self-describing representation that doesn't depend
+//!   on `rug`. Integers are encoded as lowercase big-endian hex strings without prefix.
+//!   * `EncryptionKey` is `{"n": "<hex>"}`
+//!   * `DecryptionKey` is `{"p": "<hex>", "q": "<hex>"}`. Multi-prime keys are encoded as
+//!     `{"primes": ["<hex>", ...]}`.
+//!
+//!   Keys serialized by earlier releases, where integers were encoded by `rug` (e.g.
+//!   `{"radix": 16, "value": "<hex>"}`), are still accepted by deserialization.
+//! * Binary formats use `rug` serialization of `N` for `EncryptionKey`. `DecryptionKey` is encoded
+//!   as a tuple `(p, q)`, the same way as in the first release of the crate. Multi-prime keys are
+//!   encoded as a tuple `(tag, primes)` where `tag` is a negative integer identifying the format
+//!   version (`-1` for the current one), and `primes` is a sequence of primes. Primes are always
+//!   positive, so two shapes can be told apart by the first element even in formats that are not
+//!   self-describing (e.g. bincode).
+//!
+//! `N` of deserialized `EncryptionKey` is checked to be an odd number greater than 1 regardless of
+//! the format.
+
+use std::fmt;
+
 use rug::Integer;
+use serde::de::Error as _;
+use serde::ser::SerializeTuple;
 
 use crate::{DecryptionKey, EncryptionKey};
 
@@ -7,7 +34,14 @@ impl serde::Serialize for EncryptionKey {
     where
         S: serde::Serializer,
     {
-        self.n().serialize(serializer)
+        if serializer.is_human_readable() {
+            EncryptionKeyRepr {
+                n: Hex(self.n().clone()),
+            }
+            .serialize(serializer)
+        } else {
+            self.n().serialize(serializer)
+        }
     }
 }
 
@@ -16,8 +50,16 @@ impl<'de> serde::Deserialize<'de> for EncryptionKey {
     where
         D: serde::Deserializer<'de>,
     {
-        let n = Integer::deserialize(deserializer)?;
-        Ok(EncryptionKey::from_n(n))
+        let n = if deserializer.is_human_readable() {
+            match EncryptionKeyHumanRepr::deserialize(deserializer)? {
+                EncryptionKeyHumanRepr::Hex(EncryptionKeyRepr { n: Hex(n) }) => n,
+                EncryptionKeyHumanRepr::Legacy(n) => n,
+            }
+        } else {
+            Integer::deserialize(deserializer)?
+        };
+        EncryptionKey::from_n_checked(n)
+            .map_err(|_| <D::Error as serde::de::Error>::custom("invalid paillier key"))
     }
 }
 
@@ -26,8 +68,31 @@ impl serde::Serialize for DecryptionKey {
     where
         S: serde::Serializer,
     {
-        let pq = [self.p(), self.q()];
-        pq.serialize(serializer)
+        if serializer.is_human_readable() {
+            let repr = match self.primes() {
+                [p, q] => DecryptionKeyRepr::TwoPrimes(TwoPrimesRepr {
+                    p: Hex(p.clone()),
+                    q: Hex(q.clone()),
+                }),
+                primes => DecryptionKeyRepr::Multiprime(MultiprimeRepr {
+                    primes: primes.iter().cloned().map(Hex).collect(),
+                }),
+            };
+            repr.serialize(serializer)
+        } else {
+            let mut tuple = serializer.serialize_tuple(2)?;
+            match self.primes() {
+                [p, q] => {
+                    tuple.serialize_element(p)?;
+                    tuple.serialize_element(q)?;
+                }
+                primes => {
+                    tuple.serialize_element(&Integer::from(MULTIPRIME_V1))?;
+                    tuple.serialize_element(primes)?;
+                }
+            }
+            tuple.end()
+        }
     }
 }
 
@@ -36,8 +101,141 @@ impl<'de> serde::Deserialize<'de> for DecryptionKey {
     where
         D: serde::Deserializer<'de>,
     {
-        let [p, q] = <[Integer; 2]>::deserialize(deserializer)?;
-        DecryptionKey::from_primes(p, q)
+        let DecryptionKeyPrimes(primes) = DecryptionKeyPrimes::deserialize(deserializer)?;
+        DecryptionKey::from_multiprime_deferred(primes)
             .map_err(|_| <D::Error as serde::de::Error>::custom("invalid paillier key"))
     }
 }
+
+/// Primes of serialized [`DecryptionKey`], parsed without constructing the key
+///
+/// Accepts the same representations as `DecryptionKey`, but doesn't validate the primes
+pub(crate) struct DecryptionKeyPrimes(pub Vec<Integer>);
+
+impl<'de> serde::Deserialize<'de> for DecryptionKeyPrimes {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let primes = if deserializer.is_human_readable() {
+            match DecryptionKeyRepr::deserialize(deserializer)? {
+                DecryptionKeyRepr::TwoPrimes(TwoPrimesRepr {
+                    p: Hex(p),
+                    q: Hex(q),
+                }) => vec![p, q],
+                DecryptionKeyRepr::Multiprime(MultiprimeRepr { primes }) => {
+                    primes.into_iter().map(|Hex(p)| p).collect()
+                }
+                DecryptionKeyRepr::Legacy(primes) => primes,
+            }
+        } else {
+            deserializer.deserialize_tuple(2, PrimesVisitor)?
+        };
+        Ok(Self(primes))
+    }
+}
+
+/// Format tag of binary representation of multi-prime [`DecryptionKey`], version 1
+const MULTIPRIME_V1: i32 = -1;
+
+/// Parses binary representation of [`DecryptionKey`] into a list of primes
+struct PrimesVisitor;
+
+impl<'de> serde::de::Visitor<'de> for PrimesVisitor {
+    type Value = Vec<Integer>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("tuple (p, q) or (tag, primes)")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        let first: Integer = seq
+            .next_element()?
+            .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+        if first.is_negative() {
+            if first != MULTIPRIME_V1 {
+                return Err(A::Error::custom("unknown paillier key format version"));
+            }
+            seq.next_element()?
+                .ok_or_else(|| A::Error::invalid_length(1, &self))
+        } else {
+            let q = seq
+                .next_element()?
+                .ok_or_else(|| A::Error::invalid_length(1, &self))?;
+            Ok(vec![first, q])
+        }
+    }
+}
+
+/// Human-readable representation of [`EncryptionKey`]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EncryptionKeyRepr {
+    n: Hex,
+}
+
+/// Human-readable representations of [`EncryptionKey`] accepted by deserialization
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum EncryptionKeyHumanRepr {
+    Hex(EncryptionKeyRepr),
+    /// `N` serialized by `rug`, used by releases preceding hex representation
+    Legacy(Integer),
+}
+
+/// Human-readable representation of [`DecryptionKey`]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+enum DecryptionKeyRepr {
+    TwoPrimes(TwoPrimesRepr),
+    Multiprime(MultiprimeRepr),
+    /// Sequence of primes serialized by `rug`, used by releases preceding hex representation.
+    /// Only accepted by deserialization.
+    Legacy(Vec<Integer>),
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TwoPrimesRepr {
+    p: Hex,
+    q: Hex,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MultiprimeRepr {
+    primes: Vec<Hex>,
+}
+
+/// Non-negative integer encoded as lowercase big-endian hex string without prefix
+struct Hex(Integer);
+
+impl serde::Serialize for Hex {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.0.to_string_radix(16))
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Hex {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        // `rug` parser tolerates signs, whitespaces and underscores, we only accept hex digits
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(<D::Error as serde::de::Error>::custom(
+                "expected non-empty hex string",
+            ));
+        }
+        Integer::from_str_radix(&s, 16)
+            .map(Hex)
+            .map_err(<D::Error as serde::de::Error>::custom)
+    }
+}
@@ -0,0 +1,106 @@
+use crate::{Ciphertext, EncryptionKey, Error, Reason};
+
+/// Ciphertext tagged with [fingerprint](EncryptionKey::fingerprint) of the key it was encrypted
+/// with
+///
+/// Ciphertexts encrypted with different keys can not be combined, however [`EncryptionKey::oadd`]
+/// and other homomorphic operations can't detect that and silently produce garbage. Tagged
+/// ciphertexts are meant to catch such bugs: [`EncryptionKey::oadd_tagged`],
+/// [`EncryptionKey::osub_tagged`], and [`EncryptionKey::omul_tagged`] return an error if any of
+/// operands was tagged by another key.
+///
+/// ## Example
+/// ```rust
+/// # fn main() -> Result<(), fast_paillier::Error> {
+/// # let mut rng = rand_dev::DevRng::new();
+/// # let p = fast_paillier::utils::generate_safe_prime(&mut rng, 256);
+/// # let q = fast_paillier::utils::generate_safe_prime(&mut rng, 256);
+/// # let dk = fast_paillier::DecryptionKey::from_primes(p, q)?;
+/// use rug::Integer;
+///
+/// let ek = dk.encryption_key();
+///
+/// let a = ek.encrypt_with_random(&mut rng, &Integer::from(2))?.ciphertext;
+/// let b = ek.encrypt_with_random(&mut rng, &Integer::from(3))?.ciphertext;
+/// let (a, b) = (ek.tag(a), ek.tag(b));
+///
+/// let c = ek.oadd_tagged(&a, &b)?;
+/// assert_eq!(dk.decrypt(c.ciphertext())?, Integer::from(5));
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TaggedCiphertext {
+    ciphertext: Ciphertext,
+    fingerprint: u64,
+}
+
+impl TaggedCiphertext {
+    /// Returns the ciphertext
+    pub fn ciphertext(&self) -> &Ciphertext {
+        &self.ciphertext
+    }
+
+    /// Returns fingerprint of the key the ciphertext was tagged with
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    /// Returns the ciphertext, dropping the tag
+    pub fn into_ciphertext(self) -> Ciphertext {
+        self.ciphertext
+    }
+}
+
+impl EncryptionKey {
+    /// Tags the ciphertext with fingerprint of this key
+    ///
+    /// It's up to the caller to make sure that `ciphertext` was indeed encrypted with this key.
+    pub fn tag(&self, ciphertext: Ciphertext) -> TaggedCiphertext {
+        TaggedCiphertext {
+            ciphertext,
+            fingerprint: self.fingerprint(),
+        }
+    }
+
+    /// Same as [`oadd`](Self::oadd), but checks that both ciphertexts are tagged by this key
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
+    pub fn oadd_tagged(
+        &self,
+        c1: &TaggedCiphertext,
+        c2: &TaggedCiphertext,
+    ) -> Result<TaggedCiphertext, Error> {
+        self.check_tag(c1)?;
+        self.check_tag(c2)?;
+        Ok(self.tag(self.oadd(&c1.ciphertext, &c2.ciphertext)?))
+    }
+
+    /// Same as [`osub`](Self::osub), but checks that both ciphertexts are tagged by this key
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
+    pub fn osub_tagged(
+        &self,
+        c1: &TaggedCiphertext,
+        c2: &TaggedCiphertext,
+    ) -> Result<TaggedCiphertext, Error> {
+        self.check_tag(c1)?;
+        self.check_tag(c2)?;
+        Ok(self.tag(self.osub(&c1.ciphertext, &c2.ciphertext)?))
+    }
+
+    /// Same as [`omul`](Self::omul), but checks that the ciphertext is tagged by this key
+    #[must_use = "homomorphic operations return a new ciphertext and don't modify the inputs"]
+    pub fn omul_tagged(
+        &self,
+        scalar: &rug::Integer,
+        ciphertext: &TaggedCiphertext,
+    ) -> Result<TaggedCiphertext, Error> {
+        self.check_tag(ciphertext)?;
+        Ok(self.tag(self.omul(scalar, &ciphertext.ciphertext)?))
+    }
+
+    fn check_tag(&self, ciphertext: &TaggedCiphertext) -> Result<(), Error> {
+        if ciphertext.fingerprint != self.fingerprint() {
+            return Err(Reason::KeyMismatch.into());
+        }
+        Ok(())
+    }
+}
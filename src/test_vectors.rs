@@ -0,0 +1,89 @@
+//! Precomputed safe primes and keys for tests
+//!
+//! Generating safe primes takes a while, which makes tests that need fresh keys slow. This module
+//! provides a few fixed safe prime pairs and keys derived from them, so tests can obtain a key
+//! instantly, e.g. via [`DecryptionKey::test_key_1024`].
+//!
+//! The primes are publicly known, so keys from this module must never be used outside of tests.
+
+use rug::Integer;
+
+use crate::DecryptionKey;
+
+/// Safe 512 bit prime number in hex encoding
+pub const P_512: &str = "dc46fb947102a90dd46e557fe5acc201adc7d01797845f088d4e4edfd80c7633\
+                         ac890fefb6e11502455052437750e12dffef35c9e249429e4e35457be083dfa3";
+
+/// Safe 512 bit prime number in hex encoding
+pub const Q_512: &str = "8c972d36929522962408a7e76846599705892f769b04841084592ea309360d07\
+                         db3d19c74d42f646d8be4b4f278948d1e281ac6546670cf2e063acbfebd54ae3";
+
+/// Safe 1024 bit prime number in hex encoding
+pub const P_1024: &str = "d1a310508651ed41fc7167bcaa78cd18e24792aaa85568e45f844286d873b410\
+                          ffae8dcdd7d33e2b55036e70ee45ad1e22073549edd55f351b280617c1ac0b39\
+                          53712b93b083807fcadee6a52225b5698fd88bfcb42dba0b7db9402b11689714\
+                          1035a4bbe69418932598693e09af6d814fea49dccb6421227fa594f1c6a28387";
+
+/// Safe 1024 bit prime number in hex encoding
+pub const Q_1024: &str = "86265107643a48db7a9a455712311dc32187998743156c0a8b71aeac2e8b2ef1\
+                          3cb78d4e6df053c7b715578bb75f367d2b6c5213a1e77f6cafd3d472438493fd\
+                          ff96ae899f19f96f397d45b17f1224024f8024ce21109ac7f2953b6c5645f70b\
+                          d38a1efa0b5d9c1fea7fec6d46f771096fa0630bbc4471a5c18c1555417a271b";
+
+/// Safe 1536 bit prime number in hex encoding
+pub const P_1536: &str = "e84f454a8dd9e923fc85be8ca09278e28c5a3d9419cf118ef56912910f364c52\
+                          9d999dba2837e55d413827ccf97a4b6c49addd56f079032164d487fbd22d5ea9\
+                          ff0c8fdc6bce1b878a7109f33061874f310ae35ac75db3ac3fd5f49d8b85b882\
+                          3f05fc288602abf6a4ef641a3766a44d7ecbceebe3bf144a582639b55658e93c\
+                          c57445715ce83c0e7088ec701ded2bcbd2e91a68cb26b1aaddadf99aeef927fb\
+                          82459a3805c232e36162cbea024a2fe7485b96eeb278d45016c622261b3d3aa3";
+
+/// Safe 1536 bit prime number in hex encoding
+pub const Q_1536: &str = "9461f6a273f4bdf08ce0b1071253e0688d622d6b714b407200fa709d964034c1\
+                          b84b97057a8dd48904a99e83f1cb4c94d6927ac6424b8028eefe6503336e031f\
+                          f0d7379932b1f6fa457d8a1e4d9436c42df8ba86ad54cc83a708cd6385d4d5cb\
+                          f0c62f9f692f04e500726d5d41224e2ec88d48bd3d04c004c9a8e6ce23eefb54\
+                          995d7b4473c021f8a72c06fe3ce6488e6b1b8ad51b635a853121f4285c0c364a\
+                          ab061aea672cb6dd86cee08b63a5b3f1fc78f1712e1a333b2552471e5ad8403f";
+
+/// Returns safe primes [`P_512`] and [`Q_512`]
+pub fn safe_primes_512() -> (Integer, Integer) {
+    (parse(P_512), parse(Q_512))
+}
+
+/// Returns safe primes [`P_1024`] and [`Q_1024`]
+pub fn safe_primes_1024() -> (Integer, Integer) {
+    (parse(P_1024), parse(Q_1024))
+}
+
+/// Returns safe primes [`P_1536`] and [`Q_1536`]
+pub fn safe_primes_1536() -> (Integer, Integer) {
+    (parse(P_1536), parse(Q_1536))
+}
+
+impl DecryptionKey {
+    /// Returns a test key with 1024 bit `N` built from [`safe_primes_512`]
+    pub fn test_key_1024() -> Self {
+        from_primes(safe_primes_512())
+    }
+
+    /// Returns a test key with 2048 bit `N` built from [`safe_primes_1024`]
+    pub fn test_key_2048() -> Self {
+        from_primes(safe_primes_1024())
+    }
+
+    /// Returns a test key with 3072 bit `N` built from [`safe_primes_1536`]
+    pub fn test_key_3072() -> Self {
+        from_primes(safe_primes_1536())
+    }
+}
+
+fn parse(hex: &str) -> Integer {
+    #[allow(clippy::expect_used)]
+    Integer::from_str_radix(hex, 16).expect("test vector is a valid hex")
+}
+
+fn from_primes((p, q): (Integer, Integer)) -> DecryptionKey {
+    #[allow(clippy::expect_used)]
+    DecryptionKey::from_primes(p, q).expect("test vector is a valid key")
+}
@@ -0,0 +1,104 @@
+use rug::{Complete, Integer};
+
+use crate::EncryptionKey;
+
+/// Tracks an upper bound on the absolute value of an (unknown) plaintext through homomorphic
+/// operations
+///
+/// Homomorphic operations are carried out modulo `N`, so if the actual result doesn't fit
+/// into `{-N/2, .., N/2}`, it silently wraps around. Since ciphertexts hide plaintexts, the
+/// overflow can't be detected homomorphically. Instead, `PlaintextTracker` can be used to
+/// account for the largest possible plaintext: each tracker mirrors a ciphertext and is updated
+/// via the same operations as the ciphertext. [`may_overflow`](Self::may_overflow) tells whether
+/// the plaintext might have wrapped around.
+///
+/// ## Example
+/// ```rust
+/// # fn main() -> Result<(), fast_paillier::Error> {
+/// # let mut rng = rand_dev::DevRng::new();
+/// # let p = fast_paillier::utils::generate_safe_prime(&mut rng, 256);
+/// # let q = fast_paillier::utils::generate_safe_prime(&mut rng, 256);
+/// # let dk = fast_paillier::DecryptionKey::from_primes(p, q)?;
+/// use fast_paillier::PlaintextTracker;
+/// use rug::Integer;
+///
+/// let ek = dk.encryption_key();
+///
+/// // We know that plaintexts are within `{-1000, .., 1000}`
+/// let enc_a = ek.encrypt_with_random(&mut rng, &Integer::from(-1000))?.ciphertext;
+/// let enc_b = ek.encrypt_with_random(&mut rng, &Integer::from(500))?.ciphertext;
+/// let bound_a = PlaintextTracker::new(ek, Integer::from(1000));
+/// let bound_b = PlaintextTracker::new(ek, Integer::from(1000));
+///
+/// let enc_c = ek.oadd(&enc_a, &enc_b)?;
+/// let bound_c = bound_a.oadd(&bound_b);
+/// assert!(!bound_c.may_overflow());
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Debug)]
+pub struct PlaintextTracker<'k> {
+    ek: &'k EncryptionKey,
+    bound: Integer,
+}
+
+impl<'k> PlaintextTracker<'k> {
+    /// Starts tracking a plaintext `x` that's known to satisfy `|x| <= bound`
+    ///
+    /// Sign of `bound` is ignored
+    pub fn new(ek: &'k EncryptionKey, bound: Integer) -> Self {
+        Self {
+            ek,
+            bound: bound.abs(),
+        }
+    }
+
+    /// Upper bound on the absolute value of the plaintext
+    pub fn bound(&self) -> &Integer {
+        &self.bound
+    }
+
+    /// Checks whether the plaintext might not fit into `{-N/2, .., N/2}`
+    ///
+    /// If it returns `true`, the result of decryption might have wrapped around modulo `N`
+    pub fn may_overflow(&self) -> bool {
+        self.bound > *self.ek.half_n()
+    }
+
+    /// Bound after [homomorphic addition](EncryptionKey::oadd)
+    ///
+    /// Tracker is not modified, so ignoring the output is a compile-time warning:
+    /// ```rust,compile_fail
+    /// #![deny(unused_must_use)]
+    /// # fn f(tracker: &fast_paillier::PlaintextTracker) {
+    /// tracker.oadd(tracker);
+    /// # }
+    /// ```
+    #[must_use = "operations return a new tracker and don't modify the inputs"]
+    pub fn oadd(&self, other: &Self) -> Self {
+        Self {
+            ek: self.ek,
+            bound: (&self.bound + &other.bound).complete(),
+        }
+    }
+
+    /// Bound after [homomorphic subtraction](EncryptionKey::osub)
+    #[must_use = "operations return a new tracker and don't modify the inputs"]
+    pub fn osub(&self, other: &Self) -> Self {
+        self.oadd(other)
+    }
+
+    /// Bound after [homomorphic multiplication](EncryptionKey::omul) by `scalar`
+    #[must_use = "operations return a new tracker and don't modify the inputs"]
+    pub fn omul(&self, scalar: &Integer) -> Self {
+        Self {
+            ek: self.ek,
+            bound: (&self.bound * scalar).complete().abs(),
+        }
+    }
+
+    /// Bound after [homomorphic negation](EncryptionKey::oneg)
+    #[must_use = "operations return a new tracker and don't modify the inputs"]
+    pub fn oneg(&self) -> Self {
+        self.clone()
+    }
+}
@@ -5,11 +5,17 @@ use std::fmt;
 use rand_core::RngCore;
 use rug::{Assign, Complete, Integer};
 
+#[cfg(feature = "serde")]
+mod crt_serde;
 mod small_primes;
 
 /// Wraps any randomness source that implements [`rand_core::RngCore`] and makes
-/// it compatible with [`rug::rand`].
-pub fn external_rand(rng: &mut impl RngCore) -> rug::rand::ThreadRandState {
+/// it compatible with [`rug::rand`], drawing 32 bits from it at a time
+///
+/// Deprecated in favor of [`ExternalRand64`], which makes half as many calls to the underlying
+/// RNG.
+#[deprecated(note = "use `ExternalRand64` instead, it makes half as many calls to the RNG")]
+pub fn external_rand(rng: &mut impl RngCore) -> rug::rand::ThreadRandState<'_> {
     use bytemuck::TransparentWrapper;
 
     #[derive(TransparentWrapper)]
@@ -18,13 +24,83 @@ pub fn external_rand(rng: &mut impl RngCore) -> rug::rand::ThreadRandState {
 
     impl<R: RngCore> rug::rand::ThreadRandGen for ExternalRand<R> {
         fn gen(&mut self) -> u32 {
-            self.0.next_u32()
+            abort_on_panic(|| self.0.next_u32())
         }
     }
 
     rug::rand::ThreadRandState::new_custom(ExternalRand::wrap_mut(rng))
 }
 
+/// Wraps any randomness source that implements [`rand_core::RngCore`] and makes
+/// it compatible with [`rug::rand`]
+///
+/// `rug` consumes randomness 32 bits at a time. The adapter draws 64 bits from the RNG via
+/// [`RngCore::next_u64`] and hands them to `rug` as two 32-bit words, so the RNG is called once
+/// per two words consumed.
+///
+/// ## Aborts
+/// `rng` is called from within GMP, and unwinding through GMP code is undefined behavior. If `rng`
+/// panics, the process is aborted.
+///
+/// ## Example
+/// ```rust
+/// use fast_paillier::utils::ExternalRand64;
+/// use rug::Integer;
+///
+/// let mut rng = rand_dev::DevRng::new();
+/// let mut rng = ExternalRand64::new(&mut rng);
+/// let x = Integer::from(Integer::random_bits(1024, &mut rng.rand_state()));
+/// assert!(x.significant_bits() <= 1024);
+/// ```
+pub struct ExternalRand64<R> {
+    rng: R,
+    /// Upper half of the last drawn `u64` that hasn't been consumed yet
+    buffered: Option<u32>,
+}
+
+impl<R: RngCore> ExternalRand64<R> {
+    /// Wraps the randomness source
+    pub fn new(rng: R) -> Self {
+        Self {
+            rng,
+            buffered: None,
+        }
+    }
+
+    /// Returns [`rug`] random state which draws randomness from the wrapped RNG
+    pub fn rand_state(&mut self) -> rug::rand::ThreadRandState<'_> {
+        rug::rand::ThreadRandState::new_custom(self)
+    }
+
+    /// Returns the wrapped RNG
+    pub fn into_inner(self) -> R {
+        self.rng
+    }
+}
+
+impl<R: RngCore> rug::rand::ThreadRandGen for ExternalRand64<R> {
+    fn gen(&mut self) -> u32 {
+        match self.buffered.take() {
+            Some(x) => x,
+            None => {
+                let x = abort_on_panic(|| self.rng.next_u64());
+                self.buffered = Some((x >> 32) as u32);
+                x as u32
+            }
+        }
+    }
+}
+
+/// Calls `f`, aborts the process if it panics
+///
+/// Used in callbacks invoked by GMP, as unwinding through C code is undefined behavior.
+fn abort_on_panic<T>(f: impl FnOnce() -> T) -> T {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+        Ok(x) => x,
+        Err(_) => std::process::abort(),
+    }
+}
+
 /// Checks that `x` is in Z*_n
 #[inline(always)]
 pub fn in_mult_group(x: &Integer, n: &Integer) -> bool {
@@ -37,9 +113,113 @@ pub fn in_mult_group_abs(x: &Integer, n: &Integer) -> bool {
     x.gcd_ref(n).complete() == *Integer::ONE
 }
 
+/// Maps `x` in `{-n/2, .., n/2}` to `{0, .., n-1}`
+///
+/// Negative `x` is shifted by `n`, non-negative `x` is left as is. Output is in `{0, .., n-1}` as long
+/// as `x` is in `{-n, .., n-1}`, in particular for any `x` accepted by
+/// [`EncryptionKey::in_signed_group`](crate::EncryptionKey::in_signed_group). It's inverse of
+/// [`unsigned_to_signed`].
+pub fn signed_to_unsigned(x: &Integer, n: &Integer) -> Integer {
+    if x.cmp0().is_ge() {
+        x.clone()
+    } else {
+        (x + n).complete()
+    }
+}
+
+/// Maps `x` in `{0, .., n-1}` to `{-n/2, .., n/2}`
+///
+/// Precisely, output is in `{-floor(n/2), .., floor((n-1)/2)}` and is congruent to `x` modulo `n`.
+/// It's the same mapping as used by [`DecryptionKey::decrypt`](crate::DecryptionKey::decrypt).
+pub fn unsigned_to_signed(x: Integer, n: &Integer) -> Integer {
+    if Integer::from(&x << 1) >= *n {
+        x - n
+    } else {
+        x
+    }
+}
+
+/// Reduces arbitrary `x` modulo `n` into `{-n/2, .., n/2}`
+///
+/// Output is congruent to `x` modulo `n` and is in the same range as output of
+/// [`unsigned_to_signed`]
+pub fn signed_modulo(x: &Integer, n: &Integer) -> Integer {
+    unsigned_to_signed(x.modulo_ref(n).complete(), n)
+}
+
+/// CRC-32 (IEEE 802.3) checksum of `bytes`
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// Samples uniformly random plaintext in `{-N/2, .., N/2}`
+///
+/// Output is always accepted by
+/// [`EncryptionKey::in_signed_group`](crate::EncryptionKey::in_signed_group)
+pub fn random_in_signed_group(rng: &mut impl RngCore, ek: &crate::EncryptionKey) -> Integer {
+    let mut rng = ExternalRand64::new(rng);
+    let x = Integer::from(ek.n().random_below_ref(&mut rng.rand_state()));
+    x - ek.half_n()
+}
+
+/// Boundary plaintexts accepted by [`EncryptionKey::in_signed_group`](crate::EncryptionKey::in_signed_group)
+///
+/// Returns `[lower, lower + 1, -1, 0, 1, upper - 1, upper]`, where `lower = -N/2`, and
+/// `upper = N/2` for odd `N` (or `N/2 - 1` for even `N`). All of them can be encrypted, and can be
+/// used to test behavior at the edges of plaintext space. For tiny `N`, some values may repeat.
+///
+/// Values that are just outside of the plaintext space are given by
+/// [`signed_group_out_of_range_cases`].
+pub fn signed_group_corner_cases(ek: &crate::EncryptionKey) -> [Integer; 7] {
+    let (lower, upper) = signed_group_bounds(ek);
+    [
+        lower.clone(),
+        lower + 1u8,
+        Integer::from(-1),
+        Integer::ZERO,
+        Integer::from(1),
+        (&upper - 1u8).complete(),
+        upper,
+    ]
+}
+
+/// Plaintexts right outside of the range accepted by
+/// [`EncryptionKey::in_signed_group`](crate::EncryptionKey::in_signed_group)
+///
+/// Returns `[lower - 2, lower - 1, upper + 1, upper + 2]`, where `lower` and `upper` are the bounds
+/// of plaintext space, same as in [`signed_group_corner_cases`]. None of them can be encrypted.
+pub fn signed_group_out_of_range_cases(ek: &crate::EncryptionKey) -> [Integer; 4] {
+    let (lower, upper) = signed_group_bounds(ek);
+    [
+        (&lower - 2u8).complete(),
+        lower - 1u8,
+        (&upper + 1u8).complete(),
+        upper + 2u8,
+    ]
+}
+
+/// Returns the smallest and the largest plaintexts in `{-N/2, .., N/2}`
+fn signed_group_bounds(ek: &crate::EncryptionKey) -> (Integer, Integer) {
+    let upper = if ek.n().is_odd() {
+        ek.half_n().clone()
+    } else {
+        (ek.half_n() - 1u8).complete()
+    };
+    (ek.neg_half_n().clone(), upper)
+}
+
 /// Samples `x` in Z*_n
 pub fn sample_in_mult_group(rng: &mut impl RngCore, n: &Integer) -> Integer {
-    let mut rng = external_rand(rng);
+    let mut rng = ExternalRand64::new(rng);
+    let mut rng = rng.rand_state();
     let mut x = Integer::new();
     loop {
         x.assign(n.random_below_ref(&mut rng));
@@ -49,9 +229,305 @@ pub fn sample_in_mult_group(rng: &mut impl RngCore, n: &Integer) -> Integer {
     }
 }
 
+/// Samples nonce `x` in Z*_n for `n = pq` without coprimality check
+///
+/// Samples `x` uniformly in `[1, n)`. Unlike [`sample_in_mult_group`], doesn't compute GCD:
+/// assuming that `n = pq` is a product of two large primes, `x` is not in Z*_n only if it's
+/// a multiple of `p` or `q`, which happens with probability `(p + q - 1) / (n - 1)`, i.e.
+/// negligible for paillier moduli. Same holds for multi-prime `n` as long as all the primes
+/// are large. Assumption is checked via debug assertion.
+///
+/// Only use it when `n` is known to be a product of large primes, e.g. when factorization of
+/// `n` is known, as done by [`DecryptionKey::encrypt_with_random`](crate::DecryptionKey::encrypt_with_random).
+/// If `n` comes from an untrusted source and may have small factors, use [`sample_in_mult_group`]
+/// instead. `n` must be greater than 1.
+pub fn sample_nonce_pq(rng: &mut impl RngCore, n: &Integer) -> Integer {
+    let mut rng = ExternalRand64::new(rng);
+    let n_minus_one = (n - 1u8).complete();
+    let x = Integer::from(n_minus_one.random_below_ref(&mut rng.rand_state())) + 1u8;
+    debug_assert!(
+        in_mult_group(&x, n),
+        "sampled nonce is not in Z*_n: n is not a product of two large primes"
+    );
+    x
+}
+
+/// Selects `a` if `cond` is `true`, otherwise `b`, via limb-wise masking
+///
+/// `a` and `b` must be non-negative and less than `bound`. Both are written into buffers of the
+/// same width, determined by `bound` only, and each limb of the output is computed as
+/// `(a_i & mask) | (b_i & !mask)`. The selection itself doesn't branch on `cond` and accesses the
+/// same memory regardless of it. Conversions from and to `Integer` depend only on `a`, `b` and
+/// `bound`.
+pub(crate) fn select_masked(cond: bool, a: &Integer, b: &Integer, bound: &Integer) -> Integer {
+    debug_assert!(a.cmp0().is_ge() && a < bound && b.cmp0().is_ge() && b < bound);
+    let width = bound.significant_digits::<u64>();
+    let (mut a_limbs, mut b_limbs) = (vec![0u64; width], vec![0u64; width]);
+    a.write_digits(&mut a_limbs, rug::integer::Order::Lsf);
+    b.write_digits(&mut b_limbs, rug::integer::Order::Lsf);
+
+    let mask = std::hint::black_box(0u64.wrapping_sub(u64::from(cond)));
+    for (a_i, b_i) in a_limbs.iter_mut().zip(&b_limbs) {
+        *a_i = (*a_i & mask) | (*b_i & !mask);
+    }
+    Integer::from_digits(&a_limbs, rug::integer::Order::Lsf)
+}
+
+/// Amount of candidates sampled by [`sample_in_mult_group_ct`]
+pub const CT_SAMPLING_CANDIDATES: usize = 8;
+
+/// Samples `x` in Z*_n, sampling a fixed amount of candidates
+///
+/// Unlike [`sample_in_mult_group`], which samples candidates until it finds one in Z*_n, this
+/// function always samples [`CT_SAMPLING_CANDIDATES`] candidates, checks all of them, and picks
+/// the first one in Z*_n via arithmetic selection (without branching on which candidate was
+/// picked). Output distribution is the same as for `sample_in_mult_group`.
+///
+/// For `N = pq` with large primes, a candidate is not in Z*_N with probability about `2/p`, so
+/// all candidates are rejected with negligible probability. In that case, the function falls
+/// back to rejection sampling.
+///
+/// Note that underlying big integer arithmetic is not constant-time, so this function only
+/// removes the timing difference caused by the amount of loop iterations.
+pub fn sample_in_mult_group_ct(rng: &mut impl RngCore, n: &Integer) -> Integer {
+    let mut gmp_rng = ExternalRand64::new(&mut *rng);
+    let mut gmp_rng = gmp_rng.rand_state();
+    let mut result = Integer::new();
+    let mut found = false;
+    for _ in 0..CT_SAMPLING_CANDIDATES {
+        let candidate = Integer::from(n.random_below_ref(&mut gmp_rng));
+        let take = in_mult_group(&candidate, n) & !found;
+        // result = take ? candidate : result
+        result += (candidate - &result) * u8::from(take);
+        found |= take;
+    }
+    drop(gmp_rng);
+
+    if found {
+        result
+    } else {
+        sample_in_mult_group(rng, n)
+    }
+}
+
+/// Constructs a non-negative integer from 64-bit limbs
+///
+/// If `little_endian` is `true`, `limbs[0]` is the least significant limb, otherwise it's the
+/// most significant one. Empty `limbs` correspond to zero.
+///
+/// Useful for crossing FFI boundary without converting integers to bytes. Inverse of
+/// [`integer_to_u64_limbs`] when `little_endian` is `true`.
+pub fn integer_from_u64_limbs(limbs: &[u64], little_endian: bool) -> Integer {
+    let order = if little_endian {
+        rug::integer::Order::Lsf
+    } else {
+        rug::integer::Order::Msf
+    };
+    Integer::from_digits(limbs, order)
+}
+
+/// Hashes into `Z*_n`
+///
+/// `seed` must already be fed with the caller's unique domain separation tag followed by the
+/// inputs. Hash is expanded to 128 bits more than size of `n` and reduced modulo `n`, so that
+/// the output is statistically close to uniform. Output not in `Z*_n` is rejected, and hashing
+/// is repeated with incremented counter.
+#[cfg(any(feature = "deterministic-encryption", feature = "keygen-proof"))]
+pub(crate) fn hash_to_mult_group(seed: &sha2::Sha256, n: &Integer) -> Integer {
+    use sha2::Digest;
+
+    let output_len = (n.significant_bits() as usize + 128).div_ceil(8);
+    for attempt in 0u64.. {
+        let mut bytes = Vec::with_capacity(output_len);
+        for block in 0u64.. {
+            if bytes.len() >= output_len {
+                break;
+            }
+            let hash = seed
+                .clone()
+                .chain_update(attempt.to_be_bytes())
+                .chain_update(block.to_be_bytes())
+                .finalize();
+            bytes.extend_from_slice(&hash);
+        }
+        bytes.truncate(output_len);
+
+        let x = Integer::from_digits(&bytes, rug::integer::Order::Msf) % n;
+        if in_mult_group(&x, n) {
+            return x;
+        }
+    }
+    unreachable!("u64 counter never overflows in practice")
+}
+
+/// Length of `bytes` encoded as big-endian `u64`, used to unambiguously feed variable-length
+/// inputs into a hash
+#[cfg(any(feature = "deterministic-encryption", feature = "keygen-proof"))]
+pub(crate) fn len_prefix(bytes: &[u8]) -> [u8; 8] {
+    (bytes.len() as u64).to_be_bytes()
+}
+
+/// Returns 64-bit limbs of `|x|`, least significant limb first
+///
+/// Output has no leading zero limbs, in particular zero is represented by empty vec. Sign of `x`
+/// is ignored. Can be converted back via [`integer_from_u64_limbs`] with `little_endian = true`.
+pub fn integer_to_u64_limbs(x: &Integer) -> Vec<u64> {
+    x.to_digits(rug::integer::Order::Lsf)
+}
+
+/// Factors `n = p * q` given `lambda` (or any other multiple of Carmichael function of `n`, e.g.
+/// `phi(n)`)
+///
+/// Implements the standard algorithm of factoring RSA modulus given the private exponent: writes
+/// `lambda = 2^s * t` with odd `t` and, for bases `g = 2, 3, ...`, looks for a non-trivial square
+/// root of 1 in sequence `g^t, g^{2t}, .., g^{2^s t} mod n`. Each base succeeds with probability at
+/// least 1/2, so the algorithm gives up after trying 100 bases.
+///
+/// Can be used to recover primes of a key stored as `(N, lambda)` in order to construct
+/// a [`DecryptionKey`](crate::DecryptionKey) which takes advantage of CRT.
+///
+/// Returns `(p, q)` such that `p < q`, or `None` if factorization was not found (e.g. if
+/// `lambda` is not a multiple of Carmichael function of `n`). If `n` has more than two prime
+/// factors, `p` and `q` are not guaranteed to be prime.
+pub fn factor_n_from_lambda(n: &Integer, lambda: &Integer) -> Option<(Integer, Integer)> {
+    if *n <= 3 || n.is_even() || *lambda <= 0 || lambda.is_odd() {
+        return None;
+    }
+    let n_minus_one = (n - Integer::ONE).complete();
+    let s = lambda.find_one(0)?;
+    let t = (lambda >> s).complete();
+
+    let found = |p: Integer| {
+        let q = (n / &p).complete();
+        Some(if p < q { (p, q) } else { (q, p) })
+    };
+
+    for g in 2u32..102 {
+        let g = Integer::from(g);
+        let d = g.gcd_ref(n).complete();
+        if d == *n {
+            continue;
+        } else if d != 1 {
+            return found(d);
+        }
+
+        let mut x = g.pow_mod(&t, n).ok()?;
+        for _ in 0..s {
+            if x == 1 || x == n_minus_one {
+                break;
+            }
+            let y = x.square_ref().complete() % n;
+            if y == 1 {
+                // `x` is a non-trivial square root of 1
+                return found((x - 1u8).gcd(n));
+            }
+            x = y;
+        }
+    }
+    None
+}
+
+/// Amount of Miller-Rabin rounds used by safe prime generation
+///
+/// 25 taken same as one used in mpz_nextprime
+pub(crate) const PRIMALITY_REPS: u32 = 25;
+
+/// Primality test with `reps` Miller-Rabin rounds
+///
+/// All primality tests done by the library go through this function, so primality testing
+/// implementation can be swapped in one place.
+pub(crate) fn is_probably_prime(x: &Integer, reps: u32) -> bool {
+    use rug::integer::IsPrime;
+    matches!(x.is_probably_prime(reps), IsPrime::Yes | IsPrime::Probably)
+}
+
+/// Primality test used by safe prime generation
+///
+/// Default implementation is [`GmpPrimalityTest`]. A custom test (e.g. BPSW or a deterministic
+/// certified test) can be plugged into generation via [`sieve_generate_safe_primes_with_tester`].
+pub trait PrimalityTest {
+    /// Returns `true` if `n` is (probably) prime
+    ///
+    /// `reps` is the amount of Miller-Rabin rounds the library would do for this test. An
+    /// implementation may use it as a hint or ignore it, e.g. if the test is deterministic.
+    fn is_prime(&self, n: &Integer, reps: u32) -> bool;
+}
+
+/// Primality test backed by GMP's `mpz_probab_prime_p`
+///
+/// Performs trial divisions, Baillie-PSW test, and `reps - 24` Miller-Rabin rounds
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GmpPrimalityTest;
+
+impl PrimalityTest for GmpPrimalityTest {
+    fn is_prime(&self, n: &Integer, reps: u32) -> bool {
+        is_probably_prime(n, reps)
+    }
+}
+
+impl<T: PrimalityTest + ?Sized> PrimalityTest for &T {
+    fn is_prime(&self, n: &Integer, reps: u32) -> bool {
+        (**self).is_prime(n, reps)
+    }
+}
+
+/// Checks whether `p` is a safe prime, i.e. both `p` and `(p-1)/2` are (probably) primes
+///
+/// `reps` is amount of Miller-Rabin rounds done for each primality test: the probability that
+/// a composite passes the test is at most `4^-reps`. Can be used to validate primes obtained
+/// from external sources.
+pub fn is_safe_prime(p: &Integer, reps: u32) -> bool {
+    if *p < 5 || p.is_even() {
+        return false;
+    }
+    let q = (p >> 1u32).complete();
+    // Test smaller `q` first, as it's more likely to fail for a random prime `p`
+    is_probably_prime(&q, reps) && is_probably_prime(p, reps)
+}
+
 /// Generates a random safe prime
+///
+/// Sieve parameter is selected via [`optimal_sieve_amount`].
 pub fn generate_safe_prime(rng: &mut impl RngCore, bits: u32) -> Integer {
-    sieve_generate_safe_primes(rng, bits, 135)
+    sieve_generate_safe_primes(rng, bits, optimal_sieve_amount(bits))
+}
+
+/// Returns sieve parameter for [`sieve_generate_safe_primes`] tuned for given bit size
+///
+/// Larger primes are more expensive to test for primality, so it pays off to sieve out more
+/// candidates by small primes. Values are derived from `benches/sieve_amount.rs`. The benchmark
+/// measures time to reduce a candidate modulo one small prime (`sieve`) and time of a primality
+/// test of a candidate that survived the sieve (`test`), and computes the amount of small primes
+/// minimizing the expected time spent on one candidate. Measured on Intel Xeon (single core),
+/// GMP from `gmp-mpfr-sys` 1.7, release profile:
+///
+/// | bits | sieve, ns | test, us | optimum | vs 135 primes | optimum without limit | vs optimum |
+/// |------|-----------|----------|---------|---------------|-----------------------|------------|
+/// | 256  | 30        | 3.0      | 25      | 0.711         | 25                    | 1.000      |
+/// | 512  | 37        | 15.2     | 80      | 0.973         | 80                    | 1.000      |
+/// | 1024 | 52        | 67.5     | 200     | 0.986         | 211                   | 1.000      |
+/// | 1536 | 59        | 265.6    | 200     | 0.952         | 610                   | 0.934      |
+/// | 2048 | 82        | 548.5    | 200     | 0.946         | 862                   | 0.900      |
+/// | 3072 | 102       | 1501.5   | 200     | 0.941         | 1722                  | 0.831      |
+///
+/// "vs 135 primes" is expected time per candidate relative to sieve of 135 primes (the amount
+/// used before), "vs optimum" is time that the optimum without limit would give relative to
+/// the optimum. Across three runs, the optimum varied within 25-30 for 256 bits and 72-80 for
+/// 512 bits, and 30 and 75 are used correspondingly. Time per candidate is flat around the
+/// optimum, so the difference is negligible.
+///
+/// Sieve is limited by the table of 200 small primes built into the library. Starting from 1024
+/// bits, the optimum lies beyond the table, so the whole table is used. It's the best amount
+/// achievable with the table, not a measured optimum. A larger table would make generation of
+/// 1536+ bits primes 7-17% faster per candidate. [`sieve_generate_safe_primes_windowed`] sieves
+/// cheaper and may be preferred for large primes. Bit sizes in between the measured ones are
+/// assigned to the closest measured size.
+pub fn optimal_sieve_amount(bits: u32) -> usize {
+    match bits {
+        0..=383 => 30,
+        384..=767 => 75,
+        _ => small_primes::SMALL_PRIMES.len(),
+    }
 }
 
 /// Generate a random safe prime with a given sieve parameter.
@@ -59,16 +535,83 @@ pub fn generate_safe_prime(rng: &mut impl RngCore, bits: u32) -> Integer {
 /// For different bit sizes, different parameter value will give fastest
 /// generation, the higher bit size - the higher the sieve parameter.
 /// The best way to select the parameter is by trial. The one used by
-/// [`generate_safe_prime`] is indistinguishable from optimal for 500-1700 bit
-/// lengths.
+/// [`generate_safe_prime`] is given by [`optimal_sieve_amount`].
 pub fn sieve_generate_safe_primes(rng: &mut impl RngCore, bits: u32, amount: usize) -> Integer {
-    use rug::integer::IsPrime;
+    sieve_generate_safe_primes_instrumented(rng, bits, amount).0
+}
+
+/// Statistics collected by [`sieve_generate_safe_primes_instrumented`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SieveStats {
+    /// Total amount of candidates tried
+    pub trials: u64,
+    /// Amount of candidates rejected by the sieve
+    pub sieve_rejections: u64,
+    /// Amount of candidates that passed the sieve and went through a full primality test
+    pub primality_tests: u64,
+}
+
+/// Same as [`sieve_generate_safe_primes`], but also returns statistics on how many candidates were
+/// rejected by the sieve, and how many went through the full primality test
+///
+/// Statistics can be used to tune the sieve parameter `amount` for a given bit size.
+pub fn sieve_generate_safe_primes_instrumented(
+    rng: &mut impl RngCore,
+    bits: u32,
+    amount: usize,
+) -> (Integer, SieveStats) {
+    sieve_generate_safe_primes_observed(rng, bits, amount, &GmpPrimalityTest, || {})
+}
 
+/// Same as [`sieve_generate_safe_primes`], but uses `tester` for primality tests
+///
+/// Candidates surviving the sieve are checked by `tester`, first `(p-1)/2`, then `p`. Generated
+/// prime is only as reliable as `tester` is.
+pub fn sieve_generate_safe_primes_with_tester(
+    rng: &mut impl RngCore,
+    bits: u32,
+    amount: usize,
+    tester: &impl PrimalityTest,
+) -> Integer {
+    sieve_generate_safe_primes_observed(rng, bits, amount, tester, || {}).0
+}
+
+/// Same as [`sieve_generate_safe_primes`], but increments `counter` on every trial
+///
+/// Shared atomic counter lets another thread (e.g. one reporting metrics) monitor the progress
+/// of a long-running generation. Counter is incremented by the total number of candidates tried,
+/// i.e. [`SieveStats::trials`]. It's not reset, so one counter can be shared by many generations.
+pub fn sieve_generate_safe_primes_counted(
+    rng: &mut impl RngCore,
+    bits: u32,
+    amount: usize,
+    counter: &std::sync::atomic::AtomicU64,
+) -> Integer {
+    sieve_generate_safe_primes_observed(rng, bits, amount, &GmpPrimalityTest, || {
+        counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    })
+    .0
+}
+
+/// Generates a safe prime using `tester`, calls `on_trial` on every candidate tried
+fn sieve_generate_safe_primes_observed(
+    rng: &mut impl RngCore,
+    bits: u32,
+    amount: usize,
+    tester: &impl PrimalityTest,
+    mut on_trial: impl FnMut(),
+) -> (Integer, SieveStats) {
     let amount = amount.min(small_primes::SMALL_PRIMES.len());
-    let mut rng = external_rand(rng);
+    let mut rng = ExternalRand64::new(rng);
+    let mut rng = rng.rand_state();
     let mut x = Integer::new();
+    let mut stats = SieveStats::default();
 
     'trial: loop {
+        stats.trials += 1;
+        on_trial();
+
         // generate an odd number of length `bits - 2`
         x.assign(Integer::random_bits(bits - 1, &mut rng));
         // `random_bits` is guaranteed to not set `bits-1`-th bit, but not
@@ -79,16 +622,164 @@ pub fn sieve_generate_safe_primes(rng: &mut impl RngCore, bits: u32, amount: usi
         for &small_prime in &small_primes::SMALL_PRIMES[0..amount] {
             let mod_result = x.mod_u(small_prime);
             if mod_result == (small_prime - 1) / 2 {
+                stats.sieve_rejections += 1;
                 continue 'trial;
             }
         }
 
-        // 25 taken same as one used in mpz_nextprime
-        if let IsPrime::Yes | IsPrime::Probably = x.is_probably_prime(25) {
+        stats.primality_tests += 1;
+        if tester.is_prime(&x, PRIMALITY_REPS) {
             x <<= 1;
             x += 1;
-            if let IsPrime::Yes | IsPrime::Probably = x.is_probably_prime(25) {
-                return x;
+            if tester.is_prime(&x, PRIMALITY_REPS) {
+                return (x, stats);
+            }
+        }
+    }
+}
+
+/// Generates a random safe prime, deriving candidates from a single random starting point
+///
+/// Unlike [`sieve_generate_safe_primes`], which draws a fresh random candidate from `rng` on
+/// every trial, this function draws one random starting point `x` and deterministically
+/// derives up to `window` candidates from it: `x, x + 2, x + 4, ...`. A new starting point
+/// is drawn only when the whole window is exhausted without finding a safe prime.
+///
+/// ## Latency characteristics
+/// The number of calls to `rng` is small and nearly fixed: for the default
+/// [`SAFE_PRIME_SEARCH_WINDOW`] it's almost always a single draw per generated prime. Sieving by
+/// `amount` small primes is done incrementally, by updating residues of the candidate instead of
+/// recomputing them, so rejecting a candidate costs a few machine-word operations. Running time
+/// is still dominated by primality tests of sieve survivors, and therefore varies from run to
+/// run, but there's no overhead of drawing and reducing a fresh big integer on every trial.
+///
+/// Candidates that follow long gaps between safe primes are slightly more likely to be picked.
+/// This bias is inherent to incremental prime search and is not known to weaken the resulting
+/// key.
+pub fn sieve_generate_safe_primes_incremental(
+    rng: &mut impl RngCore,
+    bits: u32,
+    amount: usize,
+    window: usize,
+) -> Integer {
+    let sieve = &small_primes::SMALL_PRIMES[..amount.min(small_primes::SMALL_PRIMES.len())];
+    let mut rng = ExternalRand64::new(rng);
+    let mut rng = rng.rand_state();
+    let mut x = Integer::new();
+    let mut residues = vec![0u32; sieve.len()];
+
+    loop {
+        // generate an odd number of length `bits - 2`
+        x.assign(Integer::random_bits(bits - 1, &mut rng));
+        x.set_bit(bits - 2, true);
+        x |= 1u32;
+        for (residue, &small_prime) in residues.iter_mut().zip(sieve) {
+            *residue = x.mod_u(small_prime);
+        }
+
+        for _ in 0..window {
+            // candidate must remain of length `bits - 2`
+            if x.significant_bits() >= bits {
+                break;
+            }
+
+            let passes_sieve = residues
+                .iter()
+                .zip(sieve)
+                .all(|(&residue, &small_prime)| residue != (small_prime - 1) / 2);
+            if passes_sieve && is_probably_prime(&x, PRIMALITY_REPS) {
+                let p = (&x << 1u32).complete() + 1u32;
+                if is_probably_prime(&p, PRIMALITY_REPS) {
+                    return p;
+                }
+            }
+
+            x += 2u32;
+            for (residue, &small_prime) in residues.iter_mut().zip(sieve) {
+                *residue = (*residue + 2) % small_prime;
+            }
+        }
+    }
+}
+
+/// Default window used by [`sieve_generate_safe_primes_incremental`]
+///
+/// It's large enough so that the window contains a safe prime with overwhelming probability
+/// for bit lengths up to 2048.
+pub const SAFE_PRIME_SEARCH_WINDOW: usize = 1 << 24;
+
+/// Generates a random safe prime, sieving a window of consecutive candidates at once
+///
+/// Draws a random starting point `x` and considers `window` consecutive odd candidates
+/// `x, x + 2, ..., x + 2 * (window - 1)`. Instead of computing residues of every candidate
+/// modulo every small prime like [`sieve_generate_safe_primes`] does, residue of `x` is computed
+/// once per small prime `s`, and every candidate `x + 2i` such that `s` divides either
+/// `x + 2i` or `2(x + 2i) + 1` is crossed out in a bitmap, stepping `i` by `s`. Only candidates
+/// that survive the sieve are tested for primality. A new starting point is drawn when the
+/// whole window is exhausted without finding a safe prime.
+///
+/// Sieving costs `O(window * log log B)` word operations per window, where `B` is the largest
+/// small prime, so it's amortized over many candidates. It makes the sieve cheap enough to use
+/// all available small primes, which pays off for large primes (3072+ bits `N`). Window
+/// of `1 << 16` candidates is a reasonable choice.
+///
+/// Same as for [`sieve_generate_safe_primes_incremental`], candidates that follow long gaps
+/// between safe primes are slightly more likely to be picked.
+pub fn sieve_generate_safe_primes_windowed(
+    rng: &mut impl RngCore,
+    bits: u32,
+    window: usize,
+) -> Integer {
+    let window = window.max(1);
+    let mut rng = ExternalRand64::new(rng);
+    let mut rng = rng.rand_state();
+    let mut x = Integer::new();
+    let mut sieve = vec![0u64; window.div_ceil(64)];
+
+    loop {
+        // generate an odd number of length `bits - 2`
+        x.assign(Integer::random_bits(bits - 1, &mut rng));
+        x.set_bit(bits - 2, true);
+        x |= 1u32;
+        sieve_window(&x, &mut sieve, window);
+
+        for i in 0..window {
+            // candidate must remain of length `bits - 2`
+            if x.significant_bits() >= bits {
+                break;
+            }
+
+            let passes_sieve = sieve[i / 64] & (1 << (i % 64)) == 0;
+            if passes_sieve && is_probably_prime(&x, PRIMALITY_REPS) {
+                let p = (&x << 1u32).complete() + 1u32;
+                if is_probably_prime(&p, PRIMALITY_REPS) {
+                    return p;
+                }
+            }
+
+            x += 2u32;
+        }
+    }
+}
+
+/// Sets `i`-th bit of `sieve` if `x + 2i` or `2(x + 2i) + 1` is divisible by a small prime
+///
+/// `x` must be odd
+fn sieve_window(x: &Integer, sieve: &mut [u64], window: usize) {
+    sieve.fill(0);
+    // `x + 2i` is always odd, so 2 is skipped
+    for &small_prime in &small_primes::SMALL_PRIMES[1..] {
+        let s = small_prime as usize;
+        let r = x.mod_u(small_prime) as usize;
+        // `2^-1 mod s`
+        let half = s.div_ceil(2);
+        // `x + 2i = 0 (mod s)` when `i = -r/2`, and `2(x + 2i) + 1 = 0 (mod s)` when
+        // `x + 2i = (s - 1) / 2 (mod s)`
+        for target in [0, (s - 1) / 2] {
+            let mut i = (target + s - r) * half % s;
+            while i < window {
+                sieve[i / 64] |= 1 << (i % 64);
+                i += s;
             }
         }
     }
@@ -99,23 +790,46 @@ pub fn sieve_generate_safe_primes(rng: &mut impl RngCore, bits: u32, amount: usi
 /// `CrtExp` makes exponentation modulo `n` faster when factorization `n = n1 * n2` is known as well as `phi(n1)` and `phi(n2)`
 /// (note that `n1` and `n2` don't need to be primes). In this case, you can [build](Self::build) a `CrtExp` and use provided
 /// [exponentiation algorithm](Self::exp).
+///
+/// Factorization into more than two factors `n = n1 * n2 * ... * nk` is supported as well, see
+/// [`CrtExp::build_from_factors`].
+///
+/// Both `CrtExp` and [`Exponent`] are `Send + Sync`. With `serde` feature, two-factor instances
+/// are serialized in the same shape as in the first release of the crate, and instances with more
+/// factors use a separate versioned shape.
 #[derive(Clone)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CrtExp {
     n: Integer,
-    n1: Integer,
-    phi_n1: Integer,
-    n2: Integer,
-    phi_n2: Integer,
+    factors: Vec<CrtFactor>,
+}
+
+/// Single factor `n_i` of CRT modulus
+#[derive(Clone)]
+struct CrtFactor {
+    n: Integer,
+    phi: Integer,
+    /// Product of all preceding factors `m = n_1 * ... * n_{i-1}`
+    m: Integer,
+    /// `m^-1 mod n_i`
     beta: Integer,
 }
 
+/// Reason why [`CrtExp`] could not be built
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum CrtBuildError {
+    #[error("at least two factors are required")]
+    TooFewFactors,
+    #[error("factor or its totient is out of range")]
+    InvalidFactor,
+    #[error("factors are not coprime")]
+    NotCoprime,
+}
+
 /// Exponent for [modular exponentiation](CrtExp::exp) via [`CrtExp`]
 #[derive(Clone)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Exponent {
-    e_mod_phi_pp: Integer,
-    e_mod_phi_qq: Integer,
+    /// `e mod phi(n_i)` for each factor `n_i`
+    e_mod_phi: Vec<Integer>,
     is_negative: bool,
 }
 
@@ -128,53 +842,126 @@ impl CrtExp {
     /// [`CrtExp::build_n`] and [`CrtExp::build_nn`] can be used when `n1` and `n2` are primes or
     /// square of primes.
     pub fn build(n1: Integer, phi_n1: Integer, n2: Integer, phi_n2: Integer) -> Option<Self> {
-        if n1.cmp0().is_le()
-            || n2.cmp0().is_le()
-            || phi_n1.cmp0().is_le()
-            || phi_n2.cmp0().is_le()
-            || phi_n1 >= n1
-            || phi_n2 >= n2
-        {
-            return None;
+        Self::build_from_factors(vec![(n1, phi_n1), (n2, phi_n2)])
+    }
+
+    /// Builds a `CrtExp` for exponentation modulo `n = n1 * n2 * ... * nk`
+    ///
+    /// Takes a list of pairs `(n_i, phi(n_i))`. Factors `n_i` need to be pairwise coprime. At least
+    /// two factors need to be provided.
+    ///
+    /// [`CrtExp::build_nn_from_primes`] can be used when `n_i` are squares of primes.
+    pub fn build_from_factors(factors: Vec<(Integer, Integer)>) -> Option<Self> {
+        Self::try_build_from_factors(factors).ok()
+    }
+
+    /// Same as [`CrtExp::build_from_factors`], but returns reason of failure
+    pub(crate) fn try_build_from_factors(
+        factors: Vec<(Integer, Integer)>,
+    ) -> Result<Self, CrtBuildError> {
+        if factors.len() < 2 {
+            return Err(CrtBuildError::TooFewFactors);
         }
 
-        let beta = n1.invert_ref(&n2)?.into();
-        Some(Self {
-            n: (&n1 * &n2).complete(),
-            n1,
-            phi_n1,
-            n2,
-            phi_n2,
-            beta,
+        let mut m = Integer::from(1);
+        let mut crt_factors = Vec::with_capacity(factors.len());
+        for (n_i, phi_i) in factors {
+            if n_i.cmp0().is_le() || phi_i.cmp0().is_le() || phi_i >= n_i {
+                return Err(CrtBuildError::InvalidFactor);
+            }
+            let beta = m.invert_ref(&n_i).ok_or(CrtBuildError::NotCoprime)?.into();
+            let next_m = (&m * &n_i).complete();
+            crt_factors.push(CrtFactor {
+                n: n_i,
+                phi: phi_i,
+                m,
+                beta,
+            });
+            m = next_m;
+        }
+
+        Ok(Self {
+            n: m,
+            factors: crt_factors,
         })
     }
 
     /// Builds a `CrtExp` for exponentiation modulo `n = p * q` where `p`, `q` are primes
+    ///
+    /// Returns `None` if `p == q`, as CRT requires factors to be coprime
     pub fn build_n(p: &Integer, q: &Integer) -> Option<Self> {
+        if p == q {
+            return None;
+        }
         let phi_p = (p - 1u8).complete();
         let phi_q = (q - 1u8).complete();
         Self::build(p.clone(), phi_p, q.clone(), phi_q)
     }
 
     /// Builds a `CrtExp` for exponentiation modulo `nn = (p * q)^2` where `p`, `q` are primes
+    ///
+    /// Returns `None` if `p == q`, as CRT requires factors to be coprime
     pub fn build_nn(p: &Integer, q: &Integer) -> Option<Self> {
-        let pp = p.square_ref().complete();
-        let qq = q.square_ref().complete();
-        let phi_pp = (&pp - p).complete();
-        let phi_qq = (&qq - q).complete();
-        Self::build(pp, phi_pp, qq, phi_qq)
+        Self::build_nn_from_primes([p, q])
+    }
+
+    /// Builds a `CrtExp` for exponentiation modulo `nn = (p_1 * ... * p_k)^2` where `p_i` are
+    /// distinct primes
+    ///
+    /// Returns `None` if any two primes are equal, as CRT requires factors to be coprime
+    pub fn build_nn_from_primes<'p>(primes: impl IntoIterator<Item = &'p Integer>) -> Option<Self> {
+        Self::try_build_nn_from_primes(primes).ok()
+    }
+
+    /// Same as [`CrtExp::build_nn_from_primes`], but returns reason of failure
+    pub(crate) fn try_build_nn_from_primes<'p>(
+        primes: impl IntoIterator<Item = &'p Integer>,
+    ) -> Result<Self, CrtBuildError> {
+        let primes: Vec<_> = primes.into_iter().collect();
+        for (i, p) in primes.iter().enumerate() {
+            if primes[i + 1..].contains(p) {
+                return Err(CrtBuildError::NotCoprime);
+            }
+        }
+
+        let factors = primes
+            .into_iter()
+            .map(|p| {
+                let pp = p.square_ref().complete();
+                let phi_pp = (&pp - p).complete();
+                (pp, phi_pp)
+            })
+            .collect();
+        Self::try_build_from_factors(factors)
+    }
+
+    /// Returns modulus `n = n1 * n2` that exponentiation is carried out by
+    pub fn modulus(&self) -> &Integer {
+        &self.n
     }
 
     /// Prepares exponent to perform [modular exponentiation](Self::exp)
+    ///
+    /// Exponent is reduced modulo `phi(n_i)` for each factor `n_i` right away, so the output
+    /// size is bounded by size of `n` regardless of size of `e`. No copies of `e` are made,
+    /// so it's safe to pass arbitrarily large exponents.
     pub fn prepare_exponent(&self, e: &Integer) -> Exponent {
-        let neg_e = (-e).complete();
         let is_negative = e.cmp0().is_lt();
-        let e = if is_negative { &neg_e } else { e };
-        let e_mod_phi_pp = e.modulo_ref(&self.phi_n1).complete();
-        let e_mod_phi_qq = e.modulo_ref(&self.phi_n2).complete();
+        let e_mod_phi = self
+            .factors
+            .iter()
+            .map(|factor| {
+                let e_mod_phi = e.modulo_ref(&factor.phi).complete();
+                if is_negative && !e_mod_phi.is_zero() {
+                    // |e| mod phi = phi - (e mod phi) for negative `e`
+                    &factor.phi - e_mod_phi
+                } else {
+                    e_mod_phi
+                }
+            })
+            .collect();
         Exponent {
-            e_mod_phi_pp,
-            e_mod_phi_qq,
+            e_mod_phi,
             is_negative,
         }
     }
@@ -183,20 +970,21 @@ impl CrtExp {
     ///
     /// Exponent needs to be output of [`CrtExp::prepare_exponent`]
     pub fn exp(&self, x: &Integer, e: &Exponent) -> Option<Integer> {
-        let s1 = x.modulo_ref(&self.n1).complete();
-        let s2 = x.modulo_ref(&self.n2).complete();
+        if e.e_mod_phi.len() != self.factors.len() {
+            return None;
+        }
 
-        // `e_mod_phi_pp` and `e_mod_phi_qq` are guaranteed to be non-negative by construction
-        #[allow(clippy::expect_used)]
-        let r1 = s1
-            .pow_mod(&e.e_mod_phi_pp, &self.n1)
-            .expect("exponent is guaranteed to be non-negative");
-        #[allow(clippy::expect_used)]
-        let r2 = s2
-            .pow_mod(&e.e_mod_phi_qq, &self.n2)
-            .expect("exponent is guaranteed to be non-negative");
+        let residues = self.factors.iter().zip(&e.e_mod_phi).map(|(factor, e_i)| {
+            let mut s_i = crate::pool::take();
+            s_i.assign(x.modulo_ref(&factor.n));
 
-        let result = ((r2 - &r1) * &self.beta).modulo(&self.n2) * &self.n1 + &r1;
+            // `e_i` is guaranteed to be non-negative by construction
+            #[allow(clippy::expect_used)]
+            s_i.pow_mod_mut(e_i, &factor.n)
+                .expect("exponent is guaranteed to be non-negative");
+            s_i
+        });
+        let result = self.recombine_unchecked(residues);
 
         if e.is_negative {
             result.invert(&self.n).ok()
@@ -204,6 +992,64 @@ impl CrtExp {
             Some(result)
         }
     }
+
+    /// Amount of factors `k` of modulus `n = n1 * ... * nk`
+    pub fn factors_count(&self) -> usize {
+        self.factors.len()
+    }
+
+    /// Recombines residues `r_i mod n_i` into `x mod n` such that `x = r_i mod n_i` for each `i`
+    ///
+    /// Residues must be given in the same order as factors were provided when `CrtExp` was built.
+    /// It allows a caller who computed the residues independently (e.g. carried out its own
+    /// exponentiation modulo each factor) to reuse the CRT structure without rebuilding it.
+    ///
+    /// Returns `None` if amount of residues doesn't match [amount of factors](Self::factors_count).
+    ///
+    /// ## Security
+    /// `recombine` reveals the factorization of `n` to anyone who can call it: recombining
+    /// residues `(1, 0, .., 0)` gives `x` such that `gcd(x, n) = n_2 * ... * n_k`. When `CrtExp` is
+    /// built from factorization of a paillier modulus (e.g. via [`build_n`](Self::build_n) or
+    /// [`build_nn`](Self::build_nn)), the factors are the secret primes (or their squares), so
+    /// such `CrtExp` must be handled as secret key material, the same way as the primes
+    /// themselves. Never expose `recombine` of such `CrtExp` to untrusted parties.
+    pub fn recombine(&self, residues: &[Integer]) -> Option<Integer> {
+        if residues.len() != self.factors.len() {
+            return None;
+        }
+        let residues = residues
+            .iter()
+            .zip(&self.factors)
+            .map(|(r_i, factor)| r_i.modulo_ref(&factor.n).complete());
+        Some(self.recombine_unchecked(residues))
+    }
+
+    /// Garner's algorithm, `residues` must be reduced modulo corresponding factors
+    fn recombine_unchecked(&self, residues: impl IntoIterator<Item = Integer>) -> Integer {
+        let mut result = Integer::new();
+        for (i, (factor, r_i)) in self.factors.iter().zip(residues).enumerate() {
+            if i == 0 {
+                result = r_i;
+            } else {
+                // result = result + m * ((r_i - result) * beta mod n_i)
+                let mut t = r_i;
+                t -= &result;
+                t *= &factor.beta;
+                t.modulo_mut(&factor.n);
+                result += &t * &factor.m;
+                crate::pool::give(t);
+            }
+        }
+        result
+    }
+
+    /// Performs exponentiation modulo `n` with exponent given directly
+    ///
+    /// Same as calling [`prepare_exponent`](Self::prepare_exponent) followed by
+    /// [`exp`](Self::exp). If the same exponent is used many times, prefer preparing it once.
+    pub fn exp_raw(&self, x: &Integer, e: &Integer) -> Option<Integer> {
+        self.exp(x, &self.prepare_exponent(e))
+    }
 }
 
 impl fmt::Debug for CrtExp {
@@ -234,4 +1080,258 @@ mod test {
             assert_eq!(&prime, rug::Integer::ONE);
         }
     }
+
+    #[test]
+    fn select_masked() {
+        let bound = rug::Integer::from(1) << 200u32;
+        let a = rug::Integer::from(u64::MAX) << 64u32;
+        let b = rug::Integer::from(7);
+        assert_eq!(super::select_masked(true, &a, &b, &bound), a);
+        assert_eq!(super::select_masked(false, &a, &b, &bound), b);
+        assert_eq!(
+            super::select_masked(true, &rug::Integer::ZERO, &b, &bound),
+            rug::Integer::ZERO
+        );
+    }
+
+    #[test]
+    fn crc32() {
+        assert_eq!(super::crc32(b""), 0);
+        assert_eq!(super::crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn optimal_sieve_amount() {
+        let amounts: Vec<_> = (0..5000).map(super::optimal_sieve_amount).collect();
+        assert!(amounts.windows(2).all(|w| w[0] <= w[1]));
+        assert!(amounts
+            .iter()
+            .all(|&a| 0 < a && a <= super::small_primes::SMALL_PRIMES.len()));
+        assert_eq!(super::optimal_sieve_amount(512), 75);
+        assert_eq!(
+            super::optimal_sieve_amount(1536),
+            super::small_primes::SMALL_PRIMES.len()
+        );
+
+        let mut rng = rand_dev::DevRng::new();
+        for size in [64, 128, 256, 300, 512] {
+            let prime = super::generate_safe_prime(&mut rng, size);
+            assert_eq!(prime.significant_bits(), size);
+            assert!(prime.is_probably_prime(25) != rug::integer::IsPrime::No);
+            let half = prime >> 1u32;
+            assert!(half.is_probably_prime(25) != rug::integer::IsPrime::No);
+        }
+    }
+
+    #[test]
+    fn windowed_sieve() {
+        use rug::Complete;
+
+        let mut rng = rand_dev::DevRng::new();
+        let mut rng = super::ExternalRand64::new(&mut rng);
+        let mut rng = rng.rand_state();
+        let window: usize = 1000;
+        let mut sieve = vec![0u64; window.div_ceil(64)];
+        for _ in 0..10 {
+            let x = rug::Integer::from(rug::Integer::random_bits(256, &mut rng)) | 1u32;
+            super::sieve_window(&x, &mut sieve, window);
+            for i in 0..window {
+                let candidate = (&x + 2 * i as u32).complete();
+                let safe = (&candidate * 2u32).complete() + 1u32;
+                let divisible = super::small_primes::SMALL_PRIMES[1..]
+                    .iter()
+                    .any(|&s| candidate.mod_u(s) == 0 || safe.mod_u(s) == 0);
+                assert_eq!(sieve[i / 64] & (1 << (i % 64)) != 0, divisible, "i = {i}");
+            }
+        }
+    }
+
+    #[test]
+    fn windowed_safe_prime() {
+        use rug::integer::IsPrime;
+
+        let mut rng = rand_dev::DevRng::new();
+        for size in [256, 500, 512, 513] {
+            let prime = super::sieve_generate_safe_primes_windowed(&mut rng, size, 1 << 12);
+            assert_eq!(prime.significant_bits(), size);
+            assert_ne!(prime.is_probably_prime(25), IsPrime::No);
+            let half = (prime - 1u32) >> 1u32;
+            assert_ne!(half.is_probably_prime(25), IsPrime::No);
+        }
+    }
+
+    #[test]
+    fn incremental_safe_prime() {
+        use rug::integer::IsPrime;
+
+        let mut rng = rand_dev::DevRng::new();
+        for size in [500, 512, 513, 514] {
+            let prime = super::sieve_generate_safe_primes_incremental(
+                &mut rng,
+                size,
+                135,
+                super::SAFE_PRIME_SEARCH_WINDOW,
+            );
+            assert_eq!(prime.significant_bits(), size);
+            assert_ne!(prime.is_probably_prime(25), IsPrime::No);
+            let half = (prime - 1u32) >> 1u32;
+            assert_ne!(half.is_probably_prime(25), IsPrime::No);
+        }
+    }
+
+    #[test]
+    fn sieve_stats_are_consistent() {
+        let mut rng = rand_dev::DevRng::new();
+        for amount in [0, 60, 135] {
+            let (prime, stats) =
+                super::sieve_generate_safe_primes_instrumented(&mut rng, 512, amount);
+            assert_eq!(prime.significant_bits(), 512);
+            assert_eq!(stats.trials, stats.sieve_rejections + stats.primality_tests);
+            assert!(stats.primality_tests >= 1);
+            if amount == 0 {
+                assert_eq!(stats.sieve_rejections, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn sieve_counter_matches_trials() {
+        let rng = rand_dev::DevRng::new();
+        let counter = std::sync::atomic::AtomicU64::new(0);
+
+        let prime = super::sieve_generate_safe_primes_counted(&mut rng.clone(), 512, 135, &counter);
+        let (expected_prime, stats) =
+            super::sieve_generate_safe_primes_instrumented(&mut rng.clone(), 512, 135);
+
+        let trials = counter.load(std::sync::atomic::Ordering::Relaxed);
+        assert!(trials > 0);
+        assert_eq!(trials, stats.trials);
+        assert_eq!(prime, expected_prime);
+    }
+
+    #[test]
+    fn ct_sampling_matches_rejection_sampling() {
+        use std::collections::BTreeMap;
+
+        let mut rng = rand_dev::DevRng::new();
+        // Units modulo 15 are {1, 2, 4, 7, 8, 11, 13, 14}
+        let n = rug::Integer::from(15);
+        let samples = 16_000;
+
+        let mut histograms = [BTreeMap::new(), BTreeMap::new()];
+        for _ in 0..samples {
+            let x = super::sample_in_mult_group(&mut rng, &n);
+            *histograms[0].entry(x.to_u32().unwrap()).or_insert(0u32) += 1;
+            let x = super::sample_in_mult_group_ct(&mut rng, &n);
+            *histograms[1].entry(x.to_u32().unwrap()).or_insert(0u32) += 1;
+        }
+
+        let units = [1, 2, 4, 7, 8, 11, 13, 14];
+        for histogram in &histograms {
+            assert!(histogram.keys().copied().eq(units));
+            // Chi-squared statistic over 7 degrees of freedom, 30 corresponds to p-value ~1e-4
+            let expected = f64::from(samples) / units.len() as f64;
+            let chi2: f64 = histogram
+                .values()
+                .map(|&observed| (f64::from(observed) - expected).powi(2) / expected)
+                .sum();
+            assert!(chi2 < 30., "chi2 = {chi2}");
+        }
+    }
+
+    #[test]
+    fn crt_exp_raw() {
+        use rug::{Complete, Integer};
+
+        let mut rng = rand_dev::DevRng::new();
+        let p = super::generate_safe_prime(&mut rng, 256);
+        let q = super::generate_safe_prime(&mut rng, 256);
+        let crt = super::CrtExp::build_nn(&p, &q).unwrap();
+        let nn = crt.modulus().clone();
+
+        for _ in 0..20 {
+            let x = super::sample_in_mult_group(&mut rng, &nn);
+            let e = Integer::from(
+                nn.random_below_ref(&mut super::ExternalRand64::new(&mut rng).rand_state()),
+            );
+            for e in [e.clone(), -e] {
+                let expected = x.pow_mod_ref(&e, &nn).map(|r| r.complete());
+                assert_eq!(crt.exp_raw(&x, &e), expected);
+                assert_eq!(crt.exp(&x, &crt.prepare_exponent(&e)), expected);
+            }
+        }
+    }
+
+    /// Counts calls made to the underlying RNG
+    struct CountingRng<R> {
+        rng: R,
+        calls: usize,
+    }
+
+    impl<R: rand_core::RngCore> rand_core::RngCore for CountingRng<R> {
+        fn next_u32(&mut self) -> u32 {
+            self.calls += 1;
+            self.rng.next_u32()
+        }
+        fn next_u64(&mut self) -> u64 {
+            self.calls += 1;
+            self.rng.next_u64()
+        }
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            self.calls += 1;
+            self.rng.fill_bytes(dest)
+        }
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+            self.calls += 1;
+            self.rng.try_fill_bytes(dest)
+        }
+    }
+
+    #[test]
+    fn external_rand64_halves_rng_calls() {
+        let mut rng = CountingRng {
+            rng: rand_dev::DevRng::new(),
+            calls: 0,
+        };
+        #[allow(deprecated)]
+        let _ = rug::Integer::from(rug::Integer::random_bits(
+            1024,
+            &mut super::external_rand(&mut rng),
+        ));
+        let calls_32 = rng.calls;
+
+        rng.calls = 0;
+        let mut rng64 = super::ExternalRand64::new(&mut rng);
+        let _ = rug::Integer::from(rug::Integer::random_bits(1024, &mut rng64.rand_state()));
+        let calls_64 = rng.calls;
+
+        assert_eq!(calls_32, 32);
+        assert_eq!(calls_64, 16);
+    }
+
+    #[test]
+    fn external_rand64_bits_are_uniform() {
+        let mut rng = rand_dev::DevRng::new();
+        let mut rng = super::ExternalRand64::new(&mut rng);
+        let mut rng = rng.rand_state();
+
+        const BITS: u32 = 96;
+        const SAMPLES: u32 = 10_000;
+        let mut ones = [0u32; BITS as usize];
+        for _ in 0..SAMPLES {
+            let x = rug::Integer::from(rug::Integer::random_bits(BITS, &mut rng));
+            for (i, count) in ones.iter_mut().enumerate() {
+                *count += u32::from(x.get_bit(i as u32));
+            }
+        }
+
+        // Each bit is set with probability 1/2, so the count of ones is expected to be
+        // within 6 standard deviations (sqrt(SAMPLES)/2 = 50) from SAMPLES/2
+        for (i, count) in ones.into_iter().enumerate() {
+            assert!(
+                (4_700..=5_300).contains(&count),
+                "bit {i} is set {count} times out of {SAMPLES}"
+            );
+        }
+    }
 }
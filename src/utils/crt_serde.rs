@@ -0,0 +1,265 @@
+//! Serde implementations of [`CrtExp`] and [`Exponent`]
+//!
+//! Two-factor instances are encoded exactly as in the first release of the crate, i.e. as
+//! structs `CrtExp { n, n1, phi_n1, n2, phi_n2, beta }` and
+//! `Exponent { e_mod_phi_pp, e_mod_phi_qq, is_negative }`, so previously serialized values
+//! remain decodable.
+//!
+//! Instances with more than two factors use a versioned shape: the first field is a negative
+//! format tag (`-1` for the current version) followed by the list of factors. Legacy shape always
+//! starts with a non-negative integer, so the shapes can be told apart by the first field even
+//! in formats that are not self-describing (e.g. bincode).
+
+use std::fmt;
+
+use rug::Integer;
+use serde::de::{self, Error as _, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::ser::SerializeStruct;
+
+use super::{CrtExp, Exponent};
+
+/// Format tag of multi-factor shape, version 1
+const MULTI_FACTOR_V1: i32 = -1;
+
+const CRT_EXP_FIELDS: &[&str] = &[
+    "n", "n1", "phi_n1", "n2", "phi_n2", "beta", "version", "factors",
+];
+const EXPONENT_FIELDS: &[&str] = &[
+    "e_mod_phi_pp",
+    "e_mod_phi_qq",
+    "version",
+    "e_mod_phi",
+    "is_negative",
+];
+
+impl serde::Serialize for CrtExp {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if let [f1, f2] = self.factors.as_slice() {
+            let mut s = serializer.serialize_struct("CrtExp", 6)?;
+            s.serialize_field("n", &self.n)?;
+            s.serialize_field("n1", &f1.n)?;
+            s.serialize_field("phi_n1", &f1.phi)?;
+            s.serialize_field("n2", &f2.n)?;
+            s.serialize_field("phi_n2", &f2.phi)?;
+            s.serialize_field("beta", &f2.beta)?;
+            s.end()
+        } else {
+            let factors: Vec<_> = self.factors.iter().map(|f| (&f.n, &f.phi)).collect();
+            let mut s = serializer.serialize_struct("CrtExp", 2)?;
+            s.serialize_field("version", &Integer::from(MULTI_FACTOR_V1))?;
+            s.serialize_field("factors", &factors)?;
+            s.end()
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for CrtExp {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("CrtExp", CRT_EXP_FIELDS, CrtExpVisitor)
+    }
+}
+
+struct CrtExpVisitor;
+
+impl CrtExpVisitor {
+    fn legacy<E: de::Error>(
+        n: Integer,
+        (n1, phi_n1): (Integer, Integer),
+        (n2, phi_n2): (Integer, Integer),
+        beta: Integer,
+    ) -> Result<CrtExp, E> {
+        let crt = CrtExp::build(n1, phi_n1, n2, phi_n2)
+            .ok_or_else(|| E::custom("invalid CrtExp factors"))?;
+        if crt.n != n || crt.factors[1].beta != beta {
+            return Err(E::custom("CrtExp is inconsistent with its factors"));
+        }
+        Ok(crt)
+    }
+
+    fn multi_factor<E: de::Error>(
+        version: Integer,
+        factors: Vec<(Integer, Integer)>,
+    ) -> Result<CrtExp, E> {
+        if version != MULTI_FACTOR_V1 {
+            return Err(E::custom("unknown CrtExp format version"));
+        }
+        CrtExp::build_from_factors(factors).ok_or_else(|| E::custom("invalid CrtExp factors"))
+    }
+}
+
+impl<'de> Visitor<'de> for CrtExpVisitor {
+    type Value = CrtExp;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("struct CrtExp")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<CrtExp, A::Error> {
+        let mut next = |i| {
+            seq.next_element::<Integer>()?
+                .ok_or_else(|| A::Error::invalid_length(i, &self))
+        };
+        let first = next(0)?;
+        if first.is_negative() {
+            let factors = seq
+                .next_element()?
+                .ok_or_else(|| A::Error::invalid_length(1, &self))?;
+            return Self::multi_factor(first, factors);
+        }
+        // Legacy shape: `n`, `n1`, `phi_n1`, `n2`, `phi_n2`, `beta`
+        let f1 = (next(1)?, next(2)?);
+        let f2 = (next(3)?, next(4)?);
+        Self::legacy(first, f1, f2, next(5)?)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<CrtExp, A::Error> {
+        let [mut n, mut n1, mut phi_n1, mut n2, mut phi_n2, mut beta, mut version] =
+            [None, None, None, None, None, None, None];
+        let mut factors = None;
+        while let Some(key) = map.next_key::<String>()? {
+            let field = match key.as_str() {
+                "n" => &mut n,
+                "n1" => &mut n1,
+                "phi_n1" => &mut phi_n1,
+                "n2" => &mut n2,
+                "phi_n2" => &mut phi_n2,
+                "beta" => &mut beta,
+                "version" => &mut version,
+                "factors" => {
+                    factors = Some(map.next_value()?);
+                    continue;
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                    continue;
+                }
+            };
+            *field = Some(map.next_value()?);
+        }
+
+        if let Some(version) = version {
+            let factors = factors.ok_or_else(|| A::Error::missing_field("factors"))?;
+            return Self::multi_factor(version, factors);
+        }
+        let required = |x: Option<Integer>, name| x.ok_or_else(|| A::Error::missing_field(name));
+        Self::legacy(
+            required(n, "n")?,
+            (required(n1, "n1")?, required(phi_n1, "phi_n1")?),
+            (required(n2, "n2")?, required(phi_n2, "phi_n2")?),
+            required(beta, "beta")?,
+        )
+    }
+}
+
+impl serde::Serialize for Exponent {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if let [e1, e2] = self.e_mod_phi.as_slice() {
+            let mut s = serializer.serialize_struct("Exponent", 3)?;
+            s.serialize_field("e_mod_phi_pp", e1)?;
+            s.serialize_field("e_mod_phi_qq", e2)?;
+            s.serialize_field("is_negative", &self.is_negative)?;
+            s.end()
+        } else {
+            let mut s = serializer.serialize_struct("Exponent", 3)?;
+            s.serialize_field("version", &Integer::from(MULTI_FACTOR_V1))?;
+            s.serialize_field("e_mod_phi", &self.e_mod_phi)?;
+            s.serialize_field("is_negative", &self.is_negative)?;
+            s.end()
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Exponent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("Exponent", EXPONENT_FIELDS, ExponentVisitor)
+    }
+}
+
+struct ExponentVisitor;
+
+impl ExponentVisitor {
+    fn build<E: de::Error>(e_mod_phi: Vec<Integer>, is_negative: bool) -> Result<Exponent, E> {
+        // Exponents modulo `phi(n_i)` are non-negative by construction
+        if e_mod_phi.len() < 2 || e_mod_phi.iter().any(|e| e.is_negative()) {
+            return Err(E::custom("invalid Exponent"));
+        }
+        Ok(Exponent {
+            e_mod_phi,
+            is_negative,
+        })
+    }
+}
+
+impl<'de> Visitor<'de> for ExponentVisitor {
+    type Value = Exponent;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("struct Exponent")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Exponent, A::Error> {
+        let first: Integer = seq
+            .next_element()?
+            .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+        let e_mod_phi = if first.is_negative() {
+            if first != MULTI_FACTOR_V1 {
+                return Err(A::Error::custom("unknown Exponent format version"));
+            }
+            seq.next_element()?
+                .ok_or_else(|| A::Error::invalid_length(1, &self))?
+        } else {
+            // Legacy shape: `e_mod_phi_pp`, `e_mod_phi_qq`, `is_negative`
+            let second = seq
+                .next_element()?
+                .ok_or_else(|| A::Error::invalid_length(1, &self))?;
+            vec![first, second]
+        };
+        let is_negative = seq
+            .next_element()?
+            .ok_or_else(|| A::Error::invalid_length(2, &self))?;
+        Self::build(e_mod_phi, is_negative)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Exponent, A::Error> {
+        let (mut pp, mut qq, mut version, mut e_mod_phi, mut is_negative) =
+            (None, None, None::<Integer>, None, None);
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "e_mod_phi_pp" => pp = Some(map.next_value()?),
+                "e_mod_phi_qq" => qq = Some(map.next_value()?),
+                "version" => version = Some(map.next_value()?),
+                "e_mod_phi" => e_mod_phi = Some(map.next_value()?),
+                "is_negative" => is_negative = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        let is_negative = is_negative.ok_or_else(|| A::Error::missing_field("is_negative"))?;
+        let e_mod_phi = if let Some(version) = version {
+            if version != MULTI_FACTOR_V1 {
+                return Err(A::Error::custom("unknown Exponent format version"));
+            }
+            e_mod_phi.ok_or_else(|| A::Error::missing_field("e_mod_phi"))?
+        } else {
+            vec![
+                pp.ok_or_else(|| A::Error::missing_field("e_mod_phi_pp"))?,
+                qq.ok_or_else(|| A::Error::missing_field("e_mod_phi_qq"))?,
+            ]
+        };
+        Self::build(e_mod_phi, is_negative)
+    }
+}
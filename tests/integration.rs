@@ -1,4 +1,4 @@
-use fast_paillier::{utils, DecryptionKey};
+use fast_paillier::{utils, DecryptionKey, Encryption};
 use rand::Rng;
 use rug::{Complete, Integer};
 
@@ -13,12 +13,13 @@ fn encrypt_decrypt() {
         let plaintext = ek
             .n()
             .clone()
-            .random_below(&mut utils::external_rand(&mut rng));
+            .random_below(&mut utils::ExternalRand64::new(&mut rng).rand_state());
         let plaintext = plaintext - (ek.n() / 2u8).complete();
         println!("Plaintext: {plaintext}");
 
         // Encrypt and decrypt
-        let (ciphertext, nonce) = ek.encrypt_with_random(&mut rng, &plaintext).unwrap();
+        let Encryption { ciphertext, nonce } =
+            ek.encrypt_with_random(&mut rng, &plaintext).unwrap();
         println!("Ciphertext: {ciphertext}");
         println!("Nonce: {nonce}");
 
@@ -31,44 +32,188 @@ fn encrypt_decrypt() {
 
     // Check corner cases
 
-    let lower_bound = -(ek.n() / 2u8).complete();
-    let upper_bound = (ek.n() / 2u8).complete();
-
-    let corner_cases = [
-        lower_bound.clone(),
-        lower_bound.clone() + 1,
-        upper_bound.clone() - 1,
-        upper_bound.clone(),
-    ];
+    let corner_cases = utils::signed_group_corner_cases(ek);
     for (i, plaintext) in corner_cases.into_iter().enumerate() {
         println!("Corner case {i}");
-        let (ciphertext, _nonce) = ek.encrypt_with_random(&mut rng, &plaintext).unwrap();
+        let ciphertext = ek
+            .encrypt_with_random(&mut rng, &plaintext)
+            .unwrap()
+            .ciphertext;
         let decrypted = dk.decrypt(&ciphertext).unwrap();
         assert_eq!(plaintext, decrypted);
     }
 }
 
 #[test]
-fn doesnt_encrypt_plaintext_out_of_bounds() {
+fn decrypt_unsigned_raw() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let mut plaintexts: Vec<_> = (0..20)
+        .map(|_| utils::random_in_signed_group(&mut rng, ek))
+        .collect();
+    plaintexts.extend([
+        -ek.half_n().clone(),
+        Integer::from(-1),
+        Integer::ZERO,
+        ek.half_n().clone(),
+    ]);
+
+    for x in plaintexts {
+        let ciphertext = ek.encrypt_with_random(&mut rng, &x).unwrap().ciphertext;
+        let unsigned = dk.decrypt_unsigned_raw(&ciphertext).unwrap();
+        assert!(unsigned.cmp0().is_ge() && unsigned < *ek.n());
+        assert_eq!(unsigned, x.modulo_ref(ek.n()).complete());
+        assert_eq!(
+            utils::unsigned_to_signed(unsigned, ek.n()),
+            dk.decrypt(&ciphertext).unwrap()
+        );
+    }
+}
+
+#[test]
+fn decrypt_uniform() {
     let mut rng = rand_dev::DevRng::new();
     let dk = random_key_for_tests(&mut rng);
     let ek = dk.encryption_key();
 
-    let lower_bound = -(ek.n() / 2u8).complete();
-    let upper_bound = (ek.n() / 2u8).complete();
+    for _ in 0..20 {
+        let x = utils::random_in_signed_group(&mut rng, ek);
+        let ciphertext = ek.encrypt_with_random(&mut rng, &x).unwrap().ciphertext;
+        assert_eq!(dk.decrypt_uniform(&ciphertext).unwrap(), x);
+    }
 
-    let cases = [
-        lower_bound.clone() - 1,
-        lower_bound.clone() - 2,
-        upper_bound.clone() + 1,
-        upper_bound.clone() + 2,
+    // Invalid ciphertexts go through the same computation, and are rejected at the end
+    let invalid_ciphertexts = [
+        Integer::ZERO,
+        ek.n().clone(),
+        (ek.n() * dk.p()).complete(),
+        -Integer::from(1),
+        ek.nn().clone(),
     ];
+    for c in invalid_ciphertexts {
+        let _: fast_paillier::Error = dk.decrypt(&c).unwrap_err();
+        let _: fast_paillier::Error = dk.decrypt_uniform(&c).unwrap_err();
+    }
+}
+
+#[test]
+fn doesnt_encrypt_plaintext_out_of_bounds() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let cases = utils::signed_group_out_of_range_cases(ek);
     for (i, plaintext) in cases.into_iter().enumerate() {
         println!("Case {i}");
         let _: fast_paillier::Error = ek.encrypt_with_random(&mut rng, &plaintext).unwrap_err();
     }
 }
 
+#[test]
+fn signed_group_corner_cases() {
+    use fast_paillier::EncryptionKey;
+
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let half_n = dk.encryption_key().half_n().clone();
+    let cases = utils::signed_group_corner_cases(dk.encryption_key());
+    assert_eq!(cases[0], -half_n.clone());
+    assert_eq!(cases[3], 0);
+    assert_eq!(cases[6], half_n);
+
+    for n in [3, 4, 15, 16, 17, 2047, 2048] {
+        let ek = EncryptionKey::from_n(Integer::from(n));
+        for x in utils::signed_group_corner_cases(&ek) {
+            assert!(ek.in_signed_group(&x), "n = {n}, x = {x}");
+        }
+        for x in utils::signed_group_out_of_range_cases(&ek) {
+            assert!(!ek.in_signed_group(&x), "n = {n}, x = {x}");
+        }
+    }
+    let ek = dk.encryption_key();
+    assert!(utils::signed_group_corner_cases(ek)
+        .iter()
+        .all(|x| ek.in_signed_group(x)));
+    assert!(!utils::signed_group_out_of_range_cases(ek)
+        .iter()
+        .any(|x| ek.in_signed_group(x)));
+}
+
+#[test]
+fn signed_group_boundaries() {
+    use fast_paillier::EncryptionKey;
+
+    // Odd N: both N/2 and -N/2 are in the group, as they're different residues modulo N
+    let ek = EncryptionKey::from_n(Integer::from(15));
+    for x in [-7, -6, 0, 6, 7] {
+        assert!(ek.in_signed_group(&Integer::from(x)), "x = {x}");
+    }
+    for x in [-9, -8, 8, 9] {
+        assert!(!ek.in_signed_group(&Integer::from(x)), "x = {x}");
+    }
+
+    // Even N: N/2 = -N/2 mod N, so only -N/2 is in the group
+    let ek = EncryptionKey::from_n(Integer::from(16));
+    for x in [-8, -7, 0, 7] {
+        assert!(ek.in_signed_group(&Integer::from(x)), "x = {x}");
+    }
+    for x in [-10, -9, 8, 9] {
+        assert!(!ek.in_signed_group(&Integer::from(x)), "x = {x}");
+    }
+
+    // Every residue modulo N corresponds to exactly one element of the group
+    for n in [15, 16] {
+        let ek = EncryptionKey::from_n(Integer::from(n));
+        let elements: Vec<_> = (-n..=n)
+            .map(Integer::from)
+            .filter(|x| ek.in_signed_group(x))
+            .collect();
+        assert_eq!(elements.len(), n as usize);
+        let mut residues: Vec<_> = elements
+            .iter()
+            .map(|x| x.modulo_ref(ek.n()).complete())
+            .collect();
+        residues.sort();
+        residues.dedup();
+        assert_eq!(residues.len(), n as usize);
+    }
+}
+
+#[test]
+fn negative_plaintext_handling() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    // Extreme negative plaintext is decrypted exactly
+    let x = -ek.half_n().clone();
+    let Encryption { ciphertext, nonce } = ek.encrypt_with_random(&mut rng, &x).unwrap();
+    assert_eq!(dk.decrypt(&ciphertext).unwrap(), x);
+    assert_eq!(dk.encrypt_with(&x, &nonce).unwrap(), ciphertext);
+
+    // Shifted plaintext is always in [0, N) and is congruent to the original plaintext
+    for n in [ek.n().clone(), Integer::from(15), Integer::from(16)] {
+        let ek = fast_paillier::EncryptionKey::from_n(n);
+        let n = ek.n();
+        let boundaries = [
+            -ek.half_n().clone(),
+            -ek.half_n().clone() + 1u8,
+            Integer::from(-1),
+            Integer::ZERO,
+            Integer::from(1),
+            ek.half_n().clone(),
+        ];
+        for x in boundaries.iter().filter(|x| ek.in_signed_group(x)) {
+            let shifted = utils::signed_to_unsigned(x, n);
+            assert!(shifted.cmp0().is_ge() && shifted < *n, "x = {x}, n = {n}");
+            assert_eq!(shifted, x.modulo_ref(n).complete());
+            assert_eq!(utils::unsigned_to_signed(shifted, n), *x);
+        }
+    }
+}
+
 #[test]
 fn homorphic_ops() {
     let mut rng = rand_dev::DevRng::new();
@@ -79,18 +224,18 @@ fn homorphic_ops() {
         let a = ek
             .n()
             .clone()
-            .random_below(&mut utils::external_rand(&mut rng));
+            .random_below(&mut utils::ExternalRand64::new(&mut rng).rand_state());
         let b = ek
             .n()
             .clone()
-            .random_below(&mut utils::external_rand(&mut rng));
+            .random_below(&mut utils::ExternalRand64::new(&mut rng).rand_state());
         let a = a - (ek.n() / 2u8).complete();
         let b = b - (ek.n() / 2u8).complete();
         println!("a: {a}");
         println!("b: {b}");
 
-        let (enc_a, _nonce) = ek.encrypt_with_random(&mut rng, &a).unwrap();
-        let (enc_b, _nonce) = ek.encrypt_with_random(&mut rng, &b).unwrap();
+        let enc_a = ek.encrypt_with_random(&mut rng, &a).unwrap().ciphertext;
+        let enc_b = ek.encrypt_with_random(&mut rng, &b).unwrap().ciphertext;
 
         // Addition
         {
@@ -122,6 +267,356 @@ fn homorphic_ops() {
     }
 }
 
+#[test]
+fn encrypt_decrypt_bytes() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+    let n_len = ek.to_bytes().len();
+
+    for len in 0..n_len - 1 {
+        let mut bytes = vec![0u8; len];
+        rng.fill(bytes.as_mut_slice());
+
+        let ciphertext = ek.encrypt_bytes(&mut rng, &bytes).unwrap().ciphertext;
+        let decrypted = dk.decrypt_bytes(&ciphertext).unwrap();
+        assert_eq!(decrypted, bytes);
+    }
+
+    // Leading zeroes are preserved
+    for bytes in [&b"\0\0x"[..], b"\0", b"\0\0\0", b""] {
+        let ciphertext = ek.encrypt_bytes(&mut rng, bytes).unwrap().ciphertext;
+        assert_eq!(dk.decrypt_bytes(&ciphertext).unwrap(), bytes);
+    }
+
+    // Plaintext must be less than N
+    let n = ek.to_bytes();
+    let _: fast_paillier::Error = ek.encrypt_bytes(&mut rng, &n).unwrap_err();
+
+    // Ciphertext not produced by `encrypt_bytes`
+    let ciphertext = ek
+        .encrypt_with_random(&mut rng, &Integer::ZERO)
+        .unwrap()
+        .ciphertext;
+    let err = dk.decrypt_bytes(&ciphertext).unwrap_err();
+    assert_eq!(err.kind(), fast_paillier::ErrorKind::InvalidEncoding);
+}
+
+#[test]
+fn omul_small_scalars() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let x = Integer::from(-1234567);
+    let enc_x = ek.encrypt_with_random(&mut rng, &x).unwrap().ciphertext;
+
+    for scalar in [0u64, 1, 2, 3, 255, 256, 1 << 32, u64::MAX] {
+        let enc_result = ek.omul_u64(scalar, &enc_x).unwrap();
+        let expected = signed_modulo(&(&x * Integer::from(scalar)), ek.n());
+        assert_eq!(
+            dk.decrypt(&enc_result).unwrap(),
+            expected,
+            "scalar = {scalar}"
+        );
+    }
+    for scalar in [0i64, 1, 2, -1, -2, -255, i64::MAX, i64::MIN] {
+        let enc_result = ek.omul_i64(scalar, &enc_x).unwrap();
+        let expected = signed_modulo(&(&x * Integer::from(scalar)), ek.n());
+        assert_eq!(
+            dk.decrypt(&enc_result).unwrap(),
+            expected,
+            "scalar = {scalar}"
+        );
+
+        if scalar != 0 {
+            assert_eq!(enc_result, ek.omul(&Integer::from(scalar), &enc_x).unwrap());
+        }
+    }
+
+    let _: fast_paillier::Error = ek.omul_u64(2, ek.n()).unwrap_err();
+}
+
+#[test]
+fn plaintext_tracker_flags_overflow() {
+    use fast_paillier::PlaintextTracker;
+
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    // x is close to N/4, so 2x doesn't overflow, but 3x does
+    let x = (ek.half_n() / 2u8).complete() - 1;
+    let enc_x = ek.encrypt_with_random(&mut rng, &x).unwrap().ciphertext;
+    let tracker = PlaintextTracker::new(ek, x.clone());
+    assert!(!tracker.may_overflow());
+
+    let enc_2x = ek.oadd(&enc_x, &enc_x).unwrap();
+    let tracker_2x = tracker.oadd(&tracker);
+    assert!(!tracker_2x.may_overflow());
+    assert_eq!(dk.decrypt(&enc_2x).unwrap(), (&x * 2u8).complete());
+
+    let enc_3x = ek.oadd(&enc_2x, &enc_x).unwrap();
+    let tracker_3x = tracker_2x.oadd(&tracker);
+    assert!(tracker_3x.may_overflow());
+    // the plaintext has indeed wrapped around
+    assert_ne!(dk.decrypt(&enc_3x).unwrap(), (&x * 3u8).complete());
+
+    // same for scalar multiplication and negation
+    assert!(!tracker.omul(&Integer::from(-2)).oneg().may_overflow());
+    assert!(tracker.omul(&Integer::from(-3)).may_overflow());
+}
+
+#[test]
+fn oneg_osub_output_is_canonical() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let is_canonical = |c: &Integer| c.cmp0().is_ge() && c < ek.nn();
+
+    let mut plaintexts: Vec<_> = (0..100)
+        .map(|_| utils::random_in_signed_group(&mut rng, ek))
+        .collect();
+    plaintexts.extend([
+        -ek.half_n().clone(),
+        Integer::from(-1),
+        Integer::ZERO,
+        Integer::from(1),
+        ek.half_n().clone(),
+    ]);
+
+    for pair in plaintexts.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        let enc_a = ek.encrypt_with_random(&mut rng, a).unwrap().ciphertext;
+        let enc_b = ek.encrypt_with_random(&mut rng, b).unwrap().ciphertext;
+
+        let neg = ek.oneg(&enc_a).unwrap();
+        assert!(is_canonical(&neg));
+        assert_eq!(
+            dk.decrypt(&neg).unwrap(),
+            signed_modulo(&(-a).complete(), ek.n())
+        );
+
+        let diff = ek.osub(&enc_a, &enc_b).unwrap();
+        assert!(is_canonical(&diff));
+        assert_eq!(
+            dk.decrypt(&diff).unwrap(),
+            signed_modulo(&(a - b).complete(), ek.n())
+        );
+    }
+}
+
+#[test]
+fn prepared_odot() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let plaintexts: Vec<_> = (0..10)
+        .map(|_| utils::random_in_signed_group(&mut rng, ek))
+        .collect();
+    let ciphertexts: Vec<_> = plaintexts
+        .iter()
+        .map(|x| ek.encrypt_with_random(&mut rng, x).unwrap().ciphertext)
+        .collect();
+    let prepared = ek.prepare_odot(&ciphertexts).unwrap();
+    assert_eq!(prepared.len(), ciphertexts.len());
+
+    for i in 0..10 {
+        let mut scalars: Vec<_> = (0..10)
+            .map(|_| utils::random_in_signed_group(&mut rng, ek))
+            .collect();
+        // check corner cases of scalars
+        scalars[0] = Integer::from(i);
+        scalars[1] = ek.n().clone();
+        scalars[2] = -ek.n().clone();
+
+        let expected = ek.odot(&scalars, &ciphertexts).unwrap();
+        let actual = prepared.odot(&scalars).unwrap();
+        assert_eq!(expected, actual);
+
+        let expected_plaintext = scalars
+            .iter()
+            .zip(&plaintexts)
+            .map(|(a, b)| (a * b).complete())
+            .sum::<Integer>();
+        assert_eq!(
+            dk.decrypt(&actual).unwrap(),
+            signed_modulo(&expected_plaintext, ek.n())
+        );
+    }
+
+    // Invalid inputs
+    let _: fast_paillier::Error = prepared.odot(&[Integer::from(1)]).unwrap_err();
+    let mut scalars = vec![Integer::from(1); 10];
+    scalars[5] = (ek.n() + 1u8).complete();
+    let _: fast_paillier::Error = prepared.odot(&scalars).unwrap_err();
+    let _: fast_paillier::Error = ek.prepare_odot(&[ek.n().clone()]).unwrap_err();
+}
+
+#[test]
+fn omatvec() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let vector = [5, -3, 7].map(Integer::from);
+    let ciphertexts: Vec<_> = vector
+        .iter()
+        .map(|x| ek.encrypt_with_random(&mut rng, x).unwrap().ciphertext)
+        .collect();
+    let matrix = vec![
+        [1, 2, 3].map(Integer::from).to_vec(),
+        [0, -1, 0].map(Integer::from).to_vec(),
+        vec![ek.n().clone(), ek.half_n().clone(), Integer::from(-4)],
+    ];
+    let expected: Vec<_> = matrix
+        .iter()
+        .map(|row| {
+            let x = row
+                .iter()
+                .zip(&vector)
+                .map(|(a, b)| (a * b).complete())
+                .sum::<Integer>();
+            signed_modulo(&x, ek.n())
+        })
+        .collect();
+
+    let from_ek = ek.omatvec(&mut rng, &matrix, &ciphertexts).unwrap();
+    let from_dk = dk.omatvec(&mut rng, &matrix, &ciphertexts).unwrap();
+    assert_eq!(from_ek.len(), matrix.len());
+    assert_eq!(from_dk.len(), matrix.len());
+    for ((a, b), expected) in from_ek.iter().zip(&from_dk).zip(&expected) {
+        assert_eq!(dk.decrypt(&a.ciphertext).unwrap(), *expected);
+        assert_eq!(dk.decrypt(&b.ciphertext).unwrap(), *expected);
+        // Outputs are re-randomized
+        assert_ne!(a.ciphertext, b.ciphertext);
+    }
+
+    // Dimensions mismatch
+    let bad_matrix = vec![vec![Integer::from(1); 3], vec![Integer::from(1); 2]];
+    let _: fast_paillier::Error = ek.omatvec(&mut rng, &bad_matrix, &ciphertexts).unwrap_err();
+    let _: fast_paillier::Error = dk.omatvec(&mut rng, &bad_matrix, &ciphertexts).unwrap_err();
+    // Empty matrix
+    assert!(ek.omatvec(&mut rng, &[], &ciphertexts).unwrap().is_empty());
+}
+
+#[test]
+fn fold_from_identity() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let plaintexts: Vec<Integer> = (0..20).map(|_| Integer::from(rng.gen::<i32>())).collect();
+    let ciphertexts: Vec<_> = plaintexts
+        .iter()
+        .map(|x| ek.encrypt_with_random(&mut rng, x).unwrap().ciphertext)
+        .collect();
+
+    let sum = ciphertexts
+        .iter()
+        .try_fold(ek.oadd_identity(), |acc, c| ek.oadd(&acc, c))
+        .unwrap();
+    assert_eq!(
+        dk.decrypt(&sum).unwrap(),
+        plaintexts.iter().sum::<Integer>()
+    );
+
+    // Folding an empty list results into encryption of zero
+    assert_eq!(dk.decrypt(&ek.oadd_identity()).unwrap(), 0);
+}
+
+#[test]
+fn sum_accumulator() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let mut acc = fast_paillier::SumAccumulator::new(ek);
+    let mut expected = Integer::new();
+    for _ in 0..1000 {
+        let x = Integer::from(rng.gen::<i64>());
+        let c = dk.encrypt_with_random(&mut rng, &x).unwrap().ciphertext;
+        acc.push(&c).unwrap();
+        expected += x;
+    }
+
+    // Invalid ciphertext must be rejected without affecting the sum
+    let _: fast_paillier::Error = acc.push(ek.n()).unwrap_err();
+
+    let sum = acc.finish();
+    assert!(sum < *ek.nn());
+    assert_eq!(dk.decrypt(&sum).unwrap(), expected);
+}
+
+#[test]
+fn homomorphic_select() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let a = Integer::from(123);
+    let b = Integer::from(-456);
+    let enc_a = ek.encrypt_with_random(&mut rng, &a).unwrap().ciphertext;
+    let enc_b = ek.encrypt_with_random(&mut rng, &b).unwrap().ciphertext;
+
+    for (cond, expected) in [(true, &a), (false, &b)] {
+        let enc_selected = ek
+            .oselect(&mut rng, cond, &enc_a, &enc_b)
+            .unwrap()
+            .ciphertext;
+        assert_ne!(enc_selected, enc_a);
+        assert_ne!(enc_selected, enc_b);
+        assert_eq!(dk.decrypt(&enc_selected).unwrap(), *expected);
+    }
+}
+
+#[test]
+fn homomorphic_ops_output_is_canonical() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+    let nn = ek.nn();
+
+    let enc_a = ek
+        .encrypt_with_random(&mut rng, &Integer::from(-5))
+        .unwrap()
+        .ciphertext;
+    let enc_b = ek
+        .encrypt_with_random(&mut rng, &Integer::from(7))
+        .unwrap()
+        .ciphertext;
+    // Same ciphertexts, but not reduced modulo N^2
+    let enc_a_big = (&enc_a + nn).complete();
+    let enc_b_big = &enc_b + (nn * 3u8).complete();
+
+    let is_canonical = |c: &Integer| c.cmp0().is_ge() && c < nn;
+    assert!(is_canonical(&enc_a) && is_canonical(&enc_b));
+
+    for (a, b) in [(&enc_a, &enc_b), (&enc_a_big, &enc_b_big)] {
+        let outputs = [
+            ek.oadd(a, b).unwrap(),
+            ek.osub(a, b).unwrap(),
+            ek.oneg(a).unwrap(),
+            ek.omul(&Integer::from(-3), a).unwrap(),
+            ek.omul(&Integer::from(3), a).unwrap(),
+            dk.omul(&Integer::from(-3), a).unwrap(),
+            dk.omul(&Integer::from(3), a).unwrap(),
+            ek.omul_i64(-3, a).unwrap(),
+            ek.omul_u64(0, a).unwrap(),
+            ek.odot(
+                &[Integer::from(-2), Integer::from(5)],
+                &[a.clone(), b.clone()],
+            )
+            .unwrap(),
+        ];
+        for (i, c) in outputs.iter().enumerate() {
+            assert!(is_canonical(c), "output {i} is not canonical");
+        }
+    }
+}
+
 #[test]
 fn encryption_with_known_factorization() {
     let mut rng = rand_dev::DevRng::new();
@@ -130,18 +625,34 @@ fn encryption_with_known_factorization() {
 
     for i in 0..100 {
         println!("Iteration {i}");
-        let x = ek
-            .n()
-            .clone()
-            .random_below(&mut utils::external_rand(&mut rng));
-        let x = x - ek.half_n();
+        let x = utils::random_in_signed_group(&mut rng, ek);
 
         let nonce = utils::sample_in_mult_group(&mut rng, ek.n());
 
         let enc_x1 = ek.encrypt_with(&x, &nonce).unwrap();
         let enc_x2 = dk.encrypt_with(&x, &nonce).unwrap();
+        let enc_x3 = ek.encrypt_with_unchecked(&x, &nonce).unwrap();
+        let enc_x4 = dk.encrypt_with_unchecked(&x, &nonce).unwrap();
 
         assert_eq!(enc_x1, enc_x2);
+        assert_eq!(enc_x1, enc_x3);
+        assert_eq!(enc_x1, enc_x4);
+    }
+}
+
+#[test]
+fn encryption_with_precomputed_nonce_power() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    for _ in 0..20 {
+        let x = utils::random_in_signed_group(&mut rng, ek);
+
+        let (nonce, nonce_pow_n) = dk.sample_nonce_and_power(&mut rng).unwrap();
+        let expected = ek.encrypt_with(&x, &nonce).unwrap();
+        let actual = ek.encrypt_with_precomputed(&x, &nonce_pow_n).unwrap();
+        assert_eq!(expected, actual);
     }
 }
 
@@ -155,12 +666,15 @@ fn factorized_exp_mod_n() {
     println!("n: {n}");
 
     let crt = utils::CrtExp::build_n(&p, &q).unwrap();
+    assert_eq!(*crt.modulus(), n);
 
     for _ in 0..100 {
         let x: Integer = n
-            .random_below_ref(&mut utils::external_rand(&mut rng))
+            .random_below_ref(&mut utils::ExternalRand64::new(&mut rng).rand_state())
             .into();
-        let mut e: Integer = Integer::random_bits(1024, &mut utils::external_rand(&mut rng)).into();
+        let mut e: Integer =
+            Integer::random_bits(1024, &mut utils::ExternalRand64::new(&mut rng).rand_state())
+                .into();
         if rng.gen::<bool>() {
             e = -e
         }
@@ -186,12 +700,15 @@ fn factorized_exp_mod_nn() {
     println!("nn: {nn}");
 
     let crt = utils::CrtExp::build_nn(&p, &q).unwrap();
+    assert_eq!(*crt.modulus(), nn);
 
     for _ in 0..100 {
         let x: Integer = nn
-            .random_below_ref(&mut utils::external_rand(&mut rng))
+            .random_below_ref(&mut utils::ExternalRand64::new(&mut rng).rand_state())
             .into();
-        let mut e: Integer = Integer::random_bits(1024, &mut utils::external_rand(&mut rng)).into();
+        let mut e: Integer =
+            Integer::random_bits(1024, &mut utils::ExternalRand64::new(&mut rng).rand_state())
+                .into();
         if rng.gen::<bool>() {
             e = -e
         }
@@ -207,6 +724,2215 @@ fn factorized_exp_mod_nn() {
     }
 }
 
+#[test]
+fn factorized_exp_huge_exponent() {
+    let mut rng = rand_dev::DevRng::new();
+
+    let p = utils::generate_safe_prime(&mut rng, 64);
+    let q = utils::generate_safe_prime(&mut rng, 64);
+    let nn = (&p * &q).complete().square();
+    let crt = utils::CrtExp::build_nn(&p, &q).unwrap();
+
+    // 2-megabit exponent
+    let e: Integer = Integer::random_bits(
+        1 << 21,
+        &mut utils::ExternalRand64::new(&mut rng).rand_state(),
+    )
+    .into();
+    let x = utils::sample_in_mult_group(&mut rng, &nn);
+    let expected: Integer = x.pow_mod_ref(&e, &nn).unwrap().into();
+    assert_eq!(crt.exp(&x, &crt.prepare_exponent(&e)).unwrap(), expected);
+    // x^-e = (x^e)^-1
+    let expected = expected.invert(&nn).unwrap();
+    assert_eq!(crt.exp(&x, &crt.prepare_exponent(&-e)).unwrap(), expected);
+
+    // Exponents that are multiples of `phi` on either side of zero
+    let phi_pp = &p * (&p - 1u8).complete();
+    for e in [phi_pp.clone(), -phi_pp] {
+        let expected: Integer = x.pow_mod_ref(&e, &nn).unwrap().into();
+        assert_eq!(crt.exp(&x, &crt.prepare_exponent(&e)).unwrap(), expected);
+    }
+}
+
+#[test]
+fn crt_recombine() {
+    let mut rng = rand_dev::DevRng::new();
+
+    let primes: Vec<_> = (0..3)
+        .map(|_| utils::generate_safe_prime(&mut rng, 128))
+        .collect();
+    let crt = utils::CrtExp::build_nn_from_primes(&primes).unwrap();
+    assert_eq!(crt.factors_count(), 3);
+    let factors: Vec<_> = primes.iter().map(|p| p.square_ref().complete()).collect();
+
+    for _ in 0..20 {
+        let x: Integer = crt
+            .modulus()
+            .random_below_ref(&mut utils::ExternalRand64::new(&mut rng).rand_state())
+            .into();
+        let residues: Vec<_> = factors.iter().map(|n_i| (&x % n_i).complete()).collect();
+        assert_eq!(crt.recombine(&residues).unwrap(), x);
+
+        // Residues don't have to be reduced
+        let unreduced: Vec<_> = residues
+            .iter()
+            .zip(&factors)
+            .map(|(r, n_i)| (r - n_i * 3u8).complete())
+            .collect();
+        assert_eq!(crt.recombine(&unreduced).unwrap(), x);
+    }
+
+    // Wrong amount of residues
+    assert!(crt.recombine(&[Integer::ZERO, Integer::ZERO]).is_none());
+}
+
+#[test]
+fn crt_recombine_reveals_factorization() {
+    let mut rng = rand_dev::DevRng::new();
+    let p = utils::generate_safe_prime(&mut rng, 128);
+    let q = utils::generate_safe_prime(&mut rng, 128);
+    let crt = utils::CrtExp::build_n(&p, &q).unwrap();
+
+    // As documented, `x = 1 mod p` and `x = 0 mod q` shares factor `q` with `n`
+    let x = crt.recombine(&[Integer::from(1), Integer::ZERO]).unwrap();
+    assert_eq!(x.gcd(crt.modulus()), q);
+}
+
+#[test]
+fn nonce_pq_in_mult_group() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    for _ in 0..1000 {
+        let nonce = utils::sample_nonce_pq(&mut rng, ek.n());
+        assert!(nonce > 0 && nonce < *ek.n());
+        assert!(utils::in_mult_group(&nonce, ek.n()));
+    }
+
+    let x = utils::random_in_signed_group(&mut rng, ek);
+    let enc = dk.encrypt_with_random(&mut rng, &x).unwrap();
+    assert!(utils::in_mult_group(&enc.nonce, ek.n()));
+    assert_eq!(dk.decrypt(&enc.ciphertext).unwrap(), x);
+
+    // Encryption key doesn't assume anything about `N`, so nonce is always in `Z*_N` even
+    // if `N` has small factors
+    let ek = fast_paillier::EncryptionKey::from_n(Integer::from(3 * 5 * 7 * 11 * 13));
+    for _ in 0..1000 {
+        let enc = ek.encrypt_with_random(&mut rng, &Integer::from(1)).unwrap();
+        assert!(utils::in_mult_group(&enc.nonce, ek.n()));
+    }
+}
+
+#[test]
+fn custom_primality_tester() {
+    struct Counting {
+        calls: std::cell::Cell<u64>,
+    }
+    impl utils::PrimalityTest for Counting {
+        fn is_prime(&self, n: &Integer, reps: u32) -> bool {
+            self.calls.set(self.calls.get() + 1);
+            utils::GmpPrimalityTest.is_prime(n, reps)
+        }
+    }
+
+    let mut rng = rand_dev::DevRng::new();
+    let tester = Counting {
+        calls: std::cell::Cell::new(0),
+    };
+    let p = utils::sieve_generate_safe_primes_with_tester(&mut rng, 256, 100, &tester);
+    assert!(utils::is_safe_prime(&p, 25));
+    assert_eq!(p.significant_bits(), 256);
+    // at least `(p-1)/2` and `p` were tested
+    assert!(tester.calls.get() >= 2);
+
+    // tester that rejects everything but a known safe prime
+    struct Only(Integer);
+    impl utils::PrimalityTest for Only {
+        fn is_prime(&self, n: &Integer, _reps: u32) -> bool {
+            *n == self.0 || *n == (&self.0 >> 1u32).complete()
+        }
+    }
+    let expected = utils::generate_safe_prime(&mut rng, 16);
+    let p = utils::sieve_generate_safe_primes_with_tester(&mut rng, 16, 0, &Only(expected.clone()));
+    assert_eq!(p, expected);
+}
+
+#[test]
+fn refresh_ciphertext() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let a = utils::random_in_signed_group(&mut rng, ek) >> 1u32;
+    let b = utils::random_in_signed_group(&mut rng, ek) >> 1u32;
+    let c_a = ek.encrypt_with_random(&mut rng, &a).unwrap().ciphertext;
+    let c_b = ek.encrypt_with_random(&mut rng, &b).unwrap().ciphertext;
+    let c = ek.oadd(&c_a, &c_b).unwrap();
+
+    let (refreshed, x, nonce) = dk.refresh(&mut rng, &c).unwrap();
+    assert_eq!(x, (&a + &b).complete());
+    assert_ne!(refreshed, c);
+    assert_eq!(dk.decrypt(&refreshed).unwrap(), x);
+    assert_eq!(ek.encrypt_with(&x, &nonce).unwrap(), refreshed);
+
+    assert!(dk.refresh(&mut rng, &Integer::ZERO).is_err());
+}
+
+#[test]
+fn batch_operations_edge_cases() {
+    use fast_paillier::{AnyEncryptionKey, ErrorKind};
+
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    // Empty inputs
+    assert!(ek.encrypt_many(&mut rng, &[]).unwrap().is_empty());
+    assert_eq!(ek.osum(&[] as &[Integer]).unwrap(), ek.oadd_identity());
+    assert_eq!(dk.decrypt(&ek.osum(&[] as &[Integer]).unwrap()).unwrap(), 0);
+    assert_eq!(AnyEncryptionKey::osum(&dk, &[]).unwrap(), 1);
+    assert_eq!(ek.odot(&[], &[] as &[Integer]).unwrap(), ek.oadd_identity());
+    assert_eq!(ek.prepare_odot(&[]).unwrap().odot(&[]).unwrap(), 1);
+
+    // Batch encryption
+    let plaintexts: Vec<_> = (-3..3).map(Integer::from).collect();
+    let encryptions = ek.encrypt_many(&mut rng, &plaintexts).unwrap();
+    let ciphertexts: Vec<_> = encryptions.iter().map(|e| e.ciphertext.clone()).collect();
+    assert_eq!(dk.decrypt_all(&ciphertexts).unwrap(), plaintexts);
+    for (enc, x) in encryptions.iter().zip(&plaintexts) {
+        assert_eq!(ek.encrypt_with(x, &enc.nonce).unwrap(), enc.ciphertext);
+    }
+
+    let mut out_of_range = plaintexts.clone();
+    out_of_range[2] = ek.n().clone();
+    let err = ek.encrypt_many(&mut rng, &out_of_range).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Encrypt);
+    assert_eq!(err.index(), Some(2));
+
+    // Length mismatch
+    let scalars: Vec<_> = (1..=5).map(Integer::from).collect();
+    let err = ek.odot(&scalars, &ciphertexts).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Ops);
+    assert_eq!(err.index(), None);
+    assert_eq!(
+        err.to_string(),
+        "homomorphic operation failed: expected 6 elements, got 5"
+    );
+    let err = AnyEncryptionKey::odot(&dk, &scalars, &ciphertexts).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Ops);
+    let prepared = ek.prepare_odot(&ciphertexts).unwrap();
+    assert_eq!(prepared.odot(&scalars).unwrap_err().kind(), ErrorKind::Ops);
+    let matrix = vec![scalars[..1].to_vec(), scalars.clone()];
+    assert!(ek.omatvec(&mut rng, &matrix, &ciphertexts).is_err());
+    assert!(dk.omatvec(&mut rng, &matrix, &ciphertexts).is_err());
+
+    // Invalid ciphertext
+    let mut invalid = ciphertexts.clone();
+    invalid[3] = Integer::ZERO;
+    assert_eq!(ek.osum(&invalid).unwrap_err().index(), Some(3));
+    let scalars: Vec<_> = (1..=6).map(Integer::from).collect();
+    assert_eq!(ek.odot(&scalars, &invalid).unwrap_err().index(), Some(3));
+    assert_eq!(ek.prepare_odot(&invalid).unwrap_err().index(), Some(3));
+    let matrix = vec![scalars];
+    assert_eq!(
+        dk.omatvec(&mut rng, &matrix, &invalid).unwrap_err().index(),
+        Some(3)
+    );
+}
+
+#[test]
+fn key_hash_is_stable() {
+    use std::hash::{Hash, Hasher};
+
+    fn hash_of(value: &impl Hash) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    // Hash is defined by canonical bytes of `N`
+    assert_eq!(hash_of(ek), hash_of(&ek.to_bytes()));
+
+    let ek2 = fast_paillier::EncryptionKey::from_bytes(&ek.to_bytes()).unwrap();
+    assert!(*ek == ek2);
+    assert_eq!(hash_of(ek), hash_of(&ek2));
+    let ek3 = fast_paillier::EncryptionKey::from_n_lazy(ek.n().clone());
+    assert_eq!(hash_of(ek), hash_of(&ek3));
+
+    let dk2 = DecryptionKey::from_bytes(&dk.to_bytes()).unwrap();
+    assert_eq!(hash_of(&dk), hash_of(&dk2));
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_string(ek).unwrap();
+        let ek2: fast_paillier::EncryptionKey = serde_json::from_str(&json).unwrap();
+        assert_eq!(hash_of(ek), hash_of(&ek2));
+        let json = serde_json::to_string(&dk).unwrap();
+        let dk2: DecryptionKey = serde_json::from_str(&json).unwrap();
+        assert_eq!(hash_of(&dk), hash_of(&dk2));
+    }
+
+    let other = random_key_for_tests(&mut rng);
+    assert!(*ek != *other.encryption_key());
+    assert_ne!(hash_of(ek), hash_of(other.encryption_key()));
+}
+
+#[test]
+fn pow_mod_with_crt() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let n = dk.n();
+    let nn = dk.nn();
+
+    for _ in 0..20 {
+        let x = utils::sample_in_mult_group(&mut rng, nn);
+        let e = Integer::from(
+            nn.random_below_ref(&mut utils::ExternalRand64::new(&mut rng).rand_state()),
+        );
+        for e in [e.clone(), -e] {
+            let expected = Integer::from(x.pow_mod_ref(&e, n).unwrap());
+            assert_eq!(dk.pow_mod_n(&x, &e).unwrap(), expected);
+            let expected = Integer::from(x.pow_mod_ref(&e, nn).unwrap());
+            assert_eq!(dk.pow_mod_nn(&x, &e).unwrap(), expected);
+        }
+    }
+
+    // Edge-case exponents
+    let x = utils::sample_in_mult_group(&mut rng, nn);
+    let phi_nn = (dk.phi() * n).complete();
+    for e in [
+        Integer::ZERO,
+        Integer::from(1),
+        Integer::from(-1),
+        dk.phi().clone(),
+        n.clone(),
+        (-n).complete(),
+        (&phi_nn + 1u8).complete(),
+        (nn * nn).complete(),
+    ] {
+        let expected = Integer::from(x.pow_mod_ref(&e, n).unwrap());
+        assert_eq!(dk.pow_mod_n(&x, &e).unwrap(), expected);
+        let expected = Integer::from(x.pow_mod_ref(&e, nn).unwrap());
+        assert_eq!(dk.pow_mod_nn(&x, &e).unwrap(), expected);
+    }
+
+    // `x` not in `Z*_N`
+    let p = dk.p().clone();
+    assert!(dk.pow_mod_n(&p, &Integer::from(3)).is_err());
+    assert!(dk.pow_mod_nn(&p, &Integer::from(3)).is_err());
+    assert!(dk.pow_mod_n(&Integer::ZERO, &Integer::from(3)).is_err());
+}
+
+#[test]
+fn exp_mod_nn() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let nn = dk.nn();
+
+    let x = utils::sample_in_mult_group(&mut rng, nn);
+    let e =
+        Integer::from(nn.random_below_ref(&mut utils::ExternalRand64::new(&mut rng).rand_state()));
+    for e in [e.clone(), -e, Integer::ZERO] {
+        let expected = Integer::from(x.pow_mod_ref(&e, nn).unwrap());
+        assert_eq!(dk.exp_mod_nn(&x, &e).unwrap(), expected);
+    }
+
+    assert!(dk.exp_mod_nn(dk.p(), &Integer::from(3)).is_err());
+}
+
+#[test]
+fn concurrent_encryption_and_decryption() {
+    // With `pool` feature, each thread reuses scratch integers from its own pool, so results
+    // must not be affected by other threads
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            let mut rng = rng.fork();
+            let dk = &dk;
+            s.spawn(move || {
+                for _ in 0..50 {
+                    let x = utils::random_in_signed_group(&mut rng, ek);
+                    let nonce = utils::sample_in_mult_group(&mut rng, ek.n());
+                    let c = ek.encrypt_with(&x, &nonce).unwrap();
+                    assert_eq!(dk.encrypt_with(&x, &nonce).unwrap(), c);
+                    assert_eq!(dk.decrypt(&c).unwrap(), x);
+                }
+            });
+        }
+    });
+}
+
+#[test]
+fn odouble() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let plaintexts = utils::signed_group_corner_cases(ek)
+        .into_iter()
+        .chain(std::iter::repeat_with(|| utils::random_in_signed_group(&mut rng, ek)).take(20))
+        .collect::<Vec<_>>();
+    for x in plaintexts {
+        let c = ek.encrypt_with_random(&mut rng, &x).unwrap().ciphertext;
+        let doubled = ek.odouble(&c).unwrap();
+        assert_eq!(doubled, ek.oadd(&c, &c).unwrap());
+        let expected = utils::signed_modulo(&(&x * 2u8).complete(), ek.n());
+        assert_eq!(dk.decrypt(&doubled).unwrap(), expected);
+    }
+
+    assert!(ek.odouble(&Integer::ZERO).is_err());
+    assert!(ek.odouble(ek.nn()).is_err());
+}
+
+#[test]
+fn batch_operations_accept_borrowed_ciphertexts() {
+    /// Ciphertext stored along with some metadata
+    #[derive(Clone)]
+    struct Labeled {
+        #[allow(dead_code)]
+        label: &'static str,
+        ciphertext: Integer,
+    }
+    impl std::borrow::Borrow<Integer> for Labeled {
+        fn borrow(&self) -> &Integer {
+            &self.ciphertext
+        }
+    }
+
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let plaintexts: Vec<_> = (1..=4).map(Integer::from).collect();
+    let labeled: Vec<_> = ek
+        .encrypt_many(&mut rng, &plaintexts)
+        .unwrap()
+        .into_iter()
+        .map(|e| Labeled {
+            label: "x",
+            ciphertext: e.ciphertext,
+        })
+        .collect();
+    let scalars: Vec<_> = (5..=8).map(Integer::from).collect();
+
+    // Iterator of wrappers, iterator of references, and a slice give the same result
+    let sum = ek.osum(labeled.iter().cloned()).unwrap();
+    assert_eq!(dk.decrypt(&sum).unwrap(), 10);
+    let refs: Vec<&Integer> = labeled.iter().map(|l| &l.ciphertext).collect();
+    assert_eq!(ek.osum(refs.iter().copied()).unwrap(), sum);
+    let ciphertexts: Vec<Integer> = labeled.iter().map(|l| l.ciphertext.clone()).collect();
+    assert_eq!(ek.osum(&ciphertexts).unwrap(), sum);
+
+    let dot = ek.odot(&scalars, labeled.clone()).unwrap();
+    assert_eq!(dk.decrypt(&dot).unwrap(), 5 + 2 * 6 + 3 * 7 + 4 * 8);
+    assert_eq!(ek.odot(&scalars, refs.iter().copied()).unwrap(), dot);
+    assert_eq!(ek.odot(&scalars, &ciphertexts).unwrap(), dot);
+
+    // Errors carry index of invalid ciphertext regardless of input type
+    let mut invalid = labeled.clone();
+    invalid[2].ciphertext = Integer::ZERO;
+    assert_eq!(
+        ek.osum(invalid.iter().cloned()).unwrap_err().index(),
+        Some(2)
+    );
+    assert_eq!(ek.odot(&scalars, invalid).unwrap_err().index(), Some(2));
+    assert!(ek.odot(&scalars[1..], refs).is_err());
+}
+
+#[test]
+fn runtime_benchmark() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+
+    let report = dk.benchmark(&mut rng, 3).unwrap();
+    assert_eq!(report.iterations, 3);
+    for ops in [
+        report.encrypt_per_sec,
+        report.decrypt_per_sec,
+        report.omul_per_sec,
+    ] {
+        assert!(ops.is_finite() && ops > 0.);
+    }
+
+    assert_eq!(dk.benchmark(&mut rng, 0).unwrap().iterations, 1);
+}
+
+#[test]
+fn enc_one_and_neg_one() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    assert_eq!(dk.decrypt(ek.enc_one()).unwrap(), 1);
+    assert_eq!(dk.decrypt(ek.enc_neg_one()).unwrap(), -1);
+    // Deterministic, i.e. encrypted with nonce `1`
+    assert_eq!(
+        *ek.enc_one(),
+        ek.encrypt_with(&Integer::from(1), &Integer::from(1))
+            .unwrap()
+    );
+    assert_eq!(
+        *ek.enc_neg_one(),
+        ek.encrypt_with(&Integer::from(-1), &Integer::from(1))
+            .unwrap()
+    );
+    // Lazily constructed key gives the same values
+    let lazy = fast_paillier::EncryptionKey::from_n_lazy(ek.n().clone());
+    assert_eq!(lazy.enc_one(), ek.enc_one());
+    assert_eq!(lazy.enc_neg_one(), ek.enc_neg_one());
+
+    // Increment and decrement
+    let x = Integer::from(41);
+    let c = ek.encrypt_with_random(&mut rng, &x).unwrap().ciphertext;
+    let inc = ek.oadd(&c, ek.enc_one()).unwrap();
+    assert_eq!(dk.decrypt(&inc).unwrap(), 42);
+    let dec = ek.oadd(&c, ek.enc_neg_one()).unwrap();
+    assert_eq!(dk.decrypt(&dec).unwrap(), 40);
+    let zero = ek.oadd(ek.enc_one(), ek.enc_neg_one()).unwrap();
+    assert_eq!(dk.decrypt(&zero).unwrap(), 0);
+}
+
+#[test]
+fn blind_compare() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let bound = Integer::from(1_000_000);
+    for m in [-1_000_000, -12345, -1, 0, 1, 12345, 1_000_000] {
+        let m = Integer::from(m);
+        let c = ek.encrypt_with_random(&mut rng, &m).unwrap().ciphertext;
+        for _ in 0..5 {
+            let blinded = dk.blind_compare(&mut rng, &c, &bound).unwrap();
+            let v = dk.decrypt(&blinded).unwrap();
+            match m.cmp0() {
+                std::cmp::Ordering::Less => assert!(v < 0),
+                std::cmp::Ordering::Equal => assert!(v >= 0),
+                std::cmp::Ordering::Greater => assert!(v > 0),
+            }
+            assert!(v.abs() <= *ek.half_n());
+        }
+    }
+
+    // Comparison of two encrypted values
+    let a = ek
+        .encrypt_with_random(&mut rng, &Integer::from(10))
+        .unwrap()
+        .ciphertext;
+    let b = ek
+        .encrypt_with_random(&mut rng, &Integer::from(20))
+        .unwrap()
+        .ciphertext;
+    let diff = ek.osub(&a, &b).unwrap();
+    let v = dk
+        .decrypt(&dk.blind_compare(&mut rng, &diff, &bound).unwrap())
+        .unwrap();
+    assert!(v < 0);
+
+    // Largest bound leaves no room for blinding
+    let m = utils::random_in_signed_group(&mut rng, ek);
+    let c = ek.encrypt_with_random(&mut rng, &m).unwrap().ciphertext;
+    let largest = (ek.half_n() >> 1u32).complete();
+    let blinded = dk.blind_compare(&mut rng, &c, &largest).unwrap();
+    assert_eq!(dk.decrypt(&blinded).unwrap(), m);
+
+    assert!(dk.blind_compare(&mut rng, &c, ek.half_n()).is_err());
+    assert!(dk.blind_compare(&mut rng, &c, &Integer::from(-1)).is_err());
+    assert!(dk.blind_compare(&mut rng, &Integer::ZERO, &bound).is_err());
+}
+
+#[test]
+fn omul_signed() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let x = Integer::from(-123456789);
+    let c = ek.encrypt_with_random(&mut rng, &x).unwrap().ciphertext;
+    for scalar in [-1000, -2, -1, 0, 1, 2, 1000] {
+        let scalar = Integer::from(scalar);
+        let product = ek.omul_signed(&scalar, &c).unwrap();
+        assert_eq!(dk.decrypt(&product).unwrap(), (&scalar * &x).complete());
+    }
+
+    // Product wraps around `N`
+    for _ in 0..10 {
+        let scalar = utils::random_in_signed_group(&mut rng, ek);
+        let x = utils::random_in_signed_group(&mut rng, ek);
+        let c = ek.encrypt_with_random(&mut rng, &x).unwrap().ciphertext;
+        let product = ek.omul_signed(&scalar, &c).unwrap();
+        let expected = utils::signed_modulo(&(&scalar * &x).complete(), ek.n());
+        assert_eq!(dk.decrypt(&product).unwrap(), expected);
+    }
+
+    for scalar in utils::signed_group_corner_cases(ek) {
+        assert!(ek.omul_signed(&scalar, &c).is_ok());
+    }
+    for scalar in utils::signed_group_out_of_range_cases(ek) {
+        assert!(ek.omul_signed(&scalar, &c).is_err());
+    }
+    assert!(ek.omul_signed(&Integer::from(2), &Integer::ZERO).is_err());
+}
+
+#[test]
+fn keys_from_bytes() {
+    use fast_paillier::{EncryptionKey, ErrorKind};
+
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    // Valid inputs
+    let ek_bytes = ek.to_bytes();
+    let ek2 = EncryptionKey::try_from(ek_bytes.as_slice()).unwrap();
+    assert_eq!(ek.n(), ek2.n());
+
+    let dk_bytes = dk.to_bytes();
+    let dk2 = DecryptionKey::try_from(dk_bytes.as_slice()).unwrap();
+    assert_eq!(dk.p(), dk2.p());
+    assert_eq!(dk.q(), dk2.q());
+
+    // Truncated inputs
+    let err = EncryptionKey::try_from(&[][..]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidEncoding);
+    for len in [0, 3, 4, 50, dk_bytes.len() - 1] {
+        let err = DecryptionKey::try_from(&dk_bytes[..len]).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidEncoding, "len = {len}");
+    }
+    let mut extended = dk_bytes.clone();
+    extended.push(0);
+    let err = DecryptionKey::try_from(extended.as_slice()).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidEncoding);
+
+    // Value-invalid inputs
+    for n in [&[0u8][..], &[1], &[0x10, 0x00]] {
+        let err = EncryptionKey::try_from(n).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidKey);
+    }
+    let encode = |p: &[u8], q: &[u8]| {
+        let mut bytes = vec![];
+        bytes.extend_from_slice(&(p.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(p);
+        bytes.extend_from_slice(&(q.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(q);
+        bytes
+    };
+    let p = dk.p().to_digits::<u8>(rug::integer::Order::Msf);
+    for (p, q) in [(&p[..], &p[..]), (&[4], &[9])] {
+        let err = DecryptionKey::try_from(encode(p, q).as_slice())
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidKey);
+    }
+}
+
+#[cfg(feature = "postcard")]
+#[test]
+fn encryption_key_from_decryption_key_bytes() {
+    use fast_paillier::{EncryptionKey, ErrorKind};
+
+    let mut rng = rand_dev::DevRng::new();
+    let primes: Vec<_> = (0..3)
+        .map(|_| utils::generate_safe_prime(&mut rng, 128))
+        .collect();
+    let multiprime = DecryptionKey::from_multiprime(primes).unwrap();
+
+    for dk in [random_key_for_tests(&mut rng), multiprime] {
+        let dk_bytes = postcard::to_allocvec(&dk).unwrap();
+
+        let expected: DecryptionKey = postcard::from_bytes(&dk_bytes).unwrap();
+        let expected = expected.encryption_key();
+        let actual = EncryptionKey::from_decryption_key_bytes(&dk_bytes).unwrap();
+        assert_eq!(expected.n(), actual.n());
+        assert_eq!(expected.nn(), actual.nn());
+
+        let err =
+            EncryptionKey::from_decryption_key_bytes(&dk_bytes[..dk_bytes.len() - 1]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidEncoding);
+        let mut trailing = dk_bytes.clone();
+        trailing.push(0);
+        let err = EncryptionKey::from_decryption_key_bytes(&trailing).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidEncoding);
+    }
+}
+
+#[test]
+fn multiprime_key() {
+    let mut rng = rand_dev::DevRng::new();
+    let primes: Vec<_> = (0..3)
+        .map(|_| utils::generate_safe_prime(&mut rng, 512))
+        .collect();
+    let dk = DecryptionKey::from_multiprime(primes.clone()).unwrap();
+    let ek = dk.encryption_key();
+    assert_eq!(*ek.n(), primes.iter().product::<Integer>());
+    let mut sorted = primes.clone();
+    sorted.sort();
+    assert_eq!(dk.primes(), sorted.as_slice());
+
+    for _ in 0..20 {
+        let x = utils::random_in_signed_group(&mut rng, ek);
+        let nonce = utils::sample_in_mult_group(&mut rng, ek.n());
+
+        let enc_x = ek.encrypt_with(&x, &nonce).unwrap();
+        assert_eq!(enc_x, dk.encrypt_with(&x, &nonce).unwrap());
+        assert_eq!(dk.decrypt(&enc_x).unwrap(), x);
+
+        let enc_minus_x = dk.omul(&Integer::from(-1), &enc_x).unwrap();
+        assert_eq!(
+            dk.decrypt(&enc_minus_x).unwrap(),
+            signed_modulo(&-x, ek.n())
+        );
+    }
+
+    let dk2 = DecryptionKey::from_bytes(&dk.to_bytes()).unwrap();
+    assert_eq!(dk2.primes(), dk.primes());
+
+    // Primes must be distinct
+    let err = DecryptionKey::from_multiprime(vec![
+        primes[0].clone(),
+        primes[1].clone(),
+        primes[0].clone(),
+    ])
+    .err()
+    .unwrap();
+    assert_eq!(err.kind(), fast_paillier::ErrorKind::InvalidKey);
+}
+
+#[test]
+fn factorized_exp_mod_nn_multiprime() {
+    let mut rng = rand_dev::DevRng::new();
+
+    let primes: Vec<_> = (0..4)
+        .map(|_| utils::generate_safe_prime(&mut rng, 256))
+        .collect();
+    let nn = primes.iter().product::<Integer>().square();
+
+    let crt = utils::CrtExp::build_nn_from_primes(&primes).unwrap();
+    assert_eq!(*crt.modulus(), nn);
+
+    for _ in 0..100 {
+        let x: Integer = nn
+            .random_below_ref(&mut utils::ExternalRand64::new(&mut rng).rand_state())
+            .into();
+        let mut e: Integer =
+            Integer::random_bits(1024, &mut utils::ExternalRand64::new(&mut rng).rand_state())
+                .into();
+        if rng.gen::<bool>() {
+            e = -e
+        }
+        let crt_e = crt.prepare_exponent(&e);
+
+        let expected: Option<Integer> = x.pow_mod_ref(&e, &nn).map(Into::into);
+        let actual = crt.exp(&x, &crt_e);
+        assert_eq!(expected, actual);
+    }
+}
+
+#[test]
+fn accessors_return_stable_references() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let accessors: [fn(&DecryptionKey) -> &Integer; 9] = [
+        DecryptionKey::n,
+        DecryptionKey::nn,
+        DecryptionKey::half_n,
+        DecryptionKey::phi,
+        DecryptionKey::lambda,
+        DecryptionKey::mu,
+        DecryptionKey::p,
+        DecryptionKey::q,
+        |dk| dk.encryption_key().n(),
+    ];
+    for accessor in accessors {
+        assert!(std::ptr::eq(accessor(&dk), accessor(&dk)));
+    }
+    assert!(std::ptr::eq(dk.n(), ek.n()));
+    assert!(std::ptr::eq(dk.nn(), ek.nn()));
+    assert!(std::ptr::eq(dk.half_n(), ek.half_n()));
+
+    let phi = (dk.p() - 1u8).complete() * (dk.q() - 1u8).complete();
+    assert_eq!(*dk.phi(), phi);
+    assert_eq!(*dk.nn(), ek.n().square_ref().complete());
+}
+
+#[cfg(feature = "getrandom")]
+#[test]
+fn generate_default() {
+    let dk = DecryptionKey::generate_default().unwrap();
+    let ek = dk.encryption_key();
+    assert_eq!(dk.bits_length(), 1536);
+
+    let x = Integer::from(-42);
+    let ciphertext = ek.encrypt_default(&x).unwrap().ciphertext;
+    assert_eq!(dk.decrypt(&ciphertext).unwrap(), x);
+}
+
+#[test]
+fn decryption_key_equality() {
+    use std::hash::{BuildHasher, RandomState};
+
+    let mut rng = rand_dev::DevRng::new();
+    let p = utils::generate_safe_prime(&mut rng, 512);
+    let q = utils::generate_safe_prime(&mut rng, 512);
+
+    let dk1 = DecryptionKey::from_primes(p.clone(), q.clone()).unwrap();
+    let dk2 = DecryptionKey::from_primes(q.clone(), p.clone()).unwrap();
+    let dk3 = random_key_for_tests(&mut rng);
+
+    assert!(dk1 == dk1.clone());
+    assert!(dk1 == dk2);
+    assert!(dk1 != dk3);
+
+    let hasher = RandomState::new();
+    assert_eq!(hasher.hash_one(&dk1), hasher.hash_one(&dk2));
+    assert_ne!(hasher.hash_one(&dk1), hasher.hash_one(&dk3));
+
+    // Order of primes is normalized
+    assert_eq!(dk1.p(), dk2.p());
+    assert_eq!(dk1.q(), dk2.q());
+    assert!(dk1.p() < dk1.q());
+    assert_eq!(dk1.primes(), [p.clone().min(q.clone()), p.max(q)]);
+    assert_eq!(dk1.to_bytes(), dk2.to_bytes());
+    #[cfg(feature = "serde")]
+    assert_eq!(
+        serde_json::to_string(&dk1).unwrap(),
+        serde_json::to_string(&dk2).unwrap()
+    );
+}
+
+#[test]
+fn crt_exp_rejects_equal_primes() {
+    let mut rng = rand_dev::DevRng::new();
+    let p = utils::generate_safe_prime(&mut rng, 256);
+    let q = utils::generate_safe_prime(&mut rng, 256);
+
+    assert!(utils::CrtExp::build_n(&p, &p).is_none());
+    assert!(utils::CrtExp::build_nn(&p, &p).is_none());
+    assert!(utils::CrtExp::build_nn_from_primes([&p, &q, &p]).is_none());
+
+    assert!(utils::CrtExp::build_n(&p, &q).is_some());
+    assert!(utils::CrtExp::build_nn(&p, &q).is_some());
+}
+
+#[test]
+fn omul_bounded() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let bound = Integer::from(1000);
+    let x = Integer::from(-1000);
+    let c = ek.encrypt_with_random(&mut rng, &x).unwrap().ciphertext;
+
+    // Largest scalar such that `|scalar| * bound <= N/2`
+    let max_scalar = (ek.half_n() / &bound).complete();
+    for scalar in [max_scalar.clone(), (-&max_scalar).complete()] {
+        let res = ek.omul_bounded(&scalar, &c, &bound).unwrap();
+        assert_eq!(dk.decrypt(&res).unwrap(), scalar * &x);
+    }
+
+    let too_large = (&max_scalar + 1u8).complete();
+    for scalar in [too_large.clone(), (-&too_large).complete()] {
+        assert!(ek.omul_bounded(&scalar, &c, &bound).is_err());
+    }
+    assert!(ek
+        .omul_bounded(&Integer::from(2), &c, &Integer::from(-1))
+        .is_err());
+}
+
+#[test]
+fn factor_n_from_lambda() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+
+    let (p, q) = if dk.p() < dk.q() {
+        (dk.p(), dk.q())
+    } else {
+        (dk.q(), dk.p())
+    };
+
+    let (p_recovered, q_recovered) = utils::factor_n_from_lambda(dk.n(), dk.lambda()).unwrap();
+    assert_eq!((&p_recovered, &q_recovered), (p, q));
+    let (p_recovered, q_recovered) = utils::factor_n_from_lambda(dk.n(), dk.phi()).unwrap();
+    assert_eq!((&p_recovered, &q_recovered), (p, q));
+
+    let dk2 = DecryptionKey::from_primes(p_recovered, q_recovered).unwrap();
+    assert!(dk == dk2);
+
+    assert!(utils::factor_n_from_lambda(dk.n(), &Integer::from(1)).is_none());
+    assert!(utils::factor_n_from_lambda(dk.n(), &(dk.lambda() + 2u8).complete()).is_none());
+}
+
+#[test]
+fn u64_limbs_roundtrip() {
+    let mut rng = rand_dev::DevRng::new();
+
+    assert!(utils::integer_to_u64_limbs(&Integer::ZERO).is_empty());
+    assert_eq!(utils::integer_from_u64_limbs(&[], true), 0);
+    assert_eq!(utils::integer_from_u64_limbs(&[0, 0], false), 0);
+
+    let x = Integer::from(1u8) << 64u32;
+    assert_eq!(utils::integer_to_u64_limbs(&x), [0, 1]);
+    assert_eq!(utils::integer_from_u64_limbs(&[0, 1], true), x);
+    assert_eq!(utils::integer_from_u64_limbs(&[1, 0], false), x);
+
+    for bits in [1, 63, 64, 65, 1024, 1536] {
+        let x = Integer::from(Integer::random_bits(
+            bits,
+            &mut utils::ExternalRand64::new(&mut rng).rand_state(),
+        ));
+        let limbs = utils::integer_to_u64_limbs(&x);
+        assert_eq!(utils::integer_from_u64_limbs(&limbs, true), x);
+
+        let reversed: Vec<u64> = limbs.iter().rev().copied().collect();
+        assert_eq!(utils::integer_from_u64_limbs(&reversed, false), x);
+    }
+}
+
+#[test]
+fn encrypt_with_context() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let context = Integer::from(0xC0FFEE);
+    let other_context = Integer::from(0xBEEF);
+    let max_context = (Integer::from(1) << fast_paillier::EncryptionKey::CONTEXT_BITS) - 1u8;
+
+    let max_x = (ek.half_n() >> (fast_paillier::EncryptionKey::CONTEXT_BITS + 1)).complete();
+    for x in [
+        Integer::from(42),
+        Integer::from(-42),
+        Integer::ZERO,
+        max_x.clone(),
+        (-&max_x).complete(),
+    ] {
+        for ctx in [&context, &Integer::ZERO, &max_context] {
+            let c = ek
+                .encrypt_with_context(&mut rng, &x, ctx)
+                .unwrap()
+                .ciphertext;
+            assert_eq!(dk.decrypt_with_context(&c, ctx).unwrap(), x);
+        }
+
+        let c = ek
+            .encrypt_with_context(&mut rng, &x, &context)
+            .unwrap()
+            .ciphertext;
+        let err = dk.decrypt_with_context(&c, &other_context).unwrap_err();
+        assert_eq!(err.kind(), fast_paillier::ErrorKind::Decrypt);
+    }
+
+    // Context out of range
+    assert!(ek
+        .encrypt_with_context(&mut rng, Integer::ONE, &(&max_context + 1u8).complete())
+        .is_err());
+    assert!(ek
+        .encrypt_with_context(&mut rng, Integer::ONE, &Integer::from(-1))
+        .is_err());
+    // Plaintext doesn't fit after packing
+    assert!(ek
+        .encrypt_with_context(&mut rng, ek.half_n(), &context)
+        .is_err());
+}
+
+#[test]
+fn lazy_encryption_key() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let eager = dk.encryption_key();
+
+    let lazy = fast_paillier::EncryptionKey::from_n_lazy(eager.n().clone());
+    assert_eq!(lazy.n(), eager.n());
+    assert_eq!(lazy.nn(), eager.nn());
+    assert_eq!(lazy.half_n(), eager.half_n());
+
+    let lazy = fast_paillier::EncryptionKey::from_n_lazy(eager.n().clone());
+    let x = Integer::from(-42);
+    let nonce = utils::sample_in_mult_group(&mut rng, eager.n());
+    let c = lazy.encrypt_with(&x, &nonce).unwrap();
+    assert_eq!(c, eager.encrypt_with(&x, &nonce).unwrap());
+    assert_eq!(dk.decrypt(&c).unwrap(), x);
+
+    let c2 = lazy.omul(&Integer::from(3), &c).unwrap();
+    assert_eq!(c2, eager.omul(&Integer::from(3), &c).unwrap());
+    assert_eq!(lazy.oadd(&c, &c2).unwrap(), eager.oadd(&c, &c2).unwrap());
+
+    let lazy = fast_paillier::EncryptionKey::from_n_lazy(eager.n().clone());
+    for x in [eager.half_n().clone(), (-eager.half_n()).complete()] {
+        assert_eq!(lazy.in_signed_group(&x), eager.in_signed_group(&x));
+        let x = x * 2u8;
+        assert_eq!(lazy.in_signed_group(&x), eager.in_signed_group(&x));
+    }
+}
+
+#[test]
+fn try_decrypt_matches_decrypt() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let valid = ek
+        .encrypt_with_random(&mut rng, &Integer::from(-42))
+        .unwrap()
+        .ciphertext;
+    let p_multiple = dk.p() * Integer::from(7);
+    let ciphertexts = [
+        valid,
+        Integer::ZERO,
+        Integer::from(-1),
+        ek.nn().clone(),
+        (ek.nn() + 1u8).complete(),
+        ek.n().clone(),
+        p_multiple,
+    ];
+    for c in &ciphertexts {
+        assert_eq!(dk.try_decrypt(c), dk.decrypt(c).ok());
+    }
+    assert_eq!(dk.try_decrypt(&ciphertexts[0]), Some(Integer::from(-42)));
+    assert_eq!(dk.try_decrypt(&ciphertexts[1]), None);
+}
+
+#[test]
+fn tagged_ciphertexts_from_different_keys() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk1 = random_key_for_tests(&mut rng);
+    let dk2 = random_key_for_tests(&mut rng);
+    let (ek1, ek2) = (dk1.encryption_key(), dk2.encryption_key());
+    assert_ne!(ek1.fingerprint(), ek2.fingerprint());
+
+    let a = ek1
+        .encrypt_with_random(&mut rng, &Integer::from(2))
+        .unwrap()
+        .ciphertext;
+    let b = ek1
+        .encrypt_with_random(&mut rng, &Integer::from(3))
+        .unwrap()
+        .ciphertext;
+    let c = ek2
+        .encrypt_with_random(&mut rng, &Integer::from(4))
+        .unwrap()
+        .ciphertext;
+    let (a, b, c) = (ek1.tag(a), ek1.tag(b), ek2.tag(c));
+
+    let sum = ek1.oadd_tagged(&a, &b).unwrap();
+    assert_eq!(sum.fingerprint(), ek1.fingerprint());
+    assert_eq!(dk1.decrypt(sum.ciphertext()).unwrap(), 5);
+    let diff = ek1.osub_tagged(&a, &b).unwrap();
+    assert_eq!(dk1.decrypt(diff.ciphertext()).unwrap(), -1);
+    let prod = ek1.omul_tagged(&Integer::from(7), &a).unwrap();
+    assert_eq!(dk1.decrypt(&prod.into_ciphertext()).unwrap(), 14);
+
+    let err = ek1.oadd_tagged(&a, &c).unwrap_err();
+    assert_eq!(err.kind(), fast_paillier::ErrorKind::Ops);
+    assert!(ek1.osub_tagged(&c, &a).is_err());
+    assert!(ek1.omul_tagged(&Integer::from(7), &c).is_err());
+    assert!(ek2.oadd_tagged(&a, &c).is_err());
+}
+
+#[cfg(feature = "test-vectors")]
+#[test]
+fn test_vectors_are_valid() {
+    use fast_paillier::test_vectors;
+    use rug::integer::IsPrime;
+
+    let is_safe_prime = |p: &Integer| {
+        let half = (p >> 1u32).complete();
+        p.is_probably_prime(25) != IsPrime::No && half.is_probably_prime(25) != IsPrime::No
+    };
+
+    let fixtures = [
+        (
+            test_vectors::safe_primes_512(),
+            DecryptionKey::test_key_1024(),
+            512,
+        ),
+        (
+            test_vectors::safe_primes_1024(),
+            DecryptionKey::test_key_2048(),
+            1024,
+        ),
+        (
+            test_vectors::safe_primes_1536(),
+            DecryptionKey::test_key_3072(),
+            1536,
+        ),
+    ];
+    let mut rng = rand_dev::DevRng::new();
+    for ((p, q), dk, bits) in fixtures {
+        assert!(is_safe_prime(&p));
+        assert!(is_safe_prime(&q));
+        assert_ne!(p, q);
+        assert_eq!(p.significant_bits(), bits);
+        assert_eq!(q.significant_bits(), bits);
+
+        assert_eq!(*dk.n(), (&p * &q).complete());
+        assert_eq!(dk.bits_length(), bits);
+
+        let x = Integer::from(-42);
+        let c = dk
+            .encryption_key()
+            .encrypt_with_random(&mut rng, &x)
+            .unwrap()
+            .ciphertext;
+        assert_eq!(dk.decrypt(&c).unwrap(), x);
+    }
+}
+
+#[test]
+fn verify_encryption() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let x = Integer::from(-42);
+    let Encryption {
+        ciphertext: c,
+        nonce,
+    } = ek.encrypt_with_random(&mut rng, &x).unwrap();
+    assert!(dk.verify_encryption(&c, &x, &nonce).unwrap());
+
+    // Tampered plaintext, nonce, or ciphertext
+    assert!(!dk
+        .verify_encryption(&c, &Integer::from(42), &nonce)
+        .unwrap());
+    let other_nonce = utils::sample_in_mult_group(&mut rng, ek.n());
+    assert!(!dk.verify_encryption(&c, &x, &other_nonce).unwrap());
+    let tampered = ek.oadd(&c, &c).unwrap();
+    assert!(!dk.verify_encryption(&tampered, &x, &nonce).unwrap());
+    // Non-canonical ciphertext
+    let non_canonical = (&c + ek.nn()).complete();
+    assert!(!dk.verify_encryption(&non_canonical, &x, &nonce).unwrap());
+
+    // Invalid nonce
+    assert!(dk.verify_encryption(&c, &x, &Integer::ZERO).is_err());
+}
+
+#[test]
+fn raw_product_matches_oadd() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let mut product = fast_paillier::RawProduct::new();
+    assert!(product.is_empty());
+    assert_eq!(product.finalize(ek).unwrap(), 1);
+
+    let mut expected = ek.oadd_identity();
+    let mut sum = Integer::ZERO;
+    for i in 0..50 {
+        let x = Integer::from(i) - 25;
+        let c = ek.encrypt_with_random(&mut rng, &x).unwrap().ciphertext;
+        product.mul(&c);
+        expected = ek.oadd(&expected, &c).unwrap();
+        sum += x;
+    }
+    assert_eq!(product.len(), 50);
+    assert!(product.bits() > ek.nn().significant_bits());
+
+    let c = product.finalize(ek).unwrap();
+    assert_eq!(c, expected);
+    assert_eq!(dk.decrypt(&c).unwrap(), sum);
+
+    // Invalid ciphertext is detected on finalization
+    product.mul(ek.n());
+    assert!(product.finalize(ek).is_err());
+}
+
+#[test]
+fn generate_strong() {
+    use rug::integer::IsPrime;
+
+    let mut rng = rand_dev::DevRng::new();
+    let dk = DecryptionKey::generate_strong(&mut rng, 512).unwrap();
+    let (p, q) = (dk.p(), dk.q());
+
+    for prime in [p, q] {
+        assert_eq!(prime.significant_bits(), 512);
+        assert_ne!(prime.is_probably_prime(25), IsPrime::No);
+        let half = (prime >> 1u32).complete();
+        assert_ne!(half.is_probably_prime(25), IsPrime::No);
+    }
+    assert_eq!(dk.n().gcd_ref(dk.phi()).complete(), 1);
+    let distance = (p - q).complete().abs();
+    assert!(distance > (Integer::from(1) << (512 - 100)));
+}
+
+#[test]
+fn panicking_rng_aborts() {
+    struct PanickingRng;
+    impl rand_core::RngCore for PanickingRng {
+        fn next_u32(&mut self) -> u32 {
+            panic!("rng failed")
+        }
+        fn next_u64(&mut self) -> u64 {
+            panic!("rng failed")
+        }
+        fn fill_bytes(&mut self, _dest: &mut [u8]) {
+            panic!("rng failed")
+        }
+        fn try_fill_bytes(&mut self, _dest: &mut [u8]) -> Result<(), rand_core::Error> {
+            panic!("rng failed")
+        }
+    }
+
+    // The test re-runs itself in a child process which is expected to abort
+    match std::env::var("FAST_PAILLIER_PANICKING_RNG").as_deref() {
+        Ok("32") => {
+            let mut rng = PanickingRng;
+            #[allow(deprecated)]
+            let mut rng = utils::external_rand(&mut rng);
+            let _ = Integer::from(Integer::random_bits(64, &mut rng));
+            return;
+        }
+        Ok("64") => {
+            let mut rng = utils::ExternalRand64::new(PanickingRng);
+            let _ = Integer::from(Integer::random_bits(64, &mut rng.rand_state()));
+            return;
+        }
+        _ => (),
+    }
+
+    for adapter in ["32", "64"] {
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args(["--exact", "panicking_rng_aborts", "--nocapture"])
+            .env("FAST_PAILLIER_PANICKING_RNG", adapter)
+            .output()
+            .unwrap();
+        assert!(!output.status.success());
+        // Regular test failure results in exit code 101, abort doesn't produce exit code
+        #[cfg(unix)]
+        assert_eq!(output.status.code(), None);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("rng failed"));
+    }
+}
+
+#[test]
+fn ciphertexts_encrypt_equal() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    for (x, y) in [(42, 42), (-42, -42), (0, 0), (42, -42), (1, 2), (0, 1)] {
+        let c1 = ek
+            .encrypt_with_random(&mut rng, &Integer::from(x))
+            .unwrap()
+            .ciphertext;
+        let c2 = ek
+            .encrypt_with_random(&mut rng, &Integer::from(y))
+            .unwrap()
+            .ciphertext;
+        assert_ne!(c1, c2);
+        assert_eq!(dk.ciphertexts_encrypt_equal(&c1, &c2).unwrap(), x == y);
+    }
+
+    let c = ek
+        .encrypt_with_random(&mut rng, Integer::ONE)
+        .unwrap()
+        .ciphertext;
+    assert!(dk.ciphertexts_encrypt_equal(&c, &Integer::ZERO).is_err());
+    assert!(dk.ciphertexts_encrypt_equal(ek.n(), &c).is_err());
+}
+
+#[test]
+fn signed_group_bounds() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    // `N` is odd, so `half_n = (N-1)/2` and signed group is `[-(N-1)/2, (N-1)/2]`
+    assert!(ek.n().is_odd());
+    let half_n = (ek.n() - 1u8).complete() >> 1u32;
+    assert_eq!(*ek.half_n(), half_n);
+    assert_eq!(*ek.neg_half_n(), (-&half_n).complete());
+    assert_eq!(dk.neg_half_n(), ek.neg_half_n());
+
+    assert!(ek.in_signed_group(ek.half_n()));
+    assert!(ek.in_signed_group(ek.neg_half_n()));
+    assert!(!ek.in_signed_group(&(ek.half_n() + 1u8).complete()));
+    assert!(!ek.in_signed_group(&(ek.neg_half_n() - 1u8).complete()));
+
+    // Decryption maps plaintexts to the same range
+    for x in [ek.half_n(), ek.neg_half_n()] {
+        let c = ek.encrypt_with_random(&mut rng, x).unwrap().ciphertext;
+        assert_eq!(dk.decrypt(&c).unwrap(), *x);
+    }
+    assert_eq!(
+        utils::unsigned_to_signed((ek.half_n() + 1u8).complete(), ek.n()),
+        *ek.neg_half_n()
+    );
+}
+
+#[test]
+fn redacted_decryption_key() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+
+    let redacted = dk.public_serialize();
+    for prime in [dk.p(), dk.q()] {
+        // No chunk of the prime is present in redacted serialization
+        let prime = prime.to_digits::<u8>(rug::integer::Order::Msf);
+        for chunk in prime.chunks(8) {
+            assert!(!redacted.windows(chunk.len()).any(|w| w == chunk));
+        }
+    }
+
+    let ek = fast_paillier::EncryptionKey::from_public_serialized(&redacted).unwrap();
+    assert_eq!(ek.n(), dk.n());
+
+    // Plain encryption key encoding is not accepted
+    let bytes = dk.encryption_key().to_bytes();
+    assert!(fast_paillier::EncryptionKey::from_public_serialized(&bytes).is_err());
+    // Decryption key can't be parsed from redacted serialization
+    assert!(DecryptionKey::from_bytes(&redacted).is_err());
+}
+
+#[test]
+fn encryption_fields() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let x = Integer::from(7);
+    let encryption = ek.encrypt_with_random(&mut rng, &x).unwrap();
+    assert_eq!(
+        ek.encrypt_with(&x, &encryption.nonce).unwrap(),
+        encryption.ciphertext
+    );
+
+    let (ciphertext, nonce): (Integer, Integer) = encryption.clone().into();
+    assert_eq!(ciphertext, encryption.ciphertext);
+    assert_eq!(nonce, encryption.nonce);
+}
+
+#[test]
+fn verify_key_pair() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk1 = random_key_for_tests(&mut rng);
+    let dk2 = random_key_for_tests(&mut rng);
+
+    let ek1 = fast_paillier::EncryptionKey::from_n(dk1.n().clone());
+    assert!(fast_paillier::verify_key_pair(&ek1, &dk1));
+    assert!(fast_paillier::verify_key_pair_roundtrip(
+        &mut rng, &ek1, &dk1
+    ));
+
+    assert!(!fast_paillier::verify_key_pair(&ek1, &dk2));
+    assert!(!fast_paillier::verify_key_pair_roundtrip(
+        &mut rng, &ek1, &dk2
+    ));
+    assert!(!fast_paillier::verify_key_pair(dk2.encryption_key(), &dk1));
+    assert!(!fast_paillier::verify_key_pair_roundtrip(
+        &mut rng,
+        dk2.encryption_key(),
+        &dk1
+    ));
+}
+
+#[test]
+fn from_primes_and_n() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let (p, q) = (dk.p().clone(), dk.q().clone());
+
+    let dk2 = DecryptionKey::from_primes_and_n(p.clone(), q.clone(), dk.n().clone()).unwrap();
+    assert!(dk == dk2);
+
+    let Err(err) = DecryptionKey::from_primes_and_n(p, q, (dk.n() + 2u8).complete()) else {
+        panic!("mismatched n must be rejected")
+    };
+    assert_eq!(err.kind(), fast_paillier::ErrorKind::InvalidKey);
+}
+
+#[test]
+fn decrypt_all() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let plaintexts: Vec<_> = (-5..5).map(Integer::from).collect();
+    let mut ciphertexts: Vec<_> = plaintexts
+        .iter()
+        .map(|x| ek.encrypt_with_random(&mut rng, x).unwrap().ciphertext)
+        .collect();
+    assert_eq!(dk.decrypt_all(&ciphertexts).unwrap(), plaintexts);
+    assert!(dk.decrypt_all(&[]).unwrap().is_empty());
+
+    ciphertexts[4] = ek.n().clone();
+    let err = dk.decrypt_all(&ciphertexts).unwrap_err();
+    assert_eq!(err.kind(), fast_paillier::ErrorKind::Decrypt);
+    assert_eq!(err.index(), Some(4));
+
+    // Errors of non-batch operations don't have an index
+    assert_eq!(dk.decrypt(&ciphertexts[4]).unwrap_err().index(), None);
+}
+
+#[test]
+fn boxed_any_keys() {
+    use fast_paillier::AnyEncryptionKey;
+
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key().clone();
+
+    let keys: Vec<Box<dyn AnyEncryptionKey>> = vec![ek.into_any(), dk.clone().into_any()];
+
+    let x = Integer::from(42);
+    let nonce = utils::sample_in_mult_group(&mut rng, dk.n());
+    let expected = dk.encrypt_with(&x, &nonce).unwrap();
+    for key in &keys {
+        assert_eq!(key.n(), dk.n());
+        assert_eq!(key.encrypt_with(&x, &nonce).unwrap(), expected);
+    }
+}
+
+#[test]
+fn encrypt_with_wrap() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let xs = [
+        Integer::from(42),
+        (ek.half_n() + 1u8).complete(),
+        -(ek.half_n() + 1u8).complete(),
+        (ek.nn() * 3u8).complete() + 5,
+        -(ek.n() * 7u8).complete() - 11,
+    ];
+    for x in xs {
+        let nonce = utils::sample_in_mult_group(&mut rng, ek.n());
+        let c = ek.encrypt_with_wrap(&x, &nonce).unwrap();
+        let wrapped = utils::signed_modulo(&x, ek.n());
+        assert!(ek.in_signed_group(&wrapped));
+        assert_eq!(dk.decrypt(&c).unwrap(), wrapped);
+        assert_eq!(c, ek.encrypt_with(&wrapped, &nonce).unwrap());
+    }
+
+    // Strict encryption still rejects out-of-range plaintext
+    let x = (ek.half_n() + 1u8).complete();
+    let nonce = utils::sample_in_mult_group(&mut rng, ek.n());
+    let _: fast_paillier::Error = ek.encrypt_with(&x, &nonce).unwrap_err();
+}
+
+#[test]
+fn types_are_send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<fast_paillier::EncryptionKey>();
+    assert_send_sync::<DecryptionKey>();
+    assert_send_sync::<utils::CrtExp>();
+    assert_send_sync::<utils::Exponent>();
+    assert_send_sync::<fast_paillier::PreparedCiphertexts>();
+    assert_send_sync::<Encryption>();
+    assert_send_sync::<fast_paillier::Error>();
+
+    // Key can actually be used from several threads at once
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ciphertext = dk
+        .encryption_key()
+        .encrypt_with_random(&mut rng, &Integer::from(7))
+        .unwrap()
+        .ciphertext;
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| assert_eq!(dk.decrypt(&ciphertext).unwrap(), 7));
+        }
+    });
+}
+
+#[test]
+fn recovery_bundle() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+
+    let bundle = dk.to_recovery_bundle();
+    let recovered = DecryptionKey::from_recovery_bundle(&bundle).unwrap();
+    assert!(dk == recovered);
+
+    for i in [0, 5, bundle.len() / 2, bundle.len() - 1] {
+        let mut corrupted = bundle.clone();
+        corrupted[i] ^= 0x10;
+        let Err(err) = DecryptionKey::from_recovery_bundle(&corrupted) else {
+            panic!("corruption at byte {i} wasn't detected")
+        };
+        assert_eq!(err.kind(), fast_paillier::ErrorKind::Corrupted);
+    }
+
+    // Truncated bundle
+    let Err(err) = DecryptionKey::from_recovery_bundle(&bundle[..3]) else {
+        panic!("truncated bundle must be rejected")
+    };
+    assert_eq!(err.kind(), fast_paillier::ErrorKind::Corrupted);
+}
+
+#[test]
+fn rsub_plaintext() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let mut pairs: Vec<_> = (0..20)
+        .map(|_| {
+            (
+                utils::random_in_signed_group(&mut rng, ek),
+                utils::random_in_signed_group(&mut rng, ek),
+            )
+        })
+        .collect();
+    pairs.extend([
+        (Integer::from(100), Integer::from(42)),
+        (Integer::ZERO, Integer::from(1)),
+        (ek.half_n().clone(), -ek.half_n().clone()),
+        (-ek.half_n().clone(), ek.half_n().clone()),
+    ]);
+    for (k, m) in pairs {
+        let c = ek.encrypt_with_random(&mut rng, &m).unwrap().ciphertext;
+        let result = ek.rsub_plaintext(&k, &c).unwrap();
+        assert_eq!(
+            dk.decrypt(&result).unwrap(),
+            signed_modulo(&(&k - &m).complete(), ek.n())
+        );
+    }
+
+    // Invalid inputs
+    let c = ek
+        .encrypt_with_random(&mut rng, &Integer::from(1))
+        .unwrap()
+        .ciphertext;
+    let out_of_range = (ek.half_n() + 1u8).complete();
+    let _: fast_paillier::Error = ek.rsub_plaintext(&out_of_range, &c).unwrap_err();
+    let _: fast_paillier::Error = ek
+        .rsub_plaintext(&Integer::from(1), &ek.n().clone())
+        .unwrap_err();
+}
+
+#[test]
+fn self_test() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    dk.self_test(&mut rng).unwrap();
+
+    let imported = DecryptionKey::from_bytes(&dk.to_bytes()).unwrap();
+    imported.self_test(&mut rng).unwrap();
+}
+
+#[test]
+fn random_in_signed_group() {
+    let mut rng = rand_dev::DevRng::new();
+
+    // Tiny key makes it possible to check the distribution
+    let ek = fast_paillier::EncryptionKey::from_n(Integer::from(15));
+    let mut counts = [0u32; 15];
+    for _ in 0..15_000 {
+        let x = utils::random_in_signed_group(&mut rng, &ek);
+        assert!(ek.in_signed_group(&x), "x = {x}");
+        counts[(x + 7u8).to_usize().unwrap()] += 1;
+    }
+    // Each of 15 values is expected to appear ~1000 times
+    assert!(
+        counts.iter().all(|&c| (800..1200).contains(&c)),
+        "{counts:?}"
+    );
+
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+    for _ in 0..100 {
+        assert!(ek.in_signed_group(&utils::random_in_signed_group(&mut rng, ek)));
+    }
+}
+
+#[test]
+fn rerand_nonce_accounting() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let (a, b) = (Integer::from(10), Integer::from(-3));
+    let enc_a = ek.encrypt_with_random(&mut rng, &a).unwrap();
+    let enc_b = ek.encrypt_with_random(&mut rng, &b).unwrap();
+
+    // c = 5 * a
+    let mut nonce = enc_a.nonce.clone();
+    let c = ek
+        .omul_rerand(
+            &mut rng,
+            &Integer::from(5),
+            &enc_a.ciphertext,
+            Some(&mut nonce),
+        )
+        .unwrap();
+    assert_ne!(c.nonce, nonce);
+    assert!(dk
+        .verify_encryption(&c.ciphertext, &Integer::from(50), &nonce)
+        .unwrap());
+
+    // d = c + b
+    nonce = (nonce * &enc_b.nonce) % ek.n();
+    let d = ek
+        .oadd_rerand(&mut rng, &c.ciphertext, &enc_b.ciphertext, Some(&mut nonce))
+        .unwrap();
+    assert!(dk
+        .verify_encryption(&d.ciphertext, &Integer::from(47), &nonce)
+        .unwrap());
+
+    // e = -7 * d
+    let e = ek
+        .omul_rerand(
+            &mut rng,
+            &Integer::from(-7),
+            &d.ciphertext,
+            Some(&mut nonce),
+        )
+        .unwrap();
+    assert!(dk
+        .verify_encryption(&e.ciphertext, &Integer::from(-329), &nonce)
+        .unwrap());
+    assert_eq!(dk.decrypt(&e.ciphertext).unwrap(), -329);
+
+    // Without accounting, only the fresh nonce is returned
+    let f = ek
+        .oadd_rerand(&mut rng, &enc_a.ciphertext, &enc_b.ciphertext, None)
+        .unwrap();
+    let expected_nonce = (&enc_a.nonce * &enc_b.nonce).complete() * &f.nonce % ek.n();
+    assert!(dk
+        .verify_encryption(&f.ciphertext, &(&a + &b).complete(), &expected_nonce)
+        .unwrap());
+}
+
+#[test]
+fn encode_message() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let mut plaintexts: Vec<_> = (0..20)
+        .map(|_| utils::random_in_signed_group(&mut rng, ek))
+        .collect();
+    plaintexts.extend([-ek.half_n().clone(), Integer::ZERO, ek.half_n().clone()]);
+    for x in plaintexts {
+        let nonce = utils::sample_in_mult_group(&mut rng, ek.n());
+        let nonce_pow_n = nonce.clone().pow_mod(ek.n(), ek.nn()).unwrap();
+        let c = ek.encode_message(&x).unwrap() * nonce_pow_n % ek.nn();
+        assert_eq!(c, ek.encrypt_with(&x, &nonce).unwrap());
+    }
+
+    let _: fast_paillier::Error = ek
+        .encode_message(&(ek.half_n() + 1u8).complete())
+        .unwrap_err();
+}
+
+#[test]
+fn error_source_chain() {
+    use std::error::Error as _;
+
+    // Factors are distinct, but not coprime, so CRT can not be built
+    let Err(err) = DecryptionKey::from_multiprime(vec![Integer::from(9), Integer::from(15)]) else {
+        panic!("key must be rejected")
+    };
+    assert_eq!(err.kind(), fast_paillier::ErrorKind::Internal);
+    let source = err.source().expect("error must have a source");
+    assert_eq!(source.to_string(), "factors are not coprime");
+    assert!(source.source().is_none());
+
+    // Leaf errors have no source
+    let err = fast_paillier::EncryptionKey::from_bytes(&[]).unwrap_err();
+    assert!(err.source().is_none());
+}
+
+#[test]
+fn nonce_power() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    for _ in 0..20 {
+        let x = utils::random_in_signed_group(&mut rng, ek);
+        let nonce = utils::sample_in_mult_group(&mut rng, ek.n());
+        let nonce_pow_n = ek.nonce_power(&nonce).unwrap();
+        assert_eq!(dk.nonce_power(&nonce).unwrap(), nonce_pow_n);
+
+        let c = ek.encode_message(&x).unwrap() * nonce_pow_n % ek.nn();
+        assert_eq!(c, ek.encrypt_with(&x, &nonce).unwrap());
+    }
+
+    // Nonce must be in Z*_n
+    for nonce in [
+        Integer::ZERO,
+        ek.n().clone(),
+        dk.p().clone(),
+        Integer::from(-1),
+    ] {
+        let _: fast_paillier::Error = ek.nonce_power(&nonce).unwrap_err();
+        let _: fast_paillier::Error = dk.nonce_power(&nonce).unwrap_err();
+    }
+}
+
+#[cfg(feature = "deterministic-encryption")]
+#[test]
+fn encrypt_deterministic() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    for x in [
+        Integer::from(42),
+        Integer::from(-42),
+        Integer::ZERO,
+        ek.half_n().clone(),
+        -ek.half_n().clone(),
+    ] {
+        let c = ek.encrypt_deterministic(&x, b"test").unwrap();
+        assert_eq!(c, ek.encrypt_deterministic(&x, b"test").unwrap());
+        assert_eq!(dk.decrypt(&c).unwrap(), x);
+
+        // Nonce depends on the domain
+        assert_ne!(c, ek.encrypt_deterministic(&x, b"other").unwrap());
+    }
+
+    let out_of_range = (ek.half_n() + 1u8).complete();
+    let _: fast_paillier::Error = ek
+        .encrypt_deterministic(&out_of_range, b"test")
+        .unwrap_err();
+}
+
+#[test]
+fn lazy_decryption_key() {
+    let mut rng = rand_dev::DevRng::new();
+    let eager = random_key_for_tests(&mut rng);
+    let lazy = DecryptionKey::from_primes_lazy(eager.p().clone(), eager.q().clone()).unwrap();
+    assert!(lazy == eager);
+
+    for _ in 0..10 {
+        let x = utils::random_in_signed_group(&mut rng, eager.encryption_key());
+        let Encryption { ciphertext, nonce } = lazy.encrypt_with_random(&mut rng, &x).unwrap();
+        assert_eq!(eager.encrypt_with(&x, &nonce).unwrap(), ciphertext);
+        assert_eq!(lazy.decrypt(&ciphertext).unwrap(), x);
+        assert_eq!(eager.decrypt(&ciphertext).unwrap(), x);
+        assert_eq!(
+            lazy.decrypt_uniform(&ciphertext).unwrap(),
+            eager.decrypt_uniform(&ciphertext).unwrap()
+        );
+    }
+    assert_eq!(lazy.mu(), eager.mu());
+
+    // Invalid primes are rejected by lazy constructor as well
+    assert!(DecryptionKey::from_primes_lazy(eager.p().clone(), eager.p().clone()).is_err());
+}
+
+#[test]
+fn in_signed_group_matches_two_sided_check() {
+    let mut rng = rand_dev::DevRng::new();
+
+    // Reference implementation: `-floor(N/2) <= x <= floor((N-1)/2)`
+    let reference = |ek: &fast_paillier::EncryptionKey, x: &Integer| {
+        let lower = -(ek.n() >> 1u32).complete();
+        let upper = (ek.n() - 1u8).complete() >> 1u32;
+        lower <= *x && *x <= upper
+    };
+
+    let dk = random_key_for_tests(&mut rng);
+    let mut keys = vec![dk.encryption_key().clone()];
+    keys.extend([1, 2, 3, 15, 16, 17, 100].map(|n| fast_paillier::EncryptionKey::from_n(n.into())));
+
+    for ek in &keys {
+        let half_n = ek.half_n().clone();
+        let mut xs = vec![Integer::ZERO, Integer::from(1), Integer::from(-1)];
+        for delta in -2..=2 {
+            for base in [
+                half_n.clone(),
+                -half_n.clone(),
+                ek.n().clone(),
+                -ek.n().clone(),
+            ] {
+                xs.push(base + delta);
+            }
+        }
+        for _ in 0..100 {
+            let x = (ek.n() * 4u8)
+                .complete()
+                .random_below(&mut utils::ExternalRand64::new(&mut rng).rand_state());
+            xs.push(x - ek.n() * 2u8);
+        }
+        for x in xs {
+            assert_eq!(
+                ek.in_signed_group(&x),
+                reference(ek, &x),
+                "n = {}, x = {x}",
+                ek.n()
+            );
+        }
+    }
+}
+
+#[cfg(feature = "deterministic-encryption")]
+#[test]
+fn deterministic_cache() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let mut cache = ek.deterministic_cache(2);
+    assert!(cache.is_empty());
+    assert_eq!(cache.capacity(), 2);
+
+    let (a, b, c) = (Integer::from(1), Integer::from(2), Integer::from(3));
+    let enc_a = cache.encrypt(&a, b"const").unwrap();
+    assert_eq!(enc_a, ek.encrypt_deterministic(&a, b"const").unwrap());
+    assert_eq!(cache.computed(), 1);
+
+    // Cache hit
+    assert_eq!(cache.encrypt(&a, b"const").unwrap(), enc_a);
+    assert_eq!(cache.computed(), 1);
+
+    // Domain is a part of the cache key
+    let enc_a2 = cache.encrypt(&a, b"other").unwrap();
+    assert_ne!(enc_a2, enc_a);
+    assert_eq!(cache.computed(), 2);
+    assert_eq!(cache.len(), 2);
+
+    // Cache is full, the oldest entry `(a, "const")` is evicted
+    let enc_c = cache.encrypt(&c, b"const").unwrap();
+    assert_eq!(dk.decrypt(&enc_c).unwrap(), c);
+    assert_eq!(cache.computed(), 3);
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.encrypt(&a, b"const").unwrap(), enc_a);
+    assert_eq!(cache.computed(), 4);
+
+    // Zero capacity cache never stores anything
+    let mut cache = ek.deterministic_cache(0);
+    let enc_b = cache.encrypt(&b, b"const").unwrap();
+    assert_eq!(cache.encrypt(&b, b"const").unwrap(), enc_b);
+    assert_eq!(cache.computed(), 2);
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn encryption_key_from_primes() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+
+    let ek = fast_paillier::EncryptionKey::from_primes(dk.p(), dk.q());
+    let expected = DecryptionKey::from_primes(dk.p().clone(), dk.q().clone()).unwrap();
+    assert_eq!(ek.n(), expected.encryption_key().n());
+    assert_eq!(ek.nn(), expected.encryption_key().nn());
+    assert!(fast_paillier::verify_key_pair(&ek, &expected));
+}
+
+#[test]
+fn sum_accumulator_rerandomization() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let ciphertexts: Vec<_> = (1..=10)
+        .map(|x| {
+            ek.encrypt_with_random(&mut rng, &Integer::from(x))
+                .unwrap()
+                .ciphertext
+        })
+        .collect();
+
+    let mut plain = fast_paillier::SumAccumulator::new(ek);
+    let mut rerand = fast_paillier::SumAccumulator::new(ek).rerandomize_every(3, rng.fork());
+    for (i, c) in ciphertexts.iter().enumerate() {
+        plain.push(c).unwrap();
+        rerand.push(c).unwrap();
+        assert_eq!(rerand.rerandomizations(), (i as u64 + 1) / 3);
+    }
+    assert_eq!(plain.rerandomizations(), 0);
+
+    let (plain, rerand) = (plain.finish(), rerand.finish());
+    assert_ne!(plain, rerand);
+    assert_eq!(dk.decrypt(&plain).unwrap(), 55);
+    assert_eq!(dk.decrypt(&rerand).unwrap(), 55);
+
+    // `n = 0` disables re-randomization
+    let mut never = fast_paillier::SumAccumulator::new(ek).rerandomize_every(0, rng.fork());
+    for c in &ciphertexts {
+        never.push(c).unwrap();
+    }
+    assert_eq!(never.rerandomizations(), 0);
+    assert_eq!(never.finish(), plain);
+}
+
+#[test]
+fn is_safe_prime() {
+    let mut rng = rand_dev::DevRng::new();
+
+    // Known safe primes
+    for p in [5, 7, 11, 23, 47, 59, 83, 107] {
+        assert!(utils::is_safe_prime(&Integer::from(p), 25), "p = {p}");
+    }
+    let dk = random_key_for_tests(&mut rng);
+    assert!(utils::is_safe_prime(dk.p(), 25));
+    assert!(utils::is_safe_prime(dk.q(), 25));
+    // Primes used in benchmarks are the 1536-bit ones
+    #[cfg(feature = "test-vectors")]
+    {
+        use fast_paillier::test_vectors;
+        for (p, q) in [
+            test_vectors::safe_primes_512(),
+            test_vectors::safe_primes_1024(),
+            test_vectors::safe_primes_1536(),
+        ] {
+            assert!(utils::is_safe_prime(&p, 25));
+            assert!(utils::is_safe_prime(&q, 25));
+        }
+    }
+
+    // Primes that are not safe
+    for p in [2, 3, 13, 17, 29, 31, 37, 41] {
+        assert!(!utils::is_safe_prime(&Integer::from(p), 25), "p = {p}");
+    }
+    let prime = Integer::from(Integer::random_bits(
+        512,
+        &mut utils::ExternalRand64::new(&mut rng).rand_state(),
+    ))
+    .next_prime();
+    let prime_is_safe =
+        (&prime >> 1u32).complete().is_probably_prime(25) != rug::integer::IsPrime::No;
+    assert_eq!(utils::is_safe_prime(&prime, 25), prime_is_safe);
+
+    // Composites
+    for n in [-7, 0, 1, 4, 9, 15, 21, 1_000_001] {
+        assert!(!utils::is_safe_prime(&Integer::from(n), 25), "n = {n}");
+    }
+    assert!(!utils::is_safe_prime(&(dk.p() * dk.q()).complete(), 25));
+}
+
+#[test]
+fn decryption_key_omul_rerand() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    for _ in 0..10 {
+        let m = utils::random_in_signed_group(&mut rng, ek);
+        let scalar = utils::random_in_signed_group(&mut rng, ek);
+        let enc = ek.encrypt_with_random(&mut rng, &m).unwrap();
+
+        let mut nonce = enc.nonce.clone();
+        let out1 = dk
+            .omul_rerand(&mut rng, &scalar, &enc.ciphertext, Some(&mut nonce))
+            .unwrap();
+        let out2 = dk
+            .omul_rerand(&mut rng, &scalar, &enc.ciphertext, None)
+            .unwrap();
+        assert_ne!(out1.ciphertext, out2.ciphertext);
+
+        let expected = signed_modulo(&(&scalar * &m).complete(), ek.n());
+        assert_eq!(dk.decrypt(&out1.ciphertext).unwrap(), expected);
+        assert_eq!(dk.decrypt(&out2.ciphertext).unwrap(), expected);
+        assert!(dk
+            .verify_encryption(&out1.ciphertext, &expected, &nonce)
+            .unwrap());
+
+        // Output is the same as re-randomizing `omul` output with the returned nonce
+        let rerandomized = ek
+            .oadd(
+                &ek.omul(&scalar, &enc.ciphertext).unwrap(),
+                &ek.encrypt_with(&Integer::ZERO, &out2.nonce).unwrap(),
+            )
+            .unwrap();
+        assert_eq!(rerandomized, out2.ciphertext);
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_json_human_readable() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+    let [p, q] = dk.primes() else {
+        panic!("two-prime key expected")
+    };
+
+    let json = serde_json::to_value(ek).unwrap();
+    assert_eq!(json, serde_json::json!({ "n": ek.n().to_string_radix(16) }));
+    let ek2: fast_paillier::EncryptionKey = serde_json::from_value(json).unwrap();
+    assert_eq!(ek2.n(), ek.n());
+
+    let json = serde_json::to_value(&dk).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "p": p.to_string_radix(16),
+            "q": q.to_string_radix(16),
+        })
+    );
+    let dk2: DecryptionKey = serde_json::from_value(json).unwrap();
+    assert!(dk == dk2);
+
+    // Malformed inputs
+    for json in [
+        serde_json::json!({ "n": "-7" }),
+        serde_json::json!({ "n": "0x7" }),
+        serde_json::json!({ "n": "" }),
+        serde_json::json!({ "n": "8" }),
+        serde_json::json!({ "n": "7", "m": "7" }),
+    ] {
+        assert!(serde_json::from_value::<fast_paillier::EncryptionKey>(json).is_err());
+    }
+    let json = serde_json::json!({ "p": p.to_string_radix(16) });
+    assert!(serde_json::from_value::<DecryptionKey>(json).is_err());
+
+    // Multi-prime keys
+    let primes: Vec<_> = (0..3)
+        .map(|_| utils::generate_safe_prime(&mut rng, 128))
+        .collect();
+    let dk = DecryptionKey::from_multiprime(primes.clone()).unwrap();
+    let json = serde_json::to_value(&dk).unwrap();
+    let mut sorted = primes.clone();
+    sorted.sort();
+    let expected: Vec<_> = sorted.iter().map(|p| p.to_string_radix(16)).collect();
+    assert_eq!(json, serde_json::json!({ "primes": expected }));
+    let dk2: DecryptionKey = serde_json::from_value(json).unwrap();
+    assert!(dk == dk2);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_json_legacy_format() {
+    // Keys serialized by releases preceding hex representation, `p = 2^127 - 1`, `q = 2^89 - 1`
+    const LEGACY_EK: &str =
+        r#"{"radix":16,"value":"ffffffffffffffffffffff7ffffffffe0000000000000000000001"}"#;
+    const LEGACY_DK: &str = r#"[{"radix":16,"value":"1ffffffffffffffffffffff"},{"radix":16,"value":"7fffffffffffffffffffffffffffffff"}]"#;
+    let p = (Integer::from(1) << 127u32) - 1u8;
+    let q = (Integer::from(1) << 89u32) - 1u8;
+    let n = (&p * &q).complete();
+
+    let ek: fast_paillier::EncryptionKey = serde_json::from_str(LEGACY_EK).unwrap();
+    assert_eq!(*ek.n(), n);
+    let dk: DecryptionKey = serde_json::from_str(LEGACY_DK).unwrap();
+    assert!(dk == DecryptionKey::from_primes(p, q).unwrap());
+
+    // Legacy `N` is validated as well
+    let even_n = r#"{"radix":16,"value":"8"}"#;
+    assert!(serde_json::from_str::<fast_paillier::EncryptionKey>(even_n).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn error_kind_serde_roundtrip() {
+    use fast_paillier::ErrorKind;
+
+    let kinds = [
+        ErrorKind::InvalidKey,
+        ErrorKind::InvalidEncoding,
+        ErrorKind::Corrupted,
+        ErrorKind::Encrypt,
+        ErrorKind::Decrypt,
+        ErrorKind::Ops,
+        ErrorKind::Internal,
+    ];
+    for kind in kinds {
+        let json = serde_json::to_string(&kind).unwrap();
+        let kind2: ErrorKind = serde_json::from_str(&json).unwrap();
+        assert_eq!(kind, kind2);
+    }
+    assert_eq!(
+        serde_json::to_string(&ErrorKind::Decrypt).unwrap(),
+        "\"Decrypt\""
+    );
+
+    // Kind of an actual error can be sent over the wire
+    let ek = fast_paillier::EncryptionKey::from_n(Integer::from(15));
+    let err = ek.oadd(&Integer::ZERO, Integer::ONE).unwrap_err();
+    let json = serde_json::to_string(&err.kind()).unwrap();
+    assert_eq!(
+        serde_json::from_str::<ErrorKind>(&json).unwrap(),
+        ErrorKind::Ops
+    );
+}
+
+#[cfg(feature = "keygen-proof")]
+#[test]
+fn keygen_correctness_proof() {
+    use fast_paillier::{EncryptionKey, KeyGenProof};
+
+    let mut rng = rand_dev::DevRng::new();
+    let (dk, proof) = DecryptionKey::generate_with_correctness_proof(&mut rng, 256).unwrap();
+    let ek = dk.encryption_key();
+    assert!(proof.verify(ek));
+
+    // Proof for an existing key
+    let proof2 = KeyGenProof::prove(&mut rng, &dk).unwrap();
+    assert!(proof2.verify(ek));
+
+    // Tampered `N`
+    for tampered in [
+        (ek.n() + 2u8).complete(),
+        (ek.n() * 3u8).complete(),
+        random_key_for_tests(&mut rng).n().clone(),
+    ] {
+        assert!(!proof.verify(&EncryptionKey::from_n(tampered)));
+    }
+
+    // Key that is not a Paillier-Blum modulus: `p` is congruent to 1 modulo 4
+    let mut p = Integer::from(1) << 127u32;
+    while p.mod_u(4) != 1 {
+        p.next_prime_mut();
+    }
+    let q = utils::generate_safe_prime(&mut rng, 128);
+    let dk = DecryptionKey::from_primes(p, q).unwrap();
+    let err = KeyGenProof::prove(&mut rng, &dk).unwrap_err();
+    assert_eq!(err.kind(), fast_paillier::ErrorKind::InvalidKey);
+}
+
+#[cfg(feature = "postcard")]
+#[test]
+fn postcard_roundtrip() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let ek = dk.encryption_key();
+
+    let bytes = ek.to_postcard();
+    assert_eq!(bytes[0], 1);
+    let ek2 = fast_paillier::EncryptionKey::from_postcard(&bytes).unwrap();
+    assert_eq!(ek.n(), ek2.n());
+
+    let bytes = dk.to_postcard();
+    assert_eq!(bytes[0], 1);
+    let dk2 = DecryptionKey::from_postcard(&bytes).unwrap();
+    assert!(dk == dk2);
+
+    // Trailing bytes are not allowed
+    let mut bytes = dk.to_postcard();
+    bytes.push(0);
+    assert!(DecryptionKey::from_postcard(&bytes).is_err());
+    assert!(fast_paillier::EncryptionKey::from_postcard(&[]).is_err());
+}
+
+#[cfg(feature = "postcard")]
+#[test]
+fn binary_serde_validates_n() {
+    for n in [
+        Integer::ZERO,
+        Integer::from(1),
+        Integer::from(8),
+        Integer::from(-7),
+    ] {
+        let bytes = postcard::to_allocvec(&n).unwrap();
+        assert!(postcard::from_bytes::<fast_paillier::EncryptionKey>(&bytes).is_err());
+    }
+    let bytes = postcard::to_allocvec(&Integer::from(15)).unwrap();
+    let ek: fast_paillier::EncryptionKey = postcard::from_bytes(&bytes).unwrap();
+    assert_eq!(*ek.n(), 15);
+}
+
+#[cfg(feature = "postcard")]
+#[test]
+fn binary_serde_is_backward_compatible() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+    let (p, q) = (dk.p().clone(), dk.q().clone());
+
+    // Two-prime key is encoded as a tuple `(p, q)`, same as in the first release
+    let legacy = postcard::to_allocvec(&(&p, &q)).unwrap();
+    assert_eq!(postcard::to_allocvec(&dk).unwrap(), legacy);
+    let dk2: DecryptionKey = postcard::from_bytes(&legacy).unwrap();
+    assert!(dk == dk2);
+    let swapped: DecryptionKey =
+        postcard::from_bytes(&postcard::to_allocvec(&(&q, &p)).unwrap()).unwrap();
+    assert!(dk == swapped);
+
+    // Multi-prime key uses a versioned shape
+    let primes: Vec<_> = (0..3)
+        .map(|_| utils::generate_safe_prime(&mut rng, 128))
+        .collect();
+    let multiprime = DecryptionKey::from_multiprime(primes).unwrap();
+    let bytes = postcard::to_allocvec(&multiprime).unwrap();
+    assert_eq!(
+        bytes,
+        postcard::to_allocvec(&(Integer::from(-1), multiprime.primes())).unwrap()
+    );
+    let multiprime2: DecryptionKey = postcard::from_bytes(&bytes).unwrap();
+    assert!(multiprime == multiprime2);
+    let unknown = postcard::to_allocvec(&(Integer::from(-2), multiprime.primes())).unwrap();
+    assert!(postcard::from_bytes::<DecryptionKey>(&unknown).is_err());
+
+    // Two-factor `CrtExp` and `Exponent` keep the shape of the first release
+    let crt = utils::CrtExp::build_nn(&p, &q).unwrap();
+    let (pp, qq) = (p.square_ref().complete(), q.square_ref().complete());
+    let phi_pp = (&pp - &p).complete();
+    let phi_qq = (&qq - &q).complete();
+    let beta = Integer::from(pp.invert_ref(&qq).unwrap());
+    let nn = (&pp * &qq).complete();
+    let legacy_crt = postcard::to_allocvec(&(&nn, &pp, &phi_pp, &qq, &phi_qq, &beta)).unwrap();
+    assert_eq!(postcard::to_allocvec(&crt).unwrap(), legacy_crt);
+    let json = serde_json::json!({
+        "n": nn, "n1": pp, "phi_n1": phi_pp, "n2": qq, "phi_n2": phi_qq, "beta": beta,
+    });
+    assert_eq!(serde_json::to_value(&crt).unwrap(), json);
+
+    let e = Integer::from(-12345);
+    let exponent = crt.prepare_exponent(&e);
+    let abs_e = e.clone().abs();
+    let legacy_exponent = postcard::to_allocvec(&(
+        (&abs_e % &phi_pp).complete(),
+        (&abs_e % &phi_qq).complete(),
+        true,
+    ))
+    .unwrap();
+    assert_eq!(postcard::to_allocvec(&exponent).unwrap(), legacy_exponent);
+
+    let x = utils::sample_in_mult_group(&mut rng, &nn);
+    let expected = crt.exp(&x, &exponent).unwrap();
+    let crt2: utils::CrtExp = postcard::from_bytes(&legacy_crt).unwrap();
+    let exponent2: utils::Exponent = postcard::from_bytes(&legacy_exponent).unwrap();
+    assert_eq!(crt2.exp(&x, &exponent2).unwrap(), expected);
+    let crt2: utils::CrtExp = serde_json::from_value(json).unwrap();
+    let exponent2: utils::Exponent =
+        serde_json::from_value(serde_json::to_value(&exponent).unwrap()).unwrap();
+    assert_eq!(crt2.exp(&x, &exponent2).unwrap(), expected);
+
+    // Multi-factor `CrtExp` and `Exponent` round trip
+    let crt = utils::CrtExp::build_nn_from_primes(multiprime.primes()).unwrap();
+    let exponent = crt.prepare_exponent(&e);
+    let x = utils::sample_in_mult_group(&mut rng, crt.modulus());
+    let expected = crt.exp(&x, &exponent).unwrap();
+    let crt2: utils::CrtExp = postcard::from_bytes(&postcard::to_allocvec(&crt).unwrap()).unwrap();
+    let exponent2: utils::Exponent =
+        postcard::from_bytes(&postcard::to_allocvec(&exponent).unwrap()).unwrap();
+    assert_eq!(crt2.exp(&x, &exponent2).unwrap(), expected);
+    let crt2: utils::CrtExp = serde_json::from_value(serde_json::to_value(&crt).unwrap()).unwrap();
+    let exponent2: utils::Exponent =
+        serde_json::from_value(serde_json::to_value(&exponent).unwrap()).unwrap();
+    assert_eq!(crt2.exp(&x, &exponent2).unwrap(), expected);
+
+    // Two-factor exponent can't be used with multi-factor CRT
+    let two_factor: utils::Exponent = postcard::from_bytes(&legacy_exponent).unwrap();
+    assert!(crt.exp(&x, &two_factor).is_none());
+}
+
+#[cfg(feature = "postcard")]
+#[test]
+fn postcard_rejects_unknown_version() {
+    let mut rng = rand_dev::DevRng::new();
+    let dk = random_key_for_tests(&mut rng);
+
+    let mut bytes = dk.encryption_key().to_postcard();
+    bytes[0] = 2;
+    let err = fast_paillier::EncryptionKey::from_postcard(&bytes).unwrap_err();
+    assert_eq!(err.kind(), fast_paillier::ErrorKind::InvalidEncoding);
+
+    let mut bytes = dk.to_postcard();
+    bytes[0] = 0;
+    let err = DecryptionKey::from_postcard(&bytes).err().unwrap();
+    assert_eq!(err.kind(), fast_paillier::ErrorKind::InvalidEncoding);
+}
+
 /// Takes `x mod n` and maps result to `{-N/2, .., N/2}`
 fn signed_modulo(x: &Integer, n: &Integer) -> Integer {
     let x = x.modulo_ref(n).complete();